@@ -0,0 +1,491 @@
+// Command line interface
+//
+// ```
+// aktis <url>                          browse from the page on a terminal (print it as Markdown otherwise),
+//                                      or offer to save files other than text
+//                                      (credentials: `user:pass@`, ~/.netrc or a prompt;
+//                                      Basic only with $AKTIS_INSECURE_BASIC set, as there is no TLS)
+// aktis <url> -w <cols>                print it wrapped to a width (the terminal's by default, none in a pipe),
+//                                      styled with escape sequences on a terminal unless $NO_COLOR is set
+//                                      (only printed pages are styled: the full-screen view shows Markdown)
+// aktis <url> -f <format>              print it as markdown, plain (like `lynx -dump`), ansi or json (an outline)
+// aktis download <url> -o <file>       save the body to a file (run again to resume)
+// aktis bookmarks [list [folder]]      list the bookmarks numbered from 1
+// aktis bookmarks add <url> [title] [-f folder] [-t tag]...
+// aktis bookmarks remove <url|n>
+// aktis bookmarks search <words>       `#tag` only matches tags
+// aktis bookmarks import <file>        read a Netscape bookmark file exported by other browsers
+// aktis bookmarks export [file]        write one (to stdout without a file)
+// aktis history [words]                the pages visited, filtered by the words
+// aktis dns <name> [type] [@server]    query name servers like dig(1)
+// ```
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Instant;
+use aktis::dns::RecordType;
+use aktis::dns::conf::{Hosts, ResolvConf};
+use aktis::dns::resolver::Resolver;
+use aktis::http::auth::Netrc;
+use aktis::http::client::{Body, Client};
+use aktis::http::download::{self, Download, Progress};
+use aktis::http::response::ResponseHead;
+use aktis::http::url::URL;
+use aktis::renderer::dom::{Element, Node};
+use aktis::renderer::{ansi_generator, json_generator, layout, plain_generator};
+use aktis::utils::progress::{self, ProgressBar};
+use aktis::utils::term;
+use crate::profile::{self, history};
+use crate::profile::bookmarks::{Bookmark, Bookmarks};
+use crate::ui::{self, Document, Fetched};
+
+const USAGE: &str = "\
+usage: aktis <url> [-w cols] [-f markdown|plain|ansi|json]
+       aktis download <url> -o <file>
+       aktis bookmarks [list [folder] | add <url> [title] [-f folder] [-t tag]... | remove <url|n>
+                       | search <words> | import <file> | export [file]]
+       aktis history [words]
+       aktis dns <name> [type] [@server]";
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum CliError {
+    Usage,
+    InvalidType(String),
+    InvalidServer(String),
+    NotText(String),
+    NoProfile,
+    NoBookmark(String),
+    InvalidWidth(String),
+    InvalidFormat(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage => write!(f, "{}", USAGE),
+            Self::InvalidType(s) => write!(f, "CLI error: unknown record type `{}`", s),
+            Self::InvalidServer(s) => write!(f, "CLI error: invalid name server `{}`", s),
+            Self::NotText(s) => write!(f, "CLI error: `{}` is not text; save it with `aktis download`", s),
+            Self::NoProfile => write!(f, "CLI error: no directory for bookmarks and history; set $AKTIS_HOME"),
+            Self::NoBookmark(s) => write!(f, "CLI error: no bookmark `{}`", s),
+            Self::InvalidWidth(s) => write!(f, "CLI error: invalid width `{}`", s),
+            Self::InvalidFormat(s) => write!(f, "CLI error: unknown format `{}` (markdown, plain, ansi or json)", s),
+        }
+    }
+}
+
+impl Error for CliError {}
+// ------------------------------------
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args.first().map(|s| s.as_str()) {
+        None | Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some("download") => download(&args[1..]),
+        Some("dns") => dns(&args[1..]),
+        Some("bookmarks") => bookmarks(&args[1..]),
+        Some("history") => history(&args[1..]),
+        Some(_) => browse(args),
+    }
+}
+
+// browse (or print) the page, or save the body if it is not meant to be displayed
+fn browse(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (url, width, format) = parse_browse_args(args)?;
+    let mut client = client();
+    let tty = term::is_tty(term::STDOUT);
+    match ui::fetch(&mut client, URL::parse(&url)?)? {
+        Fetched::File(url, head, mut body) => save(&url.to_string(), &head, &mut body),
+        Fetched::Page(url, document) if width.is_none() && format.is_none() && tty && term::is_tty(term::STDIN) => {
+            ui::run(client.cookie_jar().clone(), quiet_client, url, document.page())
+        }
+        Fetched::Page(_, document) => {
+            let format = format.unwrap_or(if tty && color() { Format::Ansi } else { Format::Markdown });
+            let width = width.or_else(|| if tty { Some(term::size().0) } else { None });
+            for line in render(&document, format, width) {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Markdown,
+    Plain,
+    Ansi,
+    Json,
+}
+
+// the lines of `document` in `format`, wrapped to `width` if given (80 columns for plain text and
+// ANSI otherwise)
+fn render(document: &Document, format: Format, width: Option<usize>) -> Vec<String> {
+    let json = |html: &Element| json_generator::generate(html).lines().map(|l| l.to_string()).collect();
+    match (document, format) {
+        (Document::Html(html), Format::Plain) => plain_generator::generate(html, width.unwrap_or(80)),
+        (Document::Html(html), Format::Ansi) => ansi_generator::generate(html, width.unwrap_or(80)),
+        (Document::Html(html), Format::Json) => json(html),
+        // text is shown as it is, but a JSON dump has it as a code block
+        (Document::Text(text), Format::Json) => {
+            let mut pre = Element::new("pre", vec![]);
+            pre.push(Node::Text(text.to_string()));
+            let mut html = Element::new("#document", vec![]);
+            html.push(Node::Element(pre));
+            json(&html)
+        }
+        _ => match width {
+            Some(width) => layout::layout(&document.page().lines(), width),
+            None => document.page().lines(),
+        },
+    }
+}
+
+// `<url> [-w cols] [-f format]` in any order
+fn parse_browse_args(args: &[String]) -> Result<(String, Option<usize>, Option<Format>), CliError> {
+    let mut url = None;
+    let mut width = None;
+    let mut format = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-w" | "--width" => {
+                let cols = args.next().ok_or(CliError::Usage)?;
+                match cols.parse::<usize>() {
+                    Ok(n) if n > 0 => width = Some(n),
+                    _ => return Err(CliError::InvalidWidth(cols.to_string())),
+                }
+            }
+            "-f" | "--format" => {
+                format = Some(match args.next().ok_or(CliError::Usage)?.as_str() {
+                    "markdown" | "md" => Format::Markdown,
+                    "plain" | "text" => Format::Plain,
+                    "ansi" => Format::Ansi,
+                    "json" => Format::Json,
+                    s => return Err(CliError::InvalidFormat(s.to_string())),
+                })
+            }
+            _ if url.is_none() => url = Some(arg.to_string()),
+            _ => return Err(CliError::Usage),
+        }
+    }
+    url.map(|url| (url, width, format)).ok_or(CliError::Usage)
+}
+
+// whether escape sequences may color the output (ref: https://no-color.org/)
+fn color() -> bool {
+    env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+// ask whether to save the body, then stream it into a file in the current directory
+fn save(url: &str, head: &ResponseHead, body: &mut Body) -> Result<(), Box<dyn Error>> {
+    let headers = head.headers();
+    let media_type = headers
+        .content_type()
+        .map_or("application/octet-stream".to_string(), |m| m.essence());
+    if !term::is_tty(term::STDIN) {
+        return Err(Box::new(CliError::NotText(media_type)));
+    }
+    let path = unique_path(&download::filename(headers, &URL::parse(url)?));
+    let size = headers.content_length().map(|n| format!(", {}", progress::format_bytes(n))).unwrap_or_default();
+    let answer = term::read_line(&format!("{}{}: save as {}? [Y/n] ", media_type, size, path.display()))?;
+    if answer.trim().to_ascii_lowercase().starts_with('n') {
+        return Ok(());
+    }
+
+    let size = Download::new(url, &path).save(head, body, &mut progress_bar())?;
+    eprintln!("saved {} bytes to {}", size, path.display());
+    Ok(())
+}
+
+// draw a progress bar if stdout is a terminal
+fn progress_bar() -> impl FnMut(&Progress) {
+    let mut bar: Option<ProgressBar<io::Stdout>> = None;
+    let tty = term::is_tty(term::STDOUT);
+    move |progress: &Progress| {
+        if !tty {
+            return;
+        }
+        let bar = bar.get_or_insert_with(|| ProgressBar::new(io::stdout(), progress.offset()));
+        let _ = bar.update(progress.received(), progress.total());
+        if progress.total() == Some(progress.received()) {
+            let _ = bar.finish();
+        }
+    }
+}
+
+// `name`, or `name.1`, `name.2` ... if it exists
+fn unique_path(name: &str) -> PathBuf {
+    let mut path = PathBuf::from(name);
+    let mut n = 1;
+    while path.exists() {
+        path = PathBuf::from(format!("{}.{}", name, n));
+        n += 1;
+    }
+    path
+}
+
+// a client taking credentials from `~/.netrc`
+// (sent with `Basic` in the clear only if $AKTIS_INSECURE_BASIC is set)
+fn quiet_client() -> Client {
+    let mut client = Client::new();
+    client.set_netrc(Some(Netrc::system()));
+    client.set_insecure_basic(env::var_os("AKTIS_INSECURE_BASIC").is_some_and(|v| !v.is_empty()));
+    client
+}
+
+// `quiet_client` asking for the other credentials on the terminal
+fn client() -> Client {
+    let mut client = quiet_client();
+    if term::is_tty(term::STDIN) {
+        client.set_prompt(Some(Box::new(|url: &URL, realm: Option<&str>| {
+            let realm = realm.map(|r| format!(" ({})", r)).unwrap_or_default();
+            let user = term::read_line(&format!("User for {}{}: ", url.authority(), realm)).ok()?;
+            if user.is_empty() {
+                return None;
+            }
+            let pass = term::read_password("Password: ").ok()?;
+            Some((user, pass))
+        })));
+    }
+    client
+}
+
+fn download(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (url, path) = parse_download_args(args)?;
+    let size = Download::new(&url, &path).run_with_progress(&mut client(), &mut progress_bar())?;
+    eprintln!("saved {} bytes to {}", size, path);
+    Ok(())
+}
+
+// `<url> -o <file>` in any order
+fn parse_download_args(args: &[String]) -> Result<(String, String), CliError> {
+    let mut url = None;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => path = Some(args.next().ok_or(CliError::Usage)?.to_string()),
+            _ if url.is_none() => url = Some(arg.to_string()),
+            _ => return Err(CliError::Usage),
+        }
+    }
+    match (url, path) {
+        (Some(url), Some(path)) => Ok((url, path)),
+        _ => Err(CliError::Usage),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum BookmarkCommand {
+    List(String),
+    Add(Bookmark),
+    Remove(String),
+    Search(String),
+    Import(String),
+    Export(Option<String>),
+}
+
+fn bookmarks(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = profile::bookmarks_path().ok_or(CliError::NoProfile)?;
+    let mut bookmarks = Bookmarks::load(&path)?;
+    match parse_bookmark_args(args)? {
+        BookmarkCommand::List(folder) => {
+            let found = bookmarks.entries().iter().filter(|b| b.is_in(&folder)).collect::<Vec<_>>();
+            print_bookmarks(&bookmarks, &found);
+        }
+        BookmarkCommand::Search(query) => print_bookmarks(&bookmarks, &bookmarks.search(&query)),
+        BookmarkCommand::Add(bookmark) => {
+            eprintln!("added {}", bookmark.summary());
+            bookmarks.add(bookmark);
+            bookmarks.save(&path)?;
+        }
+        BookmarkCommand::Remove(target) => {
+            // a number from `aktis bookmarks list`
+            let url = match target.parse::<usize>() {
+                Ok(n) if n >= 1 => bookmarks.entries().get(n - 1).map(|b| b.url().to_string()),
+                _ => Some(target.clone()),
+            };
+            let removed = url.and_then(|url| bookmarks.remove(&url)).ok_or(CliError::NoBookmark(target))?;
+            eprintln!("removed {}", removed.summary());
+            bookmarks.save(&path)?;
+        }
+        BookmarkCommand::Import(file) => {
+            let imported = Bookmarks::from_netscape(&fs::read_to_string(&file)?);
+            eprintln!("imported {} bookmarks from {}", imported.entries().len(), file);
+            bookmarks.extend(imported);
+            bookmarks.save(&path)?;
+        }
+        BookmarkCommand::Export(Some(file)) => fs::write(file, bookmarks.to_netscape())?,
+        BookmarkCommand::Export(None) => print!("{}", bookmarks.to_netscape()),
+    }
+    Ok(())
+}
+
+// numbered among all the bookmarks, as `aktis bookmarks remove <n>` takes them
+fn print_bookmarks(all: &Bookmarks, found: &[&Bookmark]) {
+    for bookmark in found {
+        let n = all.entries().iter().position(|b| b.url() == bookmark.url()).map_or(0, |i| i + 1);
+        println!("{:>3}. {}", n, bookmark.summary());
+    }
+}
+
+// `add <url> [title words] [-f folder] [-t tag]...`, `remove <url|n>`, `search <words>` ...
+fn parse_bookmark_args(args: &[String]) -> Result<BookmarkCommand, CliError> {
+    let rest = args.get(1..).unwrap_or_default();
+    match args.first().map(|s| s.as_str()) {
+        None => Ok(BookmarkCommand::List(String::new())),
+        Some("list") if rest.len() <= 1 => Ok(BookmarkCommand::List(rest.first().cloned().unwrap_or_default())),
+        Some("search") if !rest.is_empty() => Ok(BookmarkCommand::Search(rest.join(" "))),
+        Some("remove") if rest.len() == 1 => Ok(BookmarkCommand::Remove(rest[0].clone())),
+        Some("import") if rest.len() == 1 => Ok(BookmarkCommand::Import(rest[0].clone())),
+        Some("export") if rest.len() <= 1 => Ok(BookmarkCommand::Export(rest.first().cloned())),
+        Some("add") => {
+            let mut url = None;
+            let mut title = vec![];
+            let mut folder = String::new();
+            let mut tags = vec![];
+            let mut args = rest.iter();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "-f" | "--folder" => folder = args.next().ok_or(CliError::Usage)?.to_string(),
+                    "-t" | "--tag" => tags.push(args.next().ok_or(CliError::Usage)?),
+                    _ if url.is_none() => url = Some(arg),
+                    _ => title.push(arg.as_str()),
+                }
+            }
+            let url = url.ok_or(CliError::Usage)?;
+            let title = if title.is_empty() { url.to_string() } else { title.join(" ") };
+            let bookmark = Bookmark::new(url, &title).with_folder(&folder);
+            Ok(BookmarkCommand::Add(tags.iter().fold(bookmark, |b, tag| b.with_tag(tag))))
+        }
+        _ => Err(CliError::Usage),
+    }
+}
+
+fn history(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = profile::history_path().ok_or(CliError::NoProfile)?;
+    let query = args.join(" ");
+    for visit in history::load(path).unwrap_or_default().iter().filter(|v| v.matches(&query)) {
+        println!("{}", visit.summary());
+    }
+    Ok(())
+}
+
+fn dns(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (name, rtype, server) = parse_dns_args(args)?;
+    let conf = match server {
+        Some(addr) => ResolvConf::new().with_nameserver(addr),
+        None => ResolvConf::system(),
+    };
+    let resolver = Resolver::new(conf, Hosts::new());
+
+    let start = Instant::now();
+    let res = resolver.query(&name, rtype)?;
+    print!("{}", res);
+    println!("\n;; Query time: {} msec", start.elapsed().as_millis());
+    Ok(())
+}
+
+// `example.com MX @192.0.2.53` -> (`example.com`, `MX`, `192.0.2.53:53`)
+fn parse_dns_args(args: &[String]) -> Result<(String, RecordType, Option<SocketAddr>), Box<dyn Error>> {
+    let mut name = None;
+    let mut rtype = RecordType::A;
+    let mut server = None;
+    for arg in args {
+        if let Some(s) = arg.strip_prefix('@') {
+            server = Some(parse_server(s)?);
+        } else if name.is_none() {
+            name = Some(arg.clone());
+        } else {
+            rtype = RecordType::parse(arg).ok_or_else(|| CliError::InvalidType(arg.clone()))?;
+        }
+    }
+    match name {
+        Some(name) => Ok((name, rtype, server)),
+        None => Err(Box::new(CliError::Usage)),
+    }
+}
+
+// `192.0.2.53`, `[2001:db8::53]:5353` or a host name
+fn parse_server(s: &str) -> Result<SocketAddr, Box<dyn Error>> {
+    if let Ok(addr) = s.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    if let Ok(ip) = s.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, 53));
+    }
+    match Resolver::system().lookup_ip(s)?.first() {
+        Some(ip) => Ok(SocketAddr::new(*ip, 53)),
+        None => Err(Box::new(CliError::InvalidServer(s.to_string()))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_dns_args() {
+        let (name, rtype, server) = parse_dns_args(&args("example.com")).unwrap();
+        assert_eq!((name.as_str(), rtype, server), ("example.com", RecordType::A, None));
+
+        let (_, rtype, server) = parse_dns_args(&args("@[::1]:5353 example.com mx")).unwrap();
+        assert_eq!(rtype, RecordType::MX);
+        assert_eq!(server, Some("[::1]:5353".parse().unwrap()));
+        let (_, _, server) = parse_dns_args(&args("example.com @192.0.2.53")).unwrap();
+        assert_eq!(server, Some("192.0.2.53:53".parse().unwrap()));
+
+        assert!(parse_dns_args(&args("example.com BOGUS")).is_err());
+        assert!(parse_dns_args(&args("@192.0.2.53")).is_err());
+    }
+
+    #[test]
+    fn test_browse_args() {
+        let expected = ("http://example.com/".to_string(), Some(72), None);
+        assert_eq!(parse_browse_args(&args("http://example.com/ -w 72")).unwrap(), expected);
+        assert_eq!(parse_browse_args(&args("--width 72 http://example.com/")).unwrap(), expected);
+        assert_eq!(parse_browse_args(&args("http://example.com/")).unwrap().1, None);
+        assert_eq!(parse_browse_args(&args("-f json http://example.com/")).unwrap().2, Some(Format::Json));
+        assert!(parse_browse_args(&args("http://example.com/ -f html")).is_err());
+        assert!(parse_browse_args(&args("http://example.com/ -w 0")).is_err());
+        assert!(parse_browse_args(&args("-w 72")).is_err());
+    }
+
+    #[test]
+    fn test_download_args() {
+        let expected = ("http://example.com/a.iso".to_string(), "a.iso".to_string());
+        assert_eq!(parse_download_args(&args("http://example.com/a.iso -o a.iso")).unwrap(), expected);
+        assert_eq!(parse_download_args(&args("-o a.iso http://example.com/a.iso")).unwrap(), expected);
+        assert!(parse_download_args(&args("http://example.com/a.iso")).is_err());
+        assert!(parse_download_args(&args("http://example.com/a.iso -o")).is_err());
+    }
+
+    #[test]
+    fn test_bookmark_args() {
+        assert_eq!(parse_bookmark_args(&[]).unwrap(), BookmarkCommand::List(String::new()));
+        assert_eq!(parse_bookmark_args(&args("search rust #docs")).unwrap(), BookmarkCommand::Search("rust #docs".to_string()));
+        assert_eq!(parse_bookmark_args(&args("export")).unwrap(), BookmarkCommand::Export(None));
+        match parse_bookmark_args(&args("add -t rust https://www.rust-lang.org/ Rust Language -f Dev/Rust -t lang")).unwrap() {
+            BookmarkCommand::Add(b) => {
+                assert_eq!(b.summary(), "Rust Language  https://www.rust-lang.org/  (Dev/Rust #rust #lang)")
+            }
+            command => panic!("{:?}", command),
+        }
+        assert!(parse_bookmark_args(&args("add -f Dev")).is_err());
+        assert!(parse_bookmark_args(&args("remove")).is_err());
+        assert!(parse_bookmark_args(&args("bogus")).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+pub mod conf;
+pub mod message;
+pub mod resolver;
+
+use std::fmt;
+
+// ref: https://www.rfc-editor.org/rfc/rfc1035#section-3.2.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A,
+    NS,
+    CNAME,
+    SOA,
+    MX,
+    TXT,
+    AAAA,
+    Other(u16),
+}
+
+impl RecordType {
+    pub fn from_u16(n: u16) -> Self {
+        match n {
+            1 => Self::A,
+            2 => Self::NS,
+            5 => Self::CNAME,
+            6 => Self::SOA,
+            15 => Self::MX,
+            16 => Self::TXT,
+            28 => Self::AAAA,
+            n => Self::Other(n),
+        }
+    }
+
+    pub fn as_u16(&self) -> u16 {
+        match self {
+            Self::A => 1,
+            Self::NS => 2,
+            Self::CNAME => 5,
+            Self::SOA => 6,
+            Self::MX => 15,
+            Self::TXT => 16,
+            Self::AAAA => 28,
+            Self::Other(n) => *n,
+        }
+    }
+
+    // `aaaa` -> `RecordType::AAAA`, `TYPE65` -> `RecordType::Other(65)`
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.to_ascii_uppercase();
+        match s.as_str() {
+            "A" => Some(Self::A),
+            "NS" => Some(Self::NS),
+            "CNAME" => Some(Self::CNAME),
+            "SOA" => Some(Self::SOA),
+            "MX" => Some(Self::MX),
+            "TXT" => Some(Self::TXT),
+            "AAAA" => Some(Self::AAAA),
+            _ => s.strip_prefix("TYPE")?.parse().ok().map(Self::from_u16),
+        }
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A => write!(f, "A"),
+            Self::NS => write!(f, "NS"),
+            Self::CNAME => write!(f, "CNAME"),
+            Self::SOA => write!(f, "SOA"),
+            Self::MX => write!(f, "MX"),
+            Self::TXT => write!(f, "TXT"),
+            Self::AAAA => write!(f, "AAAA"),
+            Self::Other(n) => write!(f, "TYPE{}", n),
+        }
+    }
+}
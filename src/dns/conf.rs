@@ -0,0 +1,240 @@
+// System resolver configuration
+//
+// - `/etc/resolv.conf`: name servers, search domains and options
+// - `/etc/hosts`: static host names
+//
+// ref: resolv.conf(5), hosts(5)
+
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::error::Error;
+use std::time::Duration;
+use crate::dns::message::normalize;
+
+// the resolver queries the local machine without `nameserver` lines
+const DEFAULT_NAMESERVER: &str = "127.0.0.1:53";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvConf {
+    nameservers: Vec<SocketAddr>,
+    search: Vec<String>,
+    ndots: usize,
+    timeout: Duration,
+    attempts: usize,
+}
+
+impl Default for ResolvConf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResolvConf {
+    // no name servers, no search domains, `options ndots:1 timeout:5 attempts:2`
+    pub fn new() -> Self {
+        Self {
+            nameservers: vec![],
+            search: vec![],
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+        }
+    }
+
+    // Example:
+    // ```
+    // # comment
+    // nameserver 192.0.2.53
+    // nameserver 2001:db8::53
+    // search corp.example example.com
+    // options ndots:2 timeout:1 attempts:3
+    // ```
+    pub fn parse(src: &str) -> Self {
+        let mut conf = Self::new();
+        for line in src.lines() {
+            let line = line.split(['#', ';']).next().unwrap_or("");
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("nameserver") => {
+                    // `fe80::1%eth0` is not supported
+                    if let Some(Ok(ip)) = words.next().map(|w| w.parse::<IpAddr>()) {
+                        conf.nameservers.push(SocketAddr::new(ip, 53));
+                    }
+                }
+                // the last of `domain` and `search` wins
+                Some("domain") => conf.search = words.take(1).map(normalize).collect(),
+                Some("search") => conf.search = words.map(normalize).collect(),
+                Some("options") => {
+                    for option in words {
+                        let (name, value) = match option.split_once(':') {
+                            Some((name, value)) => (name, value.parse::<usize>().ok()),
+                            None => (option, None),
+                        };
+                        match (name, value) {
+                            ("ndots", Some(n)) => conf.ndots = n.min(15),
+                            ("timeout", Some(n)) => conf.timeout = Duration::from_secs(n.clamp(1, 30) as u64),
+                            ("attempts", Some(n)) => conf.attempts = n.clamp(1, 5),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        conf
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    // `/etc/resolv.conf`, or the defaults if it cannot be read
+    pub fn system() -> Self {
+        Self::load("/etc/resolv.conf").unwrap_or_default()
+    }
+
+    pub fn with_nameserver(mut self, addr: SocketAddr) -> Self {
+        self.nameservers.push(addr);
+        self
+    }
+
+    pub fn with_search(mut self, domain: &str) -> Self {
+        self.search.push(normalize(domain));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    pub fn nameservers(&self) -> Vec<SocketAddr> {
+        if self.nameservers.is_empty() {
+            return vec![DEFAULT_NAMESERVER.parse().unwrap()];
+        }
+        self.nameservers.clone()
+    }
+
+    pub fn search(&self) -> &[String] {
+        &self.search
+    }
+
+    pub fn ndots(&self) -> usize {
+        self.ndots
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    // names to query for `name` in order
+    //
+    // `www` with `search example.com` -> [`www.example.com`, `www`]
+    // `www.example.com.` (absolute) -> [`www.example.com`]
+    pub fn candidates(&self, name: &str) -> Vec<String> {
+        if name.ends_with('.') {
+            return vec![normalize(name)];
+        }
+        let name = normalize(name);
+        let searched = self.search.iter().map(|domain| format!("{}.{}", name, domain));
+        if name.matches('.').count() >= self.ndots {
+            std::iter::once(name.clone()).chain(searched).collect()
+        } else {
+            searched.chain(std::iter::once(name.clone())).collect()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hosts(Vec<(String, IpAddr)>);
+
+impl Hosts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Example:
+    // ```
+    // 127.0.0.1   localhost
+    // ::1         localhost ip6-localhost
+    // 192.0.2.10  intranet.example intranet  # comment
+    // ```
+    pub fn parse(src: &str) -> Self {
+        let mut hosts = Self::new();
+        for line in src.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let mut words = line.split_whitespace();
+            let addr = match words.next().map(|w| w.parse::<IpAddr>()) {
+                Some(Ok(addr)) => addr,
+                _ => continue,
+            };
+            for name in words {
+                hosts.add(name, addr);
+            }
+        }
+        hosts
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    // `/etc/hosts`, or no entries if it cannot be read
+    pub fn system() -> Self {
+        Self::load("/etc/hosts").unwrap_or_default()
+    }
+
+    pub fn add(&mut self, name: &str, addr: IpAddr) {
+        self.0.push((normalize(name), addr));
+    }
+
+    pub fn lookup(&self, name: &str) -> Vec<IpAddr> {
+        let name = normalize(name);
+        self.0.iter().filter(|(n, _)| *n == name).map(|(_, addr)| *addr).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolv_conf() {
+        let conf = ResolvConf::parse(
+            "# generated\nnameserver 192.0.2.53\nnameserver 2001:db8::53 ; v6\nnameserver bogus\n\
+             domain old.example\nsearch Corp.Example example.com\noptions ndots:2 timeout:1 attempts:9 rotate\n",
+        );
+        assert_eq!(conf.nameservers(), vec![
+            "192.0.2.53:53".parse::<SocketAddr>().unwrap(),
+            "[2001:db8::53]:53".parse().unwrap(),
+        ]);
+        assert_eq!(conf.search(), ["corp.example", "example.com"]);
+        assert_eq!((conf.ndots(), conf.timeout(), conf.attempts()), (2, Duration::from_secs(1), 5));
+
+        assert_eq!(conf.candidates("www"), ["www.corp.example", "www.example.com", "www"]);
+        assert_eq!(conf.candidates("a.b.c"), ["a.b.c", "a.b.c.corp.example", "a.b.c.example.com"]);
+        assert_eq!(conf.candidates("www."), ["www"]);
+        assert_eq!(ResolvConf::parse("").nameservers(), vec!["127.0.0.1:53".parse().unwrap()]);
+    }
+
+    #[test]
+    fn test_hosts() {
+        let hosts = Hosts::parse("127.0.0.1 localhost\n::1 localhost ip6-localhost\n# 192.0.2.1 commented\n192.0.2.10 Intranet.example intranet # office\n");
+        assert_eq!(hosts.lookup("localhost"), vec![
+            "127.0.0.1".parse::<IpAddr>().unwrap(),
+            "::1".parse().unwrap(),
+        ]);
+        assert_eq!(hosts.lookup("INTRANET.example."), vec!["192.0.2.10".parse::<IpAddr>().unwrap()]);
+        assert!(hosts.lookup("commented").is_empty());
+    }
+}
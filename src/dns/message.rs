@@ -0,0 +1,702 @@
+// DNS messages (RFC 1035 section 4)
+//
+// ```
+// +---------------------+
+// |        Header       |  ID, flags, QDCOUNT, ANCOUNT, NSCOUNT, ARCOUNT
+// +---------------------+
+// |       Question      |  the question for the name server
+// +---------------------+
+// |        Answer       |  RRs answering the question
+// +---------------------+
+// |      Authority      |  RRs pointing toward an authority
+// +---------------------+
+// |      Additional     |  RRs holding additional information
+// +---------------------+
+// ```
+//
+// Names in received messages may be compressed (pointers to earlier names);
+// names are always written uncompressed.
+
+use std::error::Error;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use crate::dns::RecordType;
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum MessageError {
+    TooShort,
+    InvalidName(String),
+    PointerLoop,
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort => write!(f, "DNS message error: message is too short"),
+            Self::InvalidName(s) => write!(f, "DNS message error: invalid name `{}`", s),
+            Self::PointerLoop => write!(f, "DNS message error: too many compression pointers"),
+        }
+    }
+}
+
+impl Error for MessageError {}
+// ------------------------------------
+
+pub const CLASS_IN: u16 = 1;
+
+// response codes
+pub const NOERROR: u8 = 0;
+pub const FORMERR: u8 = 1;
+pub const SERVFAIL: u8 = 2;
+pub const NXDOMAIN: u8 = 3;
+pub const NOTIMP: u8 = 4;
+pub const REFUSED: u8 = 5;
+
+// flags (the second 16 bits of the header)
+const QR: u16 = 1 << 15;
+const AA: u16 = 1 << 10;
+const TC: u16 = 1 << 9;
+const RD: u16 = 1 << 8;
+const RA: u16 = 1 << 7;
+
+const MAX_POINTERS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    name: String,
+    qtype: RecordType,
+    class: u16,
+}
+
+impl Question {
+    pub fn new(name: &str, qtype: RecordType) -> Self {
+        Self {
+            name: normalize(name),
+            qtype,
+            class: CLASS_IN,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn qtype(&self) -> RecordType {
+        self.qtype
+    }
+
+    pub fn class(&self) -> u16 {
+        self.class
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RData {
+    A(Ipv4Addr),
+    AAAA(Ipv6Addr),
+    CNAME(String),
+    NS(String),
+    MX {
+        preference: u16,
+        exchange: String,
+    },
+    // one or more <character-string>s
+    TXT(Vec<String>),
+    SOA {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    // RDATA of types we do not interpret
+    Other(u16, Vec<u8>),
+}
+
+impl RData {
+    pub fn rtype(&self) -> RecordType {
+        match self {
+            Self::A(_) => RecordType::A,
+            Self::AAAA(_) => RecordType::AAAA,
+            Self::CNAME(_) => RecordType::CNAME,
+            Self::NS(_) => RecordType::NS,
+            Self::MX { .. } => RecordType::MX,
+            Self::TXT(_) => RecordType::TXT,
+            Self::SOA { .. } => RecordType::SOA,
+            Self::Other(n, _) => RecordType::from_u16(*n),
+        }
+    }
+}
+
+impl fmt::Display for RData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A(addr) => write!(f, "{}", addr),
+            Self::AAAA(addr) => write!(f, "{}", addr),
+            Self::CNAME(name) | Self::NS(name) => write!(f, "{}.", name),
+            Self::MX { preference, exchange } => write!(f, "{} {}.", preference, exchange),
+            Self::TXT(strings) => {
+                let quoted: Vec<String> = strings
+                    .iter()
+                    .map(|s| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")))
+                    .collect();
+                write!(f, "{}", quoted.join(" "))
+            }
+            Self::SOA { mname, rname, serial, refresh, retry, expire, minimum } => write!(
+                f,
+                "{}. {}. {} {} {} {} {}",
+                mname, rname, serial, refresh, retry, expire, minimum
+            ),
+            // RFC 3597 generic format
+            Self::Other(_, data) => {
+                write!(f, "\\# {}", data.len())?;
+                if !data.is_empty() {
+                    write!(f, " ")?;
+                }
+                data.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    name: String,
+    class: u16,
+    ttl: u32,
+    data: RData,
+}
+
+impl Record {
+    pub fn new(name: &str, ttl: u32, data: RData) -> Self {
+        Self {
+            name: normalize(name),
+            class: CLASS_IN,
+            ttl,
+            data,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rtype(&self) -> RecordType {
+        self.data.rtype()
+    }
+
+    pub fn class(&self) -> u16 {
+        self.class
+    }
+
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    pub fn data(&self) -> &RData {
+        &self.data
+    }
+}
+
+// zone file format: `example.com.	3600	IN	A	93.184.216.34`
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = match self.class {
+            CLASS_IN => "IN".to_string(),
+            n => format!("CLASS{}", n),
+        };
+        write!(f, "{}.\t{}\t{}\t{}\t{}", self.name, self.ttl, class, self.rtype(), self.data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    id: u16,
+    flags: u16,
+    questions: Vec<Question>,
+    answers: Vec<Record>,
+    authorities: Vec<Record>,
+    additionals: Vec<Record>,
+}
+
+impl Message {
+    // a recursive query for one question
+    pub fn query(id: u16, name: &str, qtype: RecordType) -> Self {
+        Self {
+            id,
+            flags: RD,
+            questions: vec![Question::new(name, qtype)],
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+        }
+    }
+
+    // an empty (`NOERROR`) response to `query`
+    pub fn reply(query: &Message) -> Self {
+        Self {
+            id: query.id,
+            flags: QR | RA | (query.flags & RD),
+            questions: query.questions.clone(),
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+        }
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.flags & QR != 0
+    }
+
+    pub fn is_authoritative(&self) -> bool {
+        self.flags & AA != 0
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.flags & TC != 0
+    }
+
+    pub fn recursion_desired(&self) -> bool {
+        self.flags & RD != 0
+    }
+
+    pub fn recursion_available(&self) -> bool {
+        self.flags & RA != 0
+    }
+
+    pub fn rcode(&self) -> u8 {
+        (self.flags & 0x0F) as u8
+    }
+
+    pub fn set_truncated(&mut self, truncated: bool) {
+        self.flags = if truncated { self.flags | TC } else { self.flags & !TC };
+    }
+
+    pub fn set_rcode(&mut self, rcode: u8) {
+        self.flags = self.flags & !0x0F | (rcode & 0x0F) as u16;
+    }
+
+    pub fn questions(&self) -> &[Question] {
+        &self.questions
+    }
+
+    pub fn answers(&self) -> &[Record] {
+        &self.answers
+    }
+
+    pub fn authorities(&self) -> &[Record] {
+        &self.authorities
+    }
+
+    pub fn additionals(&self) -> &[Record] {
+        &self.additionals
+    }
+
+    pub fn add_answer(&mut self, record: Record) {
+        self.answers.push(record);
+    }
+
+    pub fn add_authority(&mut self, record: Record) {
+        self.authorities.push(record);
+    }
+
+    pub fn add_additional(&mut self, record: Record) {
+        self.additionals.push(record);
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, MessageError> {
+        let mut buf = vec![];
+        buf.extend_from_slice(&self.id.to_be_bytes());
+        buf.extend_from_slice(&self.flags.to_be_bytes());
+        for count in [
+            self.questions.len(),
+            self.answers.len(),
+            self.authorities.len(),
+            self.additionals.len(),
+        ].iter() {
+            buf.extend_from_slice(&(*count as u16).to_be_bytes());
+        }
+
+        for q in self.questions.iter() {
+            write_name(&mut buf, &q.name)?;
+            buf.extend_from_slice(&q.qtype.as_u16().to_be_bytes());
+            buf.extend_from_slice(&q.class.to_be_bytes());
+        }
+        for record in self.answers.iter().chain(self.authorities.iter()).chain(self.additionals.iter()) {
+            write_record(&mut buf, record)?;
+        }
+        Ok(buf)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, MessageError> {
+        let id = read_u16(buf, 0)?;
+        let flags = read_u16(buf, 2)?;
+        let counts = [read_u16(buf, 4)?, read_u16(buf, 6)?, read_u16(buf, 8)?, read_u16(buf, 10)?];
+        let mut pos = 12;
+
+        let mut questions = vec![];
+        for _ in 0..counts[0] {
+            let (name, next) = read_name(buf, pos)?;
+            questions.push(Question {
+                name,
+                qtype: RecordType::from_u16(read_u16(buf, next)?),
+                class: read_u16(buf, next + 2)?,
+            });
+            pos = next + 4;
+        }
+
+        let mut sections = vec![];
+        for count in counts[1..].iter() {
+            let mut records = vec![];
+            for _ in 0..*count {
+                let (record, next) = read_record(buf, pos)?;
+                records.push(record);
+                pos = next;
+            }
+            sections.push(records);
+        }
+        let additionals = sections.pop().unwrap_or_default();
+        let authorities = sections.pop().unwrap_or_default();
+        let answers = sections.pop().unwrap_or_default();
+
+        Ok(Self { id, flags, questions, answers, authorities, additionals })
+    }
+}
+
+// `3` -> `NXDOMAIN`
+pub fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        NOERROR => "NOERROR".to_string(),
+        FORMERR => "FORMERR".to_string(),
+        SERVFAIL => "SERVFAIL".to_string(),
+        NXDOMAIN => "NXDOMAIN".to_string(),
+        NOTIMP => "NOTIMP".to_string(),
+        REFUSED => "REFUSED".to_string(),
+        n => format!("RCODE{}", n),
+    }
+}
+
+// in the style of dig(1)
+//
+// Example:
+// ```
+// ;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4660
+// ;; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 0
+//
+// ;; QUESTION SECTION:
+// ;example.com.	IN	A
+//
+// ;; ANSWER SECTION:
+// example.com.	3600	IN	A	93.184.216.34
+// ```
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opcode = match (self.flags >> 11) & 0x0F {
+            0 => "QUERY".to_string(),
+            n => format!("OPCODE{}", n),
+        };
+        writeln!(f, ";; ->>HEADER<<- opcode: {}, status: {}, id: {}", opcode, rcode_name(self.rcode()), self.id)?;
+        let flags: Vec<&str> = [(QR, "qr"), (AA, "aa"), (TC, "tc"), (RD, "rd"), (RA, "ra")]
+            .iter()
+            .filter(|(bit, _)| self.flags & bit != 0)
+            .map(|(_, name)| *name)
+            .collect();
+        writeln!(
+            f,
+            ";; flags: {}; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            flags.join(" "),
+            self.questions.len(),
+            self.answers.len(),
+            self.authorities.len(),
+            self.additionals.len(),
+        )?;
+
+        if !self.questions.is_empty() {
+            write!(f, "\n;; QUESTION SECTION:\n")?;
+            for q in self.questions.iter() {
+                let class = if q.class == CLASS_IN { "IN".to_string() } else { format!("CLASS{}", q.class) };
+                writeln!(f, ";{}.\t{}\t{}", q.name, class, q.qtype)?;
+            }
+        }
+        for (title, records) in [
+            ("ANSWER", &self.answers),
+            ("AUTHORITY", &self.authorities),
+            ("ADDITIONAL", &self.additionals),
+        ].iter() {
+            if records.is_empty() {
+                continue;
+            }
+            write!(f, "\n;; {} SECTION:\n", title)?;
+            for record in records.iter() {
+                writeln!(f, "{}", record)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// `Example.COM.` -> `example.com`
+pub(crate) fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<u16, MessageError> {
+    match buf.get(pos..pos + 2) {
+        Some(b) => Ok(u16::from_be_bytes([b[0], b[1]])),
+        None => Err(MessageError::TooShort),
+    }
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Result<u32, MessageError> {
+    match buf.get(pos..pos + 4) {
+        Some(b) => Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(MessageError::TooShort),
+    }
+}
+
+// read a (possibly compressed) name at `pos`
+// and return it with the position right after it
+//
+// ref: https://www.rfc-editor.org/rfc/rfc1035#section-4.1.4
+fn read_name(buf: &[u8], mut pos: usize) -> Result<(String, usize), MessageError> {
+    let mut labels = vec![];
+    let mut end = None;
+    let mut pointers = 0;
+    loop {
+        let len = *buf.get(pos).ok_or(MessageError::TooShort)? as usize;
+        match len & 0xC0 {
+            0x00 if len == 0 => {
+                pos += 1;
+                break;
+            }
+            0x00 => {
+                let label = buf.get(pos + 1..pos + 1 + len).ok_or(MessageError::TooShort)?;
+                labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+                pos += 1 + len;
+            }
+            0xC0 => {
+                let low = *buf.get(pos + 1).ok_or(MessageError::TooShort)? as usize;
+                if end.is_none() {
+                    end = Some(pos + 2);
+                }
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return Err(MessageError::PointerLoop);
+                }
+                pos = (len & 0x3F) << 8 | low;
+            }
+            _ => return Err(MessageError::InvalidName(labels.join("."))),
+        }
+    }
+    let name = labels.join(".");
+    if name.len() > 253 {
+        return Err(MessageError::InvalidName(name));
+    }
+    Ok((name, end.unwrap_or(pos)))
+}
+
+fn write_name(buf: &mut Vec<u8>, name: &str) -> Result<(), MessageError> {
+    let name = name.trim_end_matches('.');
+    if name.len() > 253 {
+        return Err(MessageError::InvalidName(name.to_string()));
+    }
+    if !name.is_empty() {
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return Err(MessageError::InvalidName(name.to_string()));
+            }
+            buf.push(label.len() as u8);
+            buf.extend_from_slice(label.as_bytes());
+        }
+    }
+    buf.push(0);
+    Ok(())
+}
+
+fn read_record(buf: &[u8], pos: usize) -> Result<(Record, usize), MessageError> {
+    let (name, pos) = read_name(buf, pos)?;
+    let rtype = read_u16(buf, pos)?;
+    let class = read_u16(buf, pos + 2)?;
+    let ttl = read_u32(buf, pos + 4)?;
+    let len = read_u16(buf, pos + 8)? as usize;
+    let start = pos + 10;
+    let rdata = buf.get(start..start + len).ok_or(MessageError::TooShort)?;
+
+    let data = match RecordType::from_u16(rtype) {
+        RecordType::A if len == 4 => RData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3])),
+        RecordType::AAAA if len == 16 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(rdata);
+            RData::AAAA(Ipv6Addr::from(octets))
+        }
+        // names inside RDATA may point anywhere in the message
+        RecordType::CNAME => RData::CNAME(read_name(buf, start)?.0),
+        RecordType::NS => RData::NS(read_name(buf, start)?.0),
+        RecordType::MX if len > 2 => RData::MX {
+            preference: read_u16(buf, start)?,
+            exchange: read_name(buf, start + 2)?.0,
+        },
+        RecordType::TXT => {
+            let mut strings = vec![];
+            let mut rest = rdata;
+            while let Some((&n, tail)) = rest.split_first() {
+                let s = tail.get(..n as usize).ok_or(MessageError::TooShort)?;
+                strings.push(String::from_utf8_lossy(s).into_owned());
+                rest = &tail[n as usize..];
+            }
+            RData::TXT(strings)
+        }
+        RecordType::SOA => {
+            let (mname, pos) = read_name(buf, start)?;
+            let (rname, pos) = read_name(buf, pos)?;
+            RData::SOA {
+                mname,
+                rname,
+                serial: read_u32(buf, pos)?,
+                refresh: read_u32(buf, pos + 4)?,
+                retry: read_u32(buf, pos + 8)?,
+                expire: read_u32(buf, pos + 12)?,
+                minimum: read_u32(buf, pos + 16)?,
+            }
+        }
+        _ => RData::Other(rtype, rdata.to_vec()),
+    };
+    // TTLs with the top bit set are treated as zero (RFC 2181 section 8)
+    let ttl = if ttl > i32::MAX as u32 { 0 } else { ttl };
+    Ok((Record { name, class, ttl, data }, start + len))
+}
+
+fn write_record(buf: &mut Vec<u8>, record: &Record) -> Result<(), MessageError> {
+    write_name(buf, &record.name)?;
+    buf.extend_from_slice(&record.rtype().as_u16().to_be_bytes());
+    buf.extend_from_slice(&record.class.to_be_bytes());
+    buf.extend_from_slice(&record.ttl.to_be_bytes());
+
+    let mut rdata = vec![];
+    match &record.data {
+        RData::A(addr) => rdata.extend_from_slice(&addr.octets()),
+        RData::AAAA(addr) => rdata.extend_from_slice(&addr.octets()),
+        RData::CNAME(name) | RData::NS(name) => write_name(&mut rdata, name)?,
+        RData::MX { preference, exchange } => {
+            rdata.extend_from_slice(&preference.to_be_bytes());
+            write_name(&mut rdata, exchange)?;
+        }
+        RData::TXT(strings) => {
+            for s in strings.iter() {
+                // longer strings are split into <character-string>s of 255 bytes
+                for chunk in s.as_bytes().chunks(255) {
+                    rdata.push(chunk.len() as u8);
+                    rdata.extend_from_slice(chunk);
+                }
+                if s.is_empty() {
+                    rdata.push(0);
+                }
+            }
+        }
+        RData::SOA { mname, rname, serial, refresh, retry, expire, minimum } => {
+            write_name(&mut rdata, mname)?;
+            write_name(&mut rdata, rname)?;
+            for n in [serial, refresh, retry, expire, minimum].iter() {
+                rdata.extend_from_slice(&n.to_be_bytes());
+            }
+        }
+        RData::Other(_, data) => rdata.extend_from_slice(data),
+    }
+    buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&rdata);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let query = Message::query(0xBEEF, "Example.com.", RecordType::AAAA);
+        let bytes = query.encode().unwrap();
+        assert_eq!(&bytes[..12], &[0xBE, 0xEF, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&bytes[12..], b"\x07example\x03com\x00\x00\x1c\x00\x01");
+        assert_eq!(Message::decode(&bytes).unwrap(), query);
+
+        let mut reply = Message::reply(&query);
+        reply.add_answer(Record::new("example.com", 60, RData::AAAA("2001:db8::1".parse().unwrap())));
+        reply.set_rcode(NXDOMAIN);
+        let decoded = Message::decode(&reply.encode().unwrap()).unwrap();
+        assert_eq!(decoded, reply);
+        assert!(decoded.is_response() && decoded.recursion_desired());
+        assert_eq!(decoded.rcode(), NXDOMAIN);
+    }
+
+    #[test]
+    fn test_compression() {
+        // www.example.com CNAME example.com, example.com A 93.184.216.34
+        let mut bytes = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+        bytes.extend_from_slice(b"\x03www\x07example\x03com\x00\x00\x01\x00\x01");
+        // answer 1: name -> offset 12, rdata -> offset 16 (`example.com`)
+        bytes.extend_from_slice(&[0xC0, 12, 0, 5, 0, 1, 0, 0, 0x0E, 0x10, 0, 2, 0xC0, 16]);
+        // answer 2: name -> offset 16
+        bytes.extend_from_slice(&[0xC0, 16, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 93, 184, 216, 34]);
+
+        let message = Message::decode(&bytes).unwrap();
+        assert_eq!(message.questions()[0].name(), "www.example.com");
+        let answers = message.answers();
+        assert_eq!(answers[0].data(), &RData::CNAME("example.com".to_string()));
+        assert_eq!(answers[0].to_string(), "www.example.com.\t3600\tIN\tCNAME\texample.com.");
+        assert_eq!(answers[1].name(), "example.com");
+        assert_eq!(answers[1].data(), &RData::A(Ipv4Addr::new(93, 184, 216, 34)));
+
+        // a pointer to itself
+        let mut looped = bytes[..12].to_vec();
+        looped.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1]);
+        assert!(matches!(Message::decode(&looped), Err(MessageError::PointerLoop)));
+        assert!(matches!(Message::decode(&bytes[..40]), Err(MessageError::TooShort)));
+    }
+
+    #[test]
+    fn test_records() {
+        let query = Message::query(4660, "example.com", RecordType::MX);
+        let mut res = Message::reply(&query);
+        res.add_answer(Record::new("example.com", 300, RData::MX { preference: 10, exchange: "mail.example.com".to_string() }));
+        res.add_answer(Record::new("example.com", 300, RData::TXT(vec!["v=spf1 -all".to_string(), "say \"hi\"".to_string()])));
+        res.add_authority(Record::new("example.com", 86400, RData::NS("a.iana-servers.net".to_string())));
+        res.add_additional(Record::new("example.com", 3600, RData::SOA {
+            mname: "ns.icann.org".to_string(),
+            rname: "noc.dns.icann.org".to_string(),
+            serial: 2022091261,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1209600,
+            minimum: 3600,
+        }));
+        res.add_additional(Record::new("example.com", 60, RData::Other(99, vec![0xAB, 0xCD])));
+        let decoded = Message::decode(&res.encode().unwrap()).unwrap();
+        assert_eq!(decoded, res);
+
+        assert_eq!(decoded.to_string(), "\
+;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4660
+;; flags: qr rd ra; QUERY: 1, ANSWER: 2, AUTHORITY: 1, ADDITIONAL: 2
+
+;; QUESTION SECTION:
+;example.com.\tIN\tMX
+
+;; ANSWER SECTION:
+example.com.\t300\tIN\tMX\t10 mail.example.com.
+example.com.\t300\tIN\tTXT\t\"v=spf1 -all\" \"say \\\"hi\\\"\"
+
+;; AUTHORITY SECTION:
+example.com.\t86400\tIN\tNS\ta.iana-servers.net.
+
+;; ADDITIONAL SECTION:
+example.com.\t3600\tIN\tSOA\tns.icann.org. noc.dns.icann.org. 2022091261 7200 3600 1209600 3600
+example.com.\t60\tIN\tTYPE99\t\\# 2 abcd
+");
+    }
+}
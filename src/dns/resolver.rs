@@ -0,0 +1,402 @@
+// Stub resolver
+//
+// Asks the recursive name servers in `resolv.conf` instead of the OS's resolver.
+//
+// 1. `/etc/hosts`
+// 2. the TTL cache
+// 3. a query over UDP to each name server in order (`attempts` rounds),
+//    repeated over TCP if the UDP response is truncated
+//
+// ref: https://www.rfc-editor.org/rfc/rfc1035#section-7
+// ref: https://www.rfc-editor.org/rfc/rfc7766 (DNS over TCP)
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use crate::dns::RecordType;
+use crate::dns::conf::{Hosts, ResolvConf};
+use crate::dns::message::{self, normalize, Message, RData, Record};
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum ResolveError {
+    NxDomain(String),
+    ServerFailure(u8),
+    NoAddress(String),
+    CnameLoop(String),
+    Timeout,
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NxDomain(s) => write!(f, "Resolve error: `{}` does not exist", s),
+            Self::ServerFailure(code) => write!(f, "Resolve error: name server failed (rcode {})", code),
+            Self::NoAddress(s) => write!(f, "Resolve error: `{}` has no address", s),
+            Self::CnameLoop(s) => write!(f, "Resolve error: too many CNAMEs for `{}`", s),
+            Self::Timeout => write!(f, "Resolve error: no name server responded"),
+        }
+    }
+}
+
+impl Error for ResolveError {}
+// ------------------------------------
+
+// CNAME chains longer than this are treated as loops
+const MAX_CNAMES: usize = 8;
+
+// without EDNS(0), UDP responses are at most 512 bytes
+const UDP_SIZE: usize = 512;
+
+// (records, expiry)
+type Cached = (Vec<Record>, Instant);
+
+#[derive(Debug)]
+pub struct Resolver {
+    conf: ResolvConf,
+    hosts: Hosts,
+    // (name, type) -> (records, expiry)
+    cache: Mutex<HashMap<(String, RecordType), Cached>>,
+    // xorshift state for query IDs
+    seed: Mutex<u64>,
+}
+
+impl Resolver {
+    pub fn new(conf: ResolvConf, hosts: Hosts) -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        Self {
+            conf,
+            hosts,
+            cache: Mutex::new(HashMap::new()),
+            seed: Mutex::new(nanos ^ (std::process::id() as u64) << 32 | 1),
+        }
+    }
+
+    // `/etc/resolv.conf` and `/etc/hosts`
+    pub fn system() -> Self {
+        Self::new(ResolvConf::system(), Hosts::system())
+    }
+
+    pub fn conf(&self) -> &ResolvConf {
+        &self.conf
+    }
+
+    pub fn hosts(&self) -> &Hosts {
+        &self.hosts
+    }
+
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    // IPv4 addresses first, then IPv6 addresses
+    pub fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+        // `[::1]` -> `::1`
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(addr) = host.parse::<IpAddr>() {
+            return Ok(vec![addr]);
+        }
+        let addrs = self.hosts.lookup(host);
+        if !addrs.is_empty() {
+            return Ok(addrs);
+        }
+
+        let mut last_err: Box<dyn Error> = Box::new(ResolveError::NoAddress(host.to_string()));
+        for name in self.conf.candidates(host) {
+            let mut addrs = vec![];
+            for rtype in [RecordType::A, RecordType::AAAA].iter() {
+                match self.lookup(&name, *rtype) {
+                    Ok(records) => addrs.extend(records.iter().filter_map(|r| match r.data() {
+                        RData::A(addr) => Some(IpAddr::V4(*addr)),
+                        RData::AAAA(addr) => Some(IpAddr::V6(*addr)),
+                        _ => None,
+                    })),
+                    Err(e) => last_err = e,
+                }
+            }
+            if !addrs.is_empty() {
+                return Ok(addrs);
+            }
+        }
+        Err(last_err)
+    }
+
+    // records of `rtype` for `name`, following CNAMEs
+    // (an empty result means the name exists but has no such records)
+    pub fn lookup(&self, name: &str, rtype: RecordType) -> Result<Vec<Record>, Box<dyn Error>> {
+        let key = (normalize(name), rtype);
+        if let Some((records, expiry)) = self.cache.lock().unwrap().get(&key) {
+            if *expiry > Instant::now() {
+                return Ok(records.clone());
+            }
+        }
+
+        let mut target = key.0.clone();
+        for _ in 0..MAX_CNAMES {
+            let response = self.query(&target, rtype)?;
+            match response.rcode() {
+                message::NOERROR => {}
+                message::NXDOMAIN => return Err(Box::new(ResolveError::NxDomain(target))),
+                code => return Err(Box::new(ResolveError::ServerFailure(code))),
+            }
+
+            let (records, next) = follow_cnames(response.answers(), &target, rtype);
+            match next {
+                Some(next) => target = next,
+                None => {
+                    if let Some(ttl) = records.iter().map(|r| r.ttl()).min().filter(|ttl| *ttl > 0) {
+                        let expiry = Instant::now() + std::time::Duration::from_secs(ttl as u64);
+                        self.cache.lock().unwrap().insert(key, (records.clone(), expiry));
+                    }
+                    return Ok(records);
+                }
+            }
+        }
+        Err(Box::new(ResolveError::CnameLoop(key.0)))
+    }
+
+    // Send a query to the name servers and return the first usable response.
+    // `SERVFAIL` and `REFUSED` make the resolver try the next server;
+    // the last such response is returned if no server does better.
+    pub fn query(&self, name: &str, rtype: RecordType) -> Result<Message, Box<dyn Error>> {
+        let mut last: Result<Message, Box<dyn Error>> = Err(Box::new(ResolveError::Timeout));
+        for _ in 0..self.conf.attempts() {
+            for server in self.conf.nameservers() {
+                let query = Message::query(self.next_id(), name, rtype);
+                match self.exchange(server, &query) {
+                    Ok(res) if res.rcode() == message::SERVFAIL || res.rcode() == message::REFUSED => last = Ok(res),
+                    Ok(res) => return Ok(res),
+                    Err(e) => {
+                        if last.is_err() {
+                            last = Err(e);
+                        }
+                    }
+                }
+            }
+        }
+        last
+    }
+
+    fn exchange(&self, server: SocketAddr, query: &Message) -> Result<Message, Box<dyn Error>> {
+        let res = self.exchange_udp(server, query)?;
+        if res.is_truncated() {
+            return self.exchange_tcp(server, query);
+        }
+        Ok(res)
+    }
+
+    fn exchange_udp(&self, server: SocketAddr, query: &Message) -> Result<Message, Box<dyn Error>> {
+        let local = if server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+        socket.send(&query.encode()?)?;
+
+        let deadline = Instant::now() + self.conf.timeout();
+        let mut buf = [0; UDP_SIZE];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Box::new(ResolveError::Timeout));
+            }
+            socket.set_read_timeout(Some(deadline - now))?;
+            let n = match socket.recv(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                    return Err(Box::new(ResolveError::Timeout));
+                }
+                Err(e) => return Err(Box::new(e)),
+            };
+            // ignore stray or forged datagrams
+            match Message::decode(&buf[..n]) {
+                Ok(res) if is_response_to(&res, query) => return Ok(res),
+                _ => continue,
+            }
+        }
+    }
+
+    // messages over TCP are prefixed with their length in two bytes
+    fn exchange_tcp(&self, server: SocketAddr, query: &Message) -> Result<Message, Box<dyn Error>> {
+        let mut stream = TcpStream::connect_timeout(&server, self.conf.timeout())?;
+        stream.set_read_timeout(Some(self.conf.timeout()))?;
+        let bytes = query.encode()?;
+        let mut buf = (bytes.len() as u16).to_be_bytes().to_vec();
+        buf.extend_from_slice(&bytes);
+        stream.write_all(&buf)?;
+
+        let mut len = [0; 2];
+        stream.read_exact(&mut len)?;
+        let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut buf)?;
+        let res = Message::decode(&buf)?;
+        if !is_response_to(&res, query) {
+            return Err(Box::new(ResolveError::Timeout));
+        }
+        Ok(res)
+    }
+
+    fn next_id(&self) -> u16 {
+        let mut seed = self.seed.lock().unwrap();
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        (*seed >> 16) as u16
+    }
+}
+
+fn is_response_to(res: &Message, query: &Message) -> bool {
+    res.is_response() && res.id() == query.id() && res.questions() == query.questions()
+}
+
+// Walk the CNAME chain from `name` in `answers`.
+// Return the records of `rtype` at the end of the chain, or the name to query next
+// if the chain leaves the answer section without reaching them.
+fn follow_cnames(answers: &[Record], name: &str, rtype: RecordType) -> (Vec<Record>, Option<String>) {
+    let mut name = name.to_string();
+    for _ in 0..=MAX_CNAMES {
+        let records: Vec<Record> = answers
+            .iter()
+            .filter(|r| r.name() == name && r.rtype() == rtype)
+            .cloned()
+            .collect();
+        if !records.is_empty() || rtype == RecordType::CNAME {
+            return (records, None);
+        }
+        let cname = answers.iter().find_map(|r| match r.data() {
+            RData::CNAME(target) if r.name() == name => Some(target.clone()),
+            _ => None,
+        });
+        match cname {
+            Some(target) => name = target,
+            None => break,
+        }
+    }
+    // no records at the end of the chain: ask for the last name (NODATA if it is `name` itself)
+    if answers.iter().any(|r| r.rtype() == RecordType::CNAME) {
+        return (vec![], Some(name));
+    }
+    (vec![], None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::client::Client;
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    // a name server on a loopback UDP port answering `count` queries with `handler`
+    fn serve_udp(count: usize, handler: fn(&Message) -> Message) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            for _ in 0..count {
+                let (n, peer) = socket.recv_from(&mut buf).unwrap();
+                let query = Message::decode(&buf[..n]).unwrap();
+                socket.send_to(&handler(&query).encode().unwrap(), peer).unwrap();
+            }
+        });
+        addr
+    }
+
+    fn resolver(servers: &[SocketAddr]) -> Resolver {
+        let conf = servers.iter().fold(
+            ResolvConf::new().with_timeout(Duration::from_millis(500)).with_attempts(1),
+            |conf, server| conf.with_nameserver(*server),
+        );
+        Resolver::new(conf, Hosts::new())
+    }
+
+    fn answer(query: &Message) -> Message {
+        let mut res = Message::reply(query);
+        let q = &query.questions()[0];
+        match (q.name(), q.qtype()) {
+            ("www.example.test", _) => {
+                res.add_answer(Record::new("www.example.test", 300, RData::CNAME("web.example.test".to_string())));
+                res.add_answer(Record::new("web.example.test", 60, RData::CNAME("edge.cdn.test".to_string())));
+                if q.qtype() == RecordType::A {
+                    res.add_answer(Record::new("edge.cdn.test", 30, RData::A(Ipv4Addr::new(192, 0, 2, 1))));
+                }
+            }
+            ("nowhere.test", _) => res.set_rcode(message::NXDOMAIN),
+            _ => {}
+        }
+        res
+    }
+
+    #[test]
+    fn test_lookup() {
+        // A (cached afterwards), AAAA (NODATA after restarting at `edge.cdn.test`), `nowhere.test`
+        let resolver = resolver(&[serve_udp(4, answer)]);
+        let records = resolver.lookup("WWW.example.test.", RecordType::A).unwrap();
+        assert_eq!(records, vec![Record::new("edge.cdn.test", 30, RData::A(Ipv4Addr::new(192, 0, 2, 1)))]);
+        assert_eq!(resolver.lookup_ip("www.example.test").unwrap(), vec![IpAddr::from([192, 0, 2, 1])]);
+        let err = resolver.lookup("nowhere.test", RecordType::A).unwrap_err();
+        assert_eq!(err.to_string(), "Resolve error: `nowhere.test` does not exist");
+    }
+
+    #[test]
+    fn test_retry_and_tcp_fallback() {
+        // the first server fails, the second truncates and answers over TCP on the same port
+        let failing = serve_udp(1, |query| {
+            let mut res = Message::reply(query);
+            res.set_rcode(message::SERVFAIL);
+            res
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let socket = UdpSocket::bind(addr).unwrap();
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (n, peer) = socket.recv_from(&mut buf).unwrap();
+            let mut res = Message::reply(&Message::decode(&buf[..n]).unwrap());
+            res.set_truncated(true);
+            socket.send_to(&res.encode().unwrap(), peer).unwrap();
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut len = [0; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut buf).unwrap();
+            let query = Message::decode(&buf).unwrap();
+            let mut res = Message::reply(&query);
+            for i in 1..=40 {
+                res.add_answer(Record::new("big.test", 60, RData::A(Ipv4Addr::new(192, 0, 2, i))));
+            }
+            let bytes = res.encode().unwrap();
+            stream.write_all(&(bytes.len() as u16).to_be_bytes()).unwrap();
+            stream.write_all(&bytes).unwrap();
+        });
+
+        let resolver = resolver(&[failing, addr]);
+        let records = resolver.lookup("big.test", RecordType::A).unwrap();
+        assert_eq!(records.len(), 40);
+    }
+
+    #[test]
+    fn test_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).unwrap();
+            assert!(buf[..n].starts_with(b"GET / HTTP/1.0\r\n"));
+            stream.write_all(b"HTTP/1.0 200 OK\r\n\r\nresolved").unwrap();
+        });
+
+        let mut hosts = Hosts::new();
+        hosts.add("origin.test", IpAddr::from([127, 0, 0, 1]));
+        let mut client = Client::new();
+        client.set_resolver(Arc::new(Resolver::new(ResolvConf::new(), hosts)));
+        let url = format!("http://origin.test:{}/", port);
+        let res = client.send(crate::http::request::Request::get(&url).unwrap()).unwrap();
+        assert_eq!(res.text(), "resolved");
+    }
+}
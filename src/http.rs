@@ -1,10 +1,56 @@
 
+pub mod auth;
+pub mod cache;
 pub mod client;
+pub mod cookie;
+pub mod date;
+pub mod download;
+pub mod headers;
+pub mod proxy;
 pub mod request;
 pub mod response;
+pub mod socks;
+pub mod status;
+pub mod transport;
 pub mod url;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Method {
     GET,
     POST,
+    PUT,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::GET => "GET",
+            Self::POST => "POST",
+            Self::PUT => "PUT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    HTTP10,
+    HTTP11,
+}
+
+impl Version {
+    // `HTTP/1.1` -> `Version::HTTP11`
+    pub fn parse(proto: &str) -> Option<Self> {
+        match proto {
+            "HTTP/1.0" => Some(Self::HTTP10),
+            "HTTP/1.1" => Some(Self::HTTP11),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HTTP10 => "HTTP/1.0",
+            Self::HTTP11 => "HTTP/1.1",
+        }
+    }
 }
@@ -0,0 +1,414 @@
+// HTTP authentication (RFC 9110 section 11)
+//
+// ```
+// HTTP/1.1 401 Unauthorized
+// WWW-Authenticate: Digest realm="api@example.org", qop="auth", algorithm=SHA-256, nonce="7ypf...", opaque="FQhe..."
+// WWW-Authenticate: Basic realm="api@example.org"
+//
+// GET /dir/index.html HTTP/1.1
+// Authorization: Digest username="Mufasa", realm="api@example.org", uri="/dir/index.html", algorithm=SHA-256,
+//     nonce="7ypf...", nc=00000001, cnonce="f2/w...", qop=auth, response="7539...", opaque="FQhe..."
+// ```
+//
+// Basic (RFC 7617) and Digest (RFC 7616) with MD5 or SHA-256 (and their `-sess` variants)
+// and `qop=auth` are supported. `Client` retries a request answered with `401` once,
+// taking the credentials from the URL, a `.netrc` file or a prompt, in this order.
+//
+// ref: https://www.rfc-editor.org/rfc/rfc9110#section-11
+
+use std::env;
+use std::fs;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use crate::http::Method;
+use crate::http::headers::{split_quoted, unquote};
+use crate::http::url::URL;
+use crate::utils::{base64, md5, sha256};
+
+// asks the user for `(user, password)` for the URL and realm (`None` cancels)
+pub type Prompt = Box<dyn Fn(&URL, Option<&str>) -> Option<(String, String)> + Send + Sync>;
+
+// requests are retried only once, so the nonce is always used for the first time
+const NONCE_COUNT: &str = "00000001";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    scheme: String,
+    // `Negotiate YIIC...`
+    token68: Option<String>,
+    // names in lower case, values unquoted
+    params: Vec<(String, String)>,
+}
+
+impl Challenge {
+    // all challenges of a `WWW-Authenticate` value (malformed parts are skipped)
+    //
+    // Example: `Digest realm="x", nonce="abc", Basic realm="x"`
+    pub fn parse_all(src: &str) -> Vec<Self> {
+        let mut challenges: Vec<Self> = vec![];
+        for part in split_quoted(src, ',') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            let (first, rest) = match part.split_once(char::is_whitespace) {
+                Some((first, rest)) => (first, rest.trim()),
+                None => (part, ""),
+            };
+            // `realm="x"` or `realm = "x"` continues the current challenge
+            if first.contains('=') || rest.starts_with('=') {
+                if let Some(challenge) = challenges.last_mut() {
+                    challenge.push_param(part);
+                }
+                continue;
+            }
+
+            let mut challenge = Self {
+                scheme: first.to_string(),
+                token68: None,
+                params: vec![],
+            };
+            if !rest.is_empty() {
+                // token68 may end with `=` padding
+                if rest.trim_end_matches('=').contains('=') {
+                    challenge.push_param(rest);
+                } else {
+                    challenge.token68 = Some(rest.to_string());
+                }
+            }
+            challenges.push(challenge);
+        }
+        challenges
+    }
+
+    fn push_param(&mut self, src: &str) {
+        if let Some((name, value)) = src.split_once('=') {
+            self.params.push((name.trim().to_ascii_lowercase(), unquote(value.trim())));
+        }
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn token68(&self) -> Option<&str> {
+        self.token68.as_deref()
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn realm(&self) -> Option<&str> {
+        self.param("realm")
+    }
+
+    // how much we prefer the challenge (`None` if not supported)
+    fn strength(&self) -> Option<u8> {
+        if self.scheme.eq_ignore_ascii_case("basic") {
+            return Some(1);
+        }
+        if !self.scheme.eq_ignore_ascii_case("digest") || self.param("nonce").is_none() {
+            return None;
+        }
+        if let Some(qop) = self.param("qop") {
+            if !qop.split(',').any(|q| q.trim().eq_ignore_ascii_case("auth")) {
+                return None;
+            }
+        }
+        match digest_algorithm(self)?.0 {
+            Algorithm::Md5 => Some(2),
+            Algorithm::Sha256 => Some(3),
+        }
+    }
+}
+
+// the strongest supported challenge
+pub fn choose(challenges: &[Challenge]) -> Option<&Challenge> {
+    challenges
+        .iter()
+        .filter_map(|c| c.strength().map(|s| (s, c)))
+        // the first one wins a tie
+        .fold(None, |best: Option<(u8, &Challenge)>, (s, c)| match best {
+            Some((b, _)) if b >= s => best,
+            _ => Some((s, c)),
+        })
+        .map(|(_, c)| c)
+}
+
+// value of `Authorization` answering `challenge` (`None` if the challenge is not supported)
+pub fn authorization(challenge: &Challenge, user: &str, pass: &str, method: Method, uri: &str) -> Option<String> {
+    challenge.strength()?;
+    if challenge.scheme().eq_ignore_ascii_case("basic") {
+        Some(basic(user, pass))
+    } else {
+        digest(challenge, user, pass, method, uri, &cnonce())
+    }
+}
+
+// `Basic dXNlcjpwYXNz`
+pub fn basic(user: &str, pass: &str) -> String {
+    format!("Basic {}", base64::encode(format!("{}:{}", user, pass).as_bytes()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Sha256,
+}
+
+impl Algorithm {
+    fn hash(&self, src: &str) -> String {
+        match self {
+            Self::Md5 => md5::hex_digest(src.as_bytes()),
+            Self::Sha256 => sha256::hex_digest(src.as_bytes()),
+        }
+    }
+}
+
+// (algorithm, whether it is a `-sess` variant); MD5 if not specified
+fn digest_algorithm(challenge: &Challenge) -> Option<(Algorithm, bool)> {
+    let name = challenge.param("algorithm").unwrap_or("MD5").to_ascii_uppercase();
+    let (name, sess) = match name.strip_suffix("-SESS") {
+        Some(name) => (name.to_string(), true),
+        None => (name, false),
+    };
+    match name.as_str() {
+        "MD5" => Some((Algorithm::Md5, sess)),
+        "SHA-256" => Some((Algorithm::Sha256, sess)),
+        _ => None,
+    }
+}
+
+// `Digest username="...", ..., response="..."` with the client nonce `cnonce`
+//
+// ref: https://www.rfc-editor.org/rfc/rfc7616#section-3.4
+pub fn digest(challenge: &Challenge, user: &str, pass: &str, method: Method, uri: &str, cnonce: &str) -> Option<String> {
+    let (algorithm, sess) = digest_algorithm(challenge)?;
+    let realm = challenge.realm().unwrap_or("");
+    let nonce = challenge.param("nonce")?;
+    let qop = match challenge.param("qop") {
+        Some(qop) if qop.split(',').any(|q| q.trim().eq_ignore_ascii_case("auth")) => Some("auth"),
+        // only `auth-int` or unknown protections
+        Some(_) => return None,
+        // RFC 2069 compatibility
+        None => None,
+    };
+
+    let mut ha1 = algorithm.hash(&format!("{}:{}:{}", user, realm, pass));
+    if sess {
+        ha1 = algorithm.hash(&format!("{}:{}:{}", ha1, nonce, cnonce));
+    }
+    let ha2 = algorithm.hash(&format!("{}:{}", method.as_str(), uri));
+    let response = match qop {
+        Some(qop) => algorithm.hash(&format!("{}:{}:{}:{}:{}:{}", ha1, nonce, NONCE_COUNT, cnonce, qop, ha2)),
+        None => algorithm.hash(&format!("{}:{}:{}", ha1, nonce, ha2)),
+    };
+
+    let mut fields = vec![
+        format!("username={}", quote(user)),
+        format!("realm={}", quote(realm)),
+        format!("uri={}", quote(uri)),
+        format!("algorithm={}", challenge.param("algorithm").unwrap_or("MD5")),
+        format!("nonce={}", quote(nonce)),
+    ];
+    if let Some(qop) = qop {
+        fields.push(format!("nc={}", NONCE_COUNT));
+        fields.push(format!("cnonce={}", quote(cnonce)));
+        fields.push(format!("qop={}", qop));
+    }
+    fields.push(format!("response={}", quote(&response)));
+    if let Some(opaque) = challenge.param("opaque") {
+        fields.push(format!("opaque={}", quote(opaque)));
+    }
+    Some(format!("Digest {}", fields.join(", ")))
+}
+
+fn quote(src: &str) -> String {
+    format!("\"{}\"", src.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// a client nonce (32 hex digits) which differs on every call
+fn cnonce() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let seed = format!(
+        "{:?}:{}:{}",
+        SystemTime::now(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    sha256::hex_digest(seed.as_bytes())[..32].to_string()
+}
+
+// Example `.netrc` (see ftp(1)):
+// ```
+// machine example.com login alice password secret
+// default login anonymous password guest
+// ```
+//
+// `macdef` definitions are skipped and `account` is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Netrc(Vec<NetrcEntry>);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct NetrcEntry {
+    // `None` for `default`
+    machine: Option<String>,
+    login: String,
+    password: String,
+}
+
+impl Netrc {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn parse(src: &str) -> Self {
+        let mut netrc = Self::new();
+        let mut keyword: Option<&str> = None;
+        let mut in_macdef = false;
+        for line in src.lines() {
+            if in_macdef {
+                // a macro ends with an empty line
+                in_macdef = !line.trim().is_empty();
+                continue;
+            }
+            for word in line.split_whitespace() {
+                match keyword.take() {
+                    Some("machine") => netrc.0.push(NetrcEntry {
+                        machine: Some(word.to_ascii_lowercase()),
+                        ..NetrcEntry::default()
+                    }),
+                    Some("login") => {
+                        if let Some(entry) = netrc.0.last_mut() {
+                            entry.login = word.to_string();
+                        }
+                    }
+                    Some("password") => {
+                        if let Some(entry) = netrc.0.last_mut() {
+                            entry.password = word.to_string();
+                        }
+                    }
+                    Some("macdef") => in_macdef = true,
+                    // the value of `account`
+                    Some(_) => {}
+                    None => match word {
+                        "default" => netrc.0.push(NetrcEntry::default()),
+                        "machine" | "login" | "password" | "account" | "macdef" => keyword = Some(word),
+                        _ => {}
+                    },
+                }
+            }
+            // the macro body starts on the next line
+            if in_macdef {
+                keyword = None;
+            }
+        }
+        netrc
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    // `$NETRC` or `~/.netrc`, or no entries if it cannot be read
+    pub fn system() -> Self {
+        let path = match env::var_os("NETRC") {
+            Some(path) => PathBuf::from(path),
+            None => match env::var_os("HOME") {
+                Some(home) => PathBuf::from(home).join(".netrc"),
+                None => return Self::new(),
+            },
+        };
+        Self::load(path).unwrap_or_default()
+    }
+
+    // `(login, password)` for `host`, falling back to `default`
+    pub fn lookup(&self, host: &str) -> Option<(&str, &str)> {
+        let host = host.to_ascii_lowercase();
+        self.0
+            .iter()
+            .find(|e| e.machine.as_deref() == Some(host.as_str()))
+            .or_else(|| self.0.iter().find(|e| e.machine.is_none()))
+            .map(|e| (e.login.as_str(), e.password.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_challenge() {
+        let challenges = Challenge::parse_all(
+            "Digest realm=\"a, b\", qop=\"auth,auth-int\", nonce=\"n\", Basic realm = \"x\", Negotiate YWJj==",
+        );
+        assert_eq!(challenges.len(), 3);
+        assert_eq!(challenges[0].scheme(), "Digest");
+        assert_eq!(challenges[0].realm(), Some("a, b"));
+        assert_eq!(challenges[0].param("QOP"), Some("auth,auth-int"));
+        assert_eq!(challenges[1].realm(), Some("x"));
+        assert_eq!(challenges[2].token68(), Some("YWJj=="));
+        assert_eq!(choose(&challenges), Some(&challenges[0]));
+
+        let challenges = Challenge::parse_all("Basic realm=\"x\", Digest nonce=\"n\", algorithm=SHA-256");
+        assert_eq!(choose(&challenges).unwrap().param("algorithm"), Some("SHA-256"));
+        let challenges = Challenge::parse_all("Digest nonce=\"n\", algorithm=SHA-512-256, qop=auth-int");
+        assert_eq!(choose(&challenges), None);
+        assert_eq!(basic("Aladdin", "open sesame"), "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+    }
+
+    #[test]
+    fn test_digest() {
+        // RFC 7616 section 3.9.1
+        let challenge = |algorithm: &str| {
+            Challenge::parse_all(&format!(
+                "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm={}, \
+                 nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+                 opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+                algorithm
+            ))
+            .remove(0)
+        };
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+        let auth = |algorithm: &str| {
+            digest(&challenge(algorithm), "Mufasa", "Circle of Life", Method::GET, "/dir/index.html", cnonce).unwrap()
+        };
+        assert_eq!(
+            auth("MD5"),
+            "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", uri=\"/dir/index.html\", \
+             algorithm=MD5, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", nc=00000001, \
+             cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", qop=auth, \
+             response=\"8ca523f5e9506fed4657c9700eebdbec\", opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""
+        );
+        assert!(auth("SHA-256")
+            .contains("response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""));
+
+        // RFC 2617 section 3.5
+        let challenge = Challenge::parse_all(
+            "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+             nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"",
+        );
+        let auth = digest(&challenge[0], "Mufasa", "Circle Of Life", Method::GET, "/dir/index.html", "0a4f113b");
+        assert!(auth.unwrap().contains("response=\"6629fae49393a05397450978507c4ef1\""));
+    }
+
+    #[test]
+    fn test_netrc() {
+        let netrc = Netrc::parse(
+            "machine example.com\n  login alice\n  password s3cret\n\
+             macdef init\nmachine evil.com login mallory password x\n\n\
+             machine other.com login bob account a password pw\n\
+             default login anonymous password guest\n",
+        );
+        assert_eq!(netrc.lookup("Example.com"), Some(("alice", "s3cret")));
+        assert_eq!(netrc.lookup("other.com"), Some(("bob", "pw")));
+        assert_eq!(netrc.lookup("evil.com"), Some(("anonymous", "guest")));
+        assert_eq!(Netrc::new().lookup("example.com"), None);
+    }
+}
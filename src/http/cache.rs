@@ -0,0 +1,564 @@
+// Private HTTP cache (RFC 9111)
+//
+// Responses to GET requests are stored on disk, one file per method + URL.
+// The request fields named by `Vary` are stored with the response and must match
+// for the entry to be used. The total size of the directory is bounded;
+// the least recently used entries (by file modification time) are evicted first.
+//
+// Example entry file:
+// ```
+// AKTIS-CACHE 1
+// url http://example.com/
+// request-time 1620282289
+// response-time 1620282290
+// vary accept-encoding: gzip
+//
+// HTTP/1.1 200 OK
+// Cache-Control: max-age=604800
+// Etag: "3147526947"
+// Content-Length: 648
+//
+// <body>
+// ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::http::Method;
+use crate::http::date::HttpDate;
+use crate::http::headers::CacheControl;
+use crate::http::request::Request;
+use crate::http::response::{Header, Response};
+use crate::http::status::StatusCode;
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum CacheError {
+    InvalidEntry(PathBuf),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEntry(p) => write!(f, "Cache error: invalid cache entry `{}`", p.display()),
+        }
+    }
+}
+
+impl Error for CacheError {}
+// ------------------------------------
+
+const MAGIC: &str = "AKTIS-CACHE 1";
+
+// status codes which are cacheable by default (heuristic freshness is allowed)
+// ref: https://www.rfc-editor.org/rfc/rfc9110#section-15.1
+const HEURISTIC_STATUS: [u16; 12] = [200, 203, 204, 206, 300, 301, 308, 404, 405, 410, 414, 501];
+
+// status codes whose responses are stored at all
+const STORABLE_STATUS: [u16; 14] = [200, 203, 204, 206, 300, 301, 302, 303, 307, 308, 404, 405, 410, 414];
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+type EntryFile = (PathBuf, u64, SystemTime);
+
+pub struct CacheEntry {
+    url: String,
+    request_time: u64,
+    response_time: u64,
+    // request fields selected by `Vary`: (lowercase name, value)
+    vary: Vec<(String, String)>,
+    response: Response,
+}
+
+pub enum Lookup {
+    // usable without contacting the server
+    Fresh(Response),
+    // must be validated with the server first
+    Stale(CacheEntry),
+    Miss,
+}
+
+impl Cache {
+    // use `dir` (created if needed) to store at most `max_size` bytes
+    pub fn open<P: AsRef<Path>>(dir: P, max_size: u64) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_size,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn lookup(&self, req: &Request) -> Lookup {
+        self.lookup_at(req, now())
+    }
+
+    fn lookup_at(&self, req: &Request, now: u64) -> Lookup {
+        // partial responses are not stored
+        if req.method() != Method::GET || req.head().get("Range").is_some() {
+            return Lookup::Miss;
+        }
+        let path = self.path_for(req);
+        let mut entry = match CacheEntry::read(&path) {
+            Ok(entry) => entry,
+            Err(_) => return Lookup::Miss,
+        };
+        if !entry.matches_vary(req) {
+            return Lookup::Miss;
+        }
+        touch(&path);
+
+        let req_cc = request_cache_control(req);
+        let res_cc = entry.response.headers().cache_control().unwrap_or_default();
+        let age = entry.current_age(now);
+        entry.response.headers_mut().set("Age", &age.to_string());
+
+        let lifetime = freshness_lifetime(entry.response.headers(), entry.response.status());
+        // `max-age` of the request limits the acceptable age
+        let lifetime = match req_cc.max_age() {
+            Some(max_age) => lifetime.min(max_age),
+            None => lifetime,
+        };
+        let min_fresh = req_cc.min_fresh().unwrap_or(0);
+        let fresh = age + min_fresh < lifetime;
+        // `max-stale` allows stale responses unless the server forbids it
+        let stale_ok = match req_cc.get("max-stale") {
+            Some(_) => age < lifetime + req_cc.max_stale().unwrap_or(0),
+            None => req_cc.contains("max-stale"),
+        } && !res_cc.must_revalidate();
+
+        if (fresh || stale_ok) && !req_cc.no_cache() && !res_cc.no_cache() {
+            Lookup::Fresh(entry.response)
+        } else {
+            Lookup::Stale(entry)
+        }
+    }
+
+    // store `res` if it is storable
+    // ref: https://www.rfc-editor.org/rfc/rfc9111#section-3
+    pub fn store(&self, req: &Request, res: &Response, request_time: u64, response_time: u64) -> Result<(), Box<dyn Error>> {
+        if !is_storable(req, res) {
+            return Ok(());
+        }
+        let vary_names = res.headers().vary();
+        if vary_names.iter().any(|v| v == "*") {
+            return Ok(());
+        }
+        let vary = vary_names
+            .iter()
+            .map(|name| {
+                let value = req.head().get(name).unwrap_or("");
+                (name.to_ascii_lowercase(), value.to_string())
+            })
+            .collect();
+
+        let entry = CacheEntry {
+            url: req.url().to_string(),
+            request_time,
+            response_time,
+            vary,
+            response: clone_response(res)?,
+        };
+        entry.write(&self.path_for(req))?;
+        self.evict()?;
+        Ok(())
+    }
+
+    // update a stale entry with a `304 Not Modified` response and return the stored response
+    // ref: https://www.rfc-editor.org/rfc/rfc9111#section-4.3.4
+    pub fn freshen(&self, req: &Request, mut entry: CacheEntry, not_modified: &Response, request_time: u64, response_time: u64) -> Result<Response, Box<dyn Error>> {
+        for (name, _) in not_modified.headers().iter() {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                continue;
+            }
+            entry.response.headers_mut().remove(name);
+        }
+        for (name, value) in not_modified.headers().iter() {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                continue;
+            }
+            entry.response.headers_mut().add(name, value);
+        }
+        entry.response.headers_mut().remove("Age");
+        entry.request_time = request_time;
+        entry.response_time = response_time;
+        entry.write(&self.path_for(req))?;
+
+        let age = entry.current_age(response_time);
+        entry.response.headers_mut().set("Age", &age.to_string());
+        Ok(entry.response)
+    }
+
+    // unsafe methods invalidate the stored response of the target URL
+    // ref: https://www.rfc-editor.org/rfc/rfc9111#section-4.4
+    pub fn invalidate(&self, req: &Request, res: &Response) {
+        let status = res.status();
+        if req.method() != Method::GET && (status.is_success() || status.is_redirection()) {
+            let _ = fs::remove_file(self.path_for_url(&req.url().to_string()));
+        }
+    }
+
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        for (path, _, _) in self.entries()? {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    // total size of the stored entries in bytes
+    pub fn size(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(self.entries()?.iter().map(|(_, size, _)| size).sum())
+    }
+
+    // remove least recently used entries until the total size fits `max_size`
+    fn evict(&self) -> Result<(), Box<dyn Error>> {
+        let mut entries = self.entries()?;
+        let mut total = entries.iter().map(|(_, size, _)| size).sum::<u64>();
+        entries.sort_by_key(|(_, _, used)| *used);
+        for (path, size, _) in entries {
+            if total <= self.max_size {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+
+    // (path, size, last use) of every entry
+    fn entries(&self) -> Result<Vec<EntryFile>, Box<dyn Error>> {
+        let mut entries = vec![];
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            if path.extension().is_none_or(|e| e != "entry") {
+                continue;
+            }
+            let meta = dir_entry.metadata()?;
+            entries.push((path, meta.len(), meta.modified()?));
+        }
+        Ok(entries)
+    }
+
+    fn path_for(&self, req: &Request) -> PathBuf {
+        self.path_for_url(&req.url().to_string())
+    }
+
+    // the key is the method (always GET) and the URL
+    fn path_for_url(&self, url: &str) -> PathBuf {
+        let key = format!("GET {}", url);
+        self.dir.join(format!("{:016x}.entry", fnv1a(key.as_bytes())))
+    }
+}
+
+impl CacheEntry {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    // add `If-None-Match` and `If-Modified-Since` to validate this entry
+    pub fn add_validators(&self, req: &mut Request) {
+        let header = self.response.headers();
+        if let Some(etag) = header.etag() {
+            req.head_mut().set_if_none_match(&[etag]);
+        }
+        if let Some(date) = header.last_modified() {
+            req.head_mut().set_if_modified_since(date);
+        }
+    }
+
+    // ref: https://www.rfc-editor.org/rfc/rfc9111#section-4.2.3
+    fn current_age(&self, now: u64) -> u64 {
+        let header = self.response.headers();
+        let date = header.date().map_or(self.response_time, |d| d.secs());
+        let apparent_age = self.response_time.saturating_sub(date);
+        let response_delay = self.response_time.saturating_sub(self.request_time);
+        let corrected_age = header.age().unwrap_or(0) + response_delay;
+        let initial_age = apparent_age.max(corrected_age);
+        initial_age + now.saturating_sub(self.response_time)
+    }
+
+    fn matches_vary(&self, req: &Request) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| req.head().get(name).unwrap_or("") == value)
+    }
+
+    fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let invalid = || CacheError::InvalidEntry(path.to_path_buf());
+        let mut buf = vec![];
+        fs::File::open(path)?.read_to_end(&mut buf)?;
+
+        // metadata lines end with an empty line
+        let meta_end = match buf.windows(2).position(|w| w == b"\n\n") {
+            Some(i) => i,
+            None => return Err(Box::new(invalid())),
+        };
+        let meta = String::from_utf8_lossy(&buf[..meta_end]).into_owned();
+        let mut lines = meta.lines();
+        if lines.next() != Some(MAGIC) {
+            return Err(Box::new(invalid()));
+        }
+
+        let (mut url, mut request_time, mut response_time, mut vary) = (None, None, None, vec![]);
+        for line in lines {
+            match line.split_once(' ') {
+                Some(("url", u)) => url = Some(u.to_string()),
+                Some(("request-time", t)) => request_time = t.parse::<u64>().ok(),
+                Some(("response-time", t)) => response_time = t.parse::<u64>().ok(),
+                Some(("vary", field)) => {
+                    let (name, value) = field.split_once(':').ok_or_else(invalid)?;
+                    vary.push((name.to_string(), value.trim().to_string()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            url: url.ok_or_else(invalid)?,
+            request_time: request_time.ok_or_else(invalid)?,
+            response_time: response_time.ok_or_else(invalid)?,
+            vary,
+            response: Response::parse(&buf[meta_end + 2..])?,
+        })
+    }
+
+    fn write(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut buf = format!(
+            "{}\nurl {}\nrequest-time {}\nresponse-time {}\n",
+            MAGIC, self.url, self.request_time, self.response_time
+        );
+        for (name, value) in &self.vary {
+            buf.push_str(&format!("vary {}: {}\n", name, value));
+        }
+        buf.push('\n');
+
+        let mut buf = buf.into_bytes();
+        buf.extend(serialize(&self.response));
+
+        // write to a temporary file first so that readers never see a partial entry
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, buf)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+// ref: https://www.rfc-editor.org/rfc/rfc9111#section-4.2.1
+fn freshness_lifetime(header: &Header, status: StatusCode) -> u64 {
+    let cache_control = header.cache_control().unwrap_or_default();
+    if let Some(max_age) = cache_control.max_age() {
+        return max_age;
+    }
+    if let Some(expires) = header.expires() {
+        let date = header.date().map_or_else(now, |d| d.secs());
+        return expires.secs().saturating_sub(date);
+    }
+    // heuristic freshness: 10% of the time since the last modification
+    if let Some(last_modified) = header.last_modified() {
+        if HEURISTIC_STATUS.contains(&status.as_u16()) || cache_control.is_public() {
+            let date = header.date().map_or_else(now, |d| d.secs());
+            return date.saturating_sub(last_modified.secs()) / 10;
+        }
+    }
+    0
+}
+
+fn is_storable(req: &Request, res: &Response) -> bool {
+    let req_cc = request_cache_control(req);
+    let res_cc = res.headers().cache_control().unwrap_or_default();
+    let header = res.headers();
+    req.method() == Method::GET
+        && req.head().get("Range").is_none()
+        && STORABLE_STATUS.contains(&res.status().as_u16())
+        && !req_cc.no_store()
+        && !res_cc.no_store()
+        // something must allow the response to be reused or validated
+        && (res_cc.max_age().is_some()
+            || header.expires().is_some()
+            || header.etag().is_some()
+            || header.last_modified().is_some()
+            || res_cc.is_public())
+}
+
+fn request_cache_control(req: &Request) -> CacheControl {
+    req.head()
+        .get("Cache-Control")
+        .map(CacheControl::parse)
+        .unwrap_or_default()
+}
+
+fn clone_response(res: &Response) -> Result<Response, Box<dyn Error>> {
+    Response::parse(&serialize(res))
+}
+
+// HTTP/1.1 message with the body framed by `Content-Length`
+fn serialize(res: &Response) -> Vec<u8> {
+    let mut head = format!("{} {} {}\r\n", res.version().as_str(), res.status().as_u16(), res.reason());
+    for (name, value) in res.headers().iter() {
+        if name.eq_ignore_ascii_case("Transfer-Encoding") || name.eq_ignore_ascii_case("Content-Length") {
+            continue;
+        }
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\n\r\n", res.bytes().len()));
+
+    let mut buf = head.into_bytes();
+    buf.extend_from_slice(res.bytes());
+    buf
+}
+
+// mark the entry as recently used
+fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+fn now() -> u64 {
+    HttpDate::now().secs()
+}
+
+// 64-bit FNV-1a hash
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aktis-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn response(raw: &str) -> Response {
+        Response::parse(raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        let res = response("HTTP/1.1 200 OK\r\nCache-Control: max-age=604800\r\nExpires: Thu, 13 May 2021 05:24:49 GMT\r\n\r\n");
+        assert_eq!(freshness_lifetime(res.headers(), res.status()), 604800);
+
+        let res = response("HTTP/1.1 200 OK\r\nDate: Thu, 06 May 2021 05:24:49 GMT\r\nExpires: Thu, 13 May 2021 05:24:49 GMT\r\n\r\n");
+        assert_eq!(freshness_lifetime(res.headers(), res.status()), 604800);
+
+        let res = response("HTTP/1.1 200 OK\r\nDate: Thu, 11 Jan 1970 00:00:00 GMT\r\nLast-Modified: Thu, 01 Jan 1970 00:00:00 GMT\r\n\r\n");
+        assert_eq!(freshness_lifetime(res.headers(), res.status()), 86400);
+
+        let res = response("HTTP/1.1 302 Found\r\nDate: Thu, 11 Jan 1970 00:00:00 GMT\r\nLast-Modified: Thu, 01 Jan 1970 00:00:00 GMT\r\n\r\n");
+        assert_eq!(freshness_lifetime(res.headers(), res.status()), 0);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let cache = Cache::open(temp_dir("lookup"), 1 << 20).unwrap();
+        let req = Request::get("http://example.com/").unwrap();
+        assert!(matches!(cache.lookup(&req), Lookup::Miss));
+
+        let res = response("HTTP/1.1 200 OK\r\nCache-Control: max-age=100\r\nEtag: \"abc\"\r\nVary: Accept-Language\r\n\r\nbody");
+        cache.store(&req, &res, 1000, 1000).unwrap();
+
+        match cache.lookup_at(&req, 1050) {
+            Lookup::Fresh(res) => {
+                assert_eq!(res.bytes(), b"body");
+                assert_eq!(res.headers().age(), Some(50));
+            }
+            _ => panic!("expected a fresh response"),
+        }
+
+        // stale after max-age: validated with the stored ETag
+        match cache.lookup_at(&req, 1100) {
+            Lookup::Stale(entry) => {
+                let mut req = Request::get("http://example.com/").unwrap();
+                entry.add_validators(&mut req);
+                assert_eq!(req.head().get("If-None-Match"), Some("\"abc\""));
+
+                let not_modified = response("HTTP/1.1 304 Not Modified\r\nCache-Control: max-age=200\r\n\r\n");
+                let res = cache.freshen(&req, entry, &not_modified, 1100, 1100).unwrap();
+                assert_eq!(res.status(), StatusCode::OK);
+                assert_eq!(res.bytes(), b"body");
+            }
+            _ => panic!("expected a stale response"),
+        }
+        assert!(matches!(cache.lookup_at(&req, 1200), Lookup::Fresh(_)));
+
+        // the request must match the `Vary` fields
+        let mut other = Request::get("http://example.com/").unwrap();
+        other.head_mut().add("Accept-Language", "ja");
+        assert!(matches!(cache.lookup_at(&other, 1200), Lookup::Miss));
+
+        // `no-cache` in the request forces validation
+        let mut no_cache = Request::get("http://example.com/").unwrap();
+        no_cache.head_mut().add("Cache-Control", "no-cache");
+        assert!(matches!(cache.lookup_at(&no_cache, 1200), Lookup::Stale(_)));
+
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_not_storable() {
+        let cache = Cache::open(temp_dir("storable"), 1 << 20).unwrap();
+        let req = Request::get("http://example.com/").unwrap();
+        let res = response("HTTP/1.1 200 OK\r\nCache-Control: no-store, max-age=100\r\n\r\nbody");
+        cache.store(&req, &res, 1000, 1000).unwrap();
+        let res = response("HTTP/1.1 200 OK\r\n\r\nbody");
+        cache.store(&req, &res, 1000, 1000).unwrap();
+        assert!(matches!(cache.lookup(&req), Lookup::Miss));
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_evict() {
+        let dir = temp_dir("evict");
+        let body = "x".repeat(100);
+        let store = |cache: &Cache, path: &str| {
+            let req = Request::get(&format!("http://example.com{}", path)).unwrap();
+            let res = response(&format!("HTTP/1.1 200 OK\r\nCache-Control: max-age=100\r\n\r\n{}", body));
+            cache.store(&req, &res, 1000, 1000).unwrap();
+        };
+
+        // room for two and a half entries
+        let cache = Cache::open(&dir, 1 << 20).unwrap();
+        store(&cache, "/size");
+        let size = cache.size().unwrap();
+        cache.clear().unwrap();
+        let cache = Cache::open(&dir, size * 5 / 2).unwrap();
+
+        for path in &["/a", "/b", "/c"] {
+            store(&cache, path);
+            // file modification times need to differ
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            if *path == "/b" {
+                // use `/a` so that `/b` is the least recently used
+                cache.lookup(&Request::get("http://example.com/a").unwrap());
+            }
+        }
+        assert!(cache.size().unwrap() <= size * 5 / 2);
+        assert!(matches!(cache.lookup_at(&Request::get("http://example.com/b").unwrap(), 1000), Lookup::Miss));
+        assert!(matches!(cache.lookup_at(&Request::get("http://example.com/c").unwrap(), 1000), Lookup::Fresh(_)));
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
@@ -1,14 +1,24 @@
-use std::net;
 use std::io::prelude::*;
-use std::net::{TcpStream, ToSocketAddrs};
-use crate::http::{request::Request, response::Response, url::URL};
+use std::io::{BufReader, ErrorKind};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::http::{Method, Version};
+use crate::http::auth::{self, Challenge, Netrc, Prompt};
+use crate::http::cache::{Cache, Lookup};
+use crate::http::cookie::CookieJar;
+use crate::http::date::HttpDate;
+use crate::http::proxy::{self, ProxyConfig, ProxyKind};
+use crate::http::socks;
+use crate::dns::resolver::Resolver;
+use crate::http::transport::{DefaultTransport, Stream, Transport, TransportError};
+use crate::http::{request::Request, response::{BodyReader, Header, Response, ResponseHead}, status::StatusCode};
 use std::fmt;
 use std::error::Error;
 
 // ERROR HANDLING ---------------------
 #[derive(Debug)]
 pub enum ClientError {
-    
+
 }
 
 impl fmt::Display for ClientError {
@@ -23,62 +33,604 @@ impl Error for ClientError {
 }
 // ------------------------------------
 
+// the body of a response from `Client::open`
+pub type Body = BodyReader<BufReader<Box<dyn Stream>>>;
+
 pub struct Client {
+    cookie_jar: CookieJar,
+    cache: Option<Cache>,
+    proxy: ProxyConfig,
+    transport: Box<dyn Transport>,
+    // credentials for `401 Unauthorized` (after the ones in the URL)
+    netrc: Option<Netrc>,
+    prompt: Option<Prompt>,
+    // answer `Basic` challenges although the password is sent in the clear
+    insecure_basic: bool,
+    // send `Expect: 100-continue` for POST/PUT bodies of at least this many bytes
+    expect_continue: Option<usize>,
+    // how long to wait for `100 Continue` before sending the body anyway
+    continue_timeout: Duration,
 }
 
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Client {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            cookie_jar: CookieJar::new(),
+            cache: None,
+            proxy: ProxyConfig::new(),
+            transport: Box::new(DefaultTransport::new()),
+            netrc: None,
+            prompt: None,
+            insecure_basic: false,
+            expect_continue: None,
+            continue_timeout: Duration::from_secs(1),
+        }
+    }
+
+    pub fn cookie_jar(&self) -> &CookieJar {
+        &self.cookie_jar
+    }
+
+    pub fn cookie_jar_mut(&mut self) -> &mut CookieJar {
+        &mut self.cookie_jar
+    }
+
+    pub fn set_cookie_jar(&mut self, jar: CookieJar) {
+        self.cookie_jar = jar;
+    }
+
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    // `None` disables the cache (default)
+    pub fn set_cache(&mut self, cache: Option<Cache>) {
+        self.cache = cache;
+    }
+
+    pub fn proxy(&self) -> &ProxyConfig {
+        &self.proxy
+    }
+
+    // `ProxyConfig::new()` connects directly (default),
+    // `ProxyConfig::from_env()` follows `http_proxy` and the like
+    pub fn set_proxy(&mut self, proxy: ProxyConfig) {
+        self.proxy = proxy;
+    }
+
+    // `DefaultTransport` (TCP, or a Unix socket for `http+unix://`) by default
+    pub fn set_transport(&mut self, transport: Box<dyn Transport>) {
+        self.transport = transport;
+    }
+
+    // resolve host names with our own stub resolver instead of the OS's
+    // (replaces the transport with `DefaultTransport` using `resolver`)
+    pub fn set_resolver(&mut self, resolver: Arc<Resolver>) {
+        self.transport = Box::new(DefaultTransport::new().with_resolver(resolver));
+    }
+
+    // look up credentials in `netrc` (e.g. `Netrc::system()`) when the URL has none
+    pub fn set_netrc(&mut self, netrc: Option<Netrc>) {
+        self.netrc = netrc;
+    }
+
+    // ask for credentials found neither in the URL nor in `.netrc`
+    pub fn set_prompt(&mut self, prompt: Option<Prompt>) {
+        self.prompt = prompt;
+    }
+
+    // every connection is plaintext until there is TLS, so `Basic` challenges
+    // (the password barely encoded) are ignored unless this is set
+    pub fn set_insecure_basic(&mut self, insecure: bool) {
+        self.insecure_basic = insecure;
+    }
+
+    // `None` disables `Expect: 100-continue` (default)
+    pub fn set_expect_continue(&mut self, threshold: Option<usize>) {
+        self.expect_continue = threshold;
+    }
+
+    pub fn set_continue_timeout(&mut self, timeout: Duration) {
+        self.continue_timeout = timeout;
     }
 
     pub fn get(url: &str) -> Result<Response, Box<dyn Error>> {
-        
-        // get IP address using OS's DNS resolver
-        let parsed_url = URL::parse(url)?;
-        let ip = format!("{}:{}", parsed_url.host(), parsed_url.port()) // create valid URL to find IP address
-            .to_socket_addrs()? // get IP addresses from resolver
-            .nth(0) // get IPv4 address
-            .unwrap();
-        
-        // create GET request from URL
-        let mut req = Request::get(url)?;
-        let req = req.build()?;
-        
-        // send request using OS's TCP socket
-        let mut stream = TcpStream::connect(ip)?;
-        stream.write_all(req.as_bytes())?;
-        
+        Self::new().send(Request::get(url)?)
+    }
+
+    pub fn send(&mut self, mut req: Request) -> Result<Response, Box<dyn Error>> {
+        if let Some(cookie) = self.cookie_jar.header_value(req.url()) {
+            req.head_mut().add("Cookie", &cookie);
+        }
+
+        let cache = match self.cache.clone() {
+            Some(cache) => cache,
+            None => return self.fetch(&mut req),
+        };
+
+        let stale = match cache.lookup(&req) {
+            Lookup::Fresh(res) => return Ok(res),
+            Lookup::Stale(entry) => {
+                entry.add_validators(&mut req);
+                Some(entry)
+            }
+            Lookup::Miss => None,
+        };
+
+        let request_time = HttpDate::now().secs();
+        let res = self.fetch(&mut req)?;
+        let response_time = HttpDate::now().secs();
+
+        if let Some(entry) = stale {
+            if res.status() == StatusCode::NOT_MODIFIED {
+                return cache.freshen(&req, entry, &res, request_time, response_time);
+            }
+        }
+        cache.invalidate(&req, &res);
+        // a broken cache must not break browsing
+        let _ = cache.store(&req, &res, request_time, response_time);
+        Ok(res)
+    }
+
+    // send `req` and return the response head with the body left unread
+    // (for bodies too large to hold in memory; the cache is not used)
+    pub fn open(&mut self, mut req: Request) -> Result<(ResponseHead, Body), Box<dyn Error>> {
+        if let Some(cookie) = self.cookie_jar.header_value(req.url()) {
+            req.head_mut().add("Cookie", &cookie);
+        }
+        self.authenticated(&mut req, Self::transfer_head)
+    }
+
+    // send `req` to the server and store the cookies of the response
+    fn fetch(&mut self, req: &mut Request) -> Result<Response, Box<dyn Error>> {
+        self.authenticated(req, Self::transfer)
+    }
+
+    // `send` `req` and store the cookies of the response
+    // (retried once with `Authorization` on `401 Unauthorized`)
+    fn authenticated<T: Received>(
+        &mut self,
+        req: &mut Request,
+        send: impl Fn(&Self, &mut Request) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        let res = send(self, req)?;
+        for set_cookie in res.headers().set_cookies() {
+            self.cookie_jar.set_cookie(req.url(), set_cookie);
+        }
+
+        if res.status() != StatusCode::UNAUTHORIZED || req.head().get("Authorization").is_some() {
+            return Ok(res);
+        }
+        let authorization = match self.authorization(req, res.headers()) {
+            Some(authorization) => authorization,
+            None => return Ok(res),
+        };
+        drop(res);
+        req.head_mut().add("Authorization", &authorization);
+        if let Some(cookie) = self.cookie_jar.header_value(req.url()) {
+            req.head_mut().add("Cookie", &cookie);
+        }
+
+        let res = send(self, req)?;
+        for set_cookie in res.headers().set_cookies() {
+            self.cookie_jar.set_cookie(req.url(), set_cookie);
+        }
+        Ok(res)
+    }
+
+    // answer the strongest challenge of a `401` response with the credentials
+    // in the URL, `.netrc` or from the prompt (`Basic` only if `set_insecure_basic`)
+    fn authorization(&self, req: &Request, headers: &Header) -> Option<String> {
+        let challenges: Vec<Challenge> = headers
+            .get_all("WWW-Authenticate")
+            .into_iter()
+            .flat_map(Challenge::parse_all)
+            .filter(|c| self.insecure_basic || !c.scheme().eq_ignore_ascii_case("basic"))
+            .collect();
+        let challenge = auth::choose(&challenges)?;
+
+        let url = req.url();
+        let (user, pass) = match url.credentials() {
+            Some((user, pass)) => (user.to_string(), pass.to_string()),
+            None => match self.netrc.as_ref().and_then(|netrc| netrc.lookup(&url.host())) {
+                Some((user, pass)) => (user.to_string(), pass.to_string()),
+                None => (self.prompt.as_ref()?)(url, challenge.realm())?,
+            },
+        };
+        auth::authorization(challenge, &user, &pass, req.method(), &url.path())
+    }
+
+    // send `req` over a new connection and read the final response head
+    fn transfer_head(&self, req: &mut Request) -> Result<(ResponseHead, Body), Box<dyn Error>> {
+        let mut reader = BufReader::new(self.connect(req)?);
+        reader.get_mut().write_all(req.build()?.as_bytes())?;
+        loop {
+            let head = ResponseHead::read_from(&mut reader)?;
+            if !head.is_interim() {
+                let body = BodyReader::new(reader, &head);
+                return Ok((head, body));
+            }
+        }
+    }
+
+    // send `req` over a new connection and read the response
+    fn transfer(&self, req: &mut Request) -> Result<Response, Box<dyn Error>> {
+        let expect = match (req.method(), self.expect_continue) {
+            (Method::POST, Some(threshold)) | (Method::PUT, Some(threshold)) => {
+                !req.body().is_empty() && req.body().len() >= threshold
+            }
+            _ => false,
+        };
+
+        if expect {
+            // servers ignore the expectation in HTTP/1.0 requests
+            req.set_version(Version::HTTP11);
+            req.head_mut().add("Connection", "close");
+            req.head_mut().add("Expect", "100-continue");
+            let res = self.send_expect_continue(req)?;
+            if res.status() != StatusCode::EXPECTATION_FAILED {
+                return Ok(res);
+            }
+            // the server refused the expectation: retry without it
+            req.head_mut().remove("Expect");
+        }
+
+        let mut reader = BufReader::new(self.connect(req)?);
+        reader.get_mut().write_all(req.build()?.as_bytes())?;
+
         // read response
-        let mut res = String::new();
-        stream.read_to_string(&mut res)?;
-        
-        // shutdown TCP connection
-        stream.shutdown(net::Shutdown::Both)?;
-        
-        // parse response
-        let res = Response::parse(&res)?;
+        let res = Response::read_from(&mut reader)?;
+
+        // shutdown connection
+        reader.get_ref().shutdown()?;
 
         Ok(res)
     }
 
-    // pub fn send(request: &Request) -> Response {
-    // 
-    // }
+    // send the head, wait for `100 Continue` (or a final response) and then send the body
+    // ref: https://www.rfc-editor.org/rfc/rfc9110#section-10.1.1
+    fn send_expect_continue(&self, req: &mut Request) -> Result<Response, Box<dyn Error>> {
+        let mut reader = BufReader::new(self.connect(req)?);
+        reader.get_mut().write_all(req.build_head()?.as_bytes())?;
+
+        let mut interim = vec![];
+        loop {
+            // wait until the server starts a response (or the timeout expires)
+            reader.get_ref().set_read_timeout(Some(self.continue_timeout))?;
+            let responded = match reader.fill_buf() {
+                Ok(buf) => !buf.is_empty(),
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => false,
+                Err(e) => return Err(Box::new(e)),
+            };
+            reader.get_ref().set_read_timeout(None)?;
+            if !responded {
+                break;
+            }
+
+            let head = ResponseHead::read_from(&mut reader)?;
+            if head.status() == StatusCode::CONTINUE {
+                interim.push(head);
+                break;
+            }
+            if head.is_interim() {
+                interim.push(head);
+                continue;
+            }
+            // final response without reading the body (e.g. `401` or `417`)
+            let res = Response::from_head(head, &mut reader, interim)?;
+            reader.get_ref().shutdown()?;
+            return Ok(res);
+        }
+
+        reader.get_mut().write_all(req.body().as_bytes())?;
+        let res = Response::read_after(&mut reader, interim)?;
+        reader.get_ref().shutdown()?;
+        Ok(res)
+    }
+
+    // connect to the origin of `req` or to the proxy for it.
+    // `req` is rewritten to be forwarded by the proxy if needed.
+    fn connect(&self, req: &mut Request) -> Result<Box<dyn Stream>, Box<dyn Error>> {
+        // no TLS yet: refuse before any transport (or proxy tunnel) sees the request
+        if req.url().scheme() == "https" {
+            return Err(Box::new(TransportError::UnsupportedScheme("https".to_string())));
+        }
+        let proxy = match self.proxy.proxy_for(req.url()) {
+            Some(proxy) => proxy,
+            None => return self.transport.connect(req.url()),
+        };
+
+        let mut stream = self.transport.connect(proxy.url())?;
+        let (host, port) = (req.url().host(), req.url().port() as u16);
+        let credentials = proxy.credentials();
+        match proxy.kind() {
+            ProxyKind::Socks4 | ProxyKind::Socks4a => {
+                let user_id = credentials.map_or("", |(user, _)| user);
+                socks::socks4_connect(&mut stream, &host, port, user_id, proxy.kind() == ProxyKind::Socks4a)?;
+            }
+            ProxyKind::Socks5 | ProxyKind::Socks5h => {
+                socks::socks5_connect(&mut stream, &host, port, credentials, proxy.kind() == ProxyKind::Socks5h)?;
+            }
+            ProxyKind::Http if proxy.tunnels(req.url()) => {
+                proxy::connect_tunnel(&mut stream, proxy, req.url())?;
+            }
+            ProxyKind::Http => {
+                // absolute-form request target
+                let target = req.url().to_string();
+                req.set_target(&target);
+                if let Some(auth) = proxy.authorization() {
+                    req.head_mut().add("Proxy-Authorization", &auth);
+                }
+            }
+        }
+        Ok(stream)
+    }
+}
+
+// what `Client::authenticated` needs to see in a response
+trait Received {
+    fn status(&self) -> StatusCode;
+    fn headers(&self) -> &Header;
 }
 
+impl Received for Response {
+    fn status(&self) -> StatusCode {
+        self.status()
+    }
+
+    fn headers(&self) -> &Header {
+        self.headers()
+    }
+}
+
+impl Received for (ResponseHead, Body) {
+    fn status(&self) -> StatusCode {
+        self.0.status()
+    }
+
+    fn headers(&self) -> &Header {
+        self.0.headers()
+    }
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
 
     #[test]
     fn test_get() {
         Client::get("example.com").unwrap();
     }
-}
 
+    // read a request head (and its body if `Content-Length` is given)
+    fn read_request(reader: &mut BufReader<TcpStream>) -> (String, String) {
+        let mut head = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" || line.is_empty() {
+                break;
+            }
+            head.push_str(&line);
+        }
+        let len = head
+            .lines()
+            .find_map(|l| l.strip_prefix("Content-Length:"))
+            .map(|l| l.trim().parse::<usize>().unwrap())
+            .unwrap_or(0);
+        let mut body = vec![0; len];
+        if !head.contains("Expect:") {
+            reader.read_exact(&mut body).unwrap();
+        }
+        (head, String::from_utf8(body).unwrap())
+    }
+
+    // start a server handling one connection per element of `handlers`
+    fn serve(handlers: Vec<fn(BufReader<TcpStream>, TcpStream)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for handler in handlers {
+                let (stream, _) = listener.accept().unwrap();
+                handler(BufReader::new(stream.try_clone().unwrap()), stream);
+            }
+        });
+        format!("http://{}/", addr)
+    }
+
+    #[test]
+    fn test_early_hints() {
+        let url = serve(vec![|mut reader, mut stream| {
+            read_request(&mut reader);
+            stream.write_all(b"HTTP/1.1 103 Early Hints\r\nLink: </a.css>; rel=preload\r\n\r\n").unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+        }]);
+
+        let res = Client::get(&url).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.bytes(), b"ok");
+        assert_eq!(res.early_hints()[0].get("Link"), Some("</a.css>; rel=preload"));
+    }
 
+    #[test]
+    fn test_expect_continue() {
+        let url = serve(vec![|mut reader, mut stream| {
+            let (head, _) = read_request(&mut reader);
+            assert!(head.starts_with("POST / HTTP/1.1\r\n"));
+            assert!(head.contains("Expect:100-continue\r\n"));
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+            let mut body = [0; 5];
+            reader.read_exact(&mut body).unwrap();
+            assert_eq!(&body, b"hello");
+            stream.write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n").unwrap();
+        }]);
 
+        let mut client = Client::new();
+        client.set_expect_continue(Some(4));
+        let res = client.send(Request::post(&url, "hello").unwrap()).unwrap();
+        assert_eq!(res.status().as_u16(), 201);
+        assert_eq!(res.interim()[0].status(), StatusCode::CONTINUE);
+    }
 
+    #[test]
+    fn test_expectation_failed() {
+        let url = serve(vec![
+            |mut reader, mut stream| {
+                read_request(&mut reader);
+                stream.write_all(b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 0\r\n\r\n").unwrap();
+            },
+            |mut reader, mut stream| {
+                let (head, body) = read_request(&mut reader);
+                assert!(!head.contains("Expect"));
+                assert_eq!(body, "hello");
+                stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+            },
+        ]);
+
+        let mut client = Client::new();
+        client.set_expect_continue(Some(1));
+        let res = client.send(Request::put(&url, "hello").unwrap()).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_cookies() {
+        let url = serve(vec![
+            |mut reader, mut stream| {
+                let (head, _) = read_request(&mut reader);
+                assert!(!head.contains("Cookie"));
+                stream.write_all(b"HTTP/1.1 200 OK\r\nSet-Cookie: id=a3fWa; Path=/\r\nContent-Length: 0\r\n\r\n").unwrap();
+            },
+            |mut reader, mut stream| {
+                let (head, _) = read_request(&mut reader);
+                assert!(head.contains("Cookie:id=a3fWa\r\n"));
+                stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+            },
+        ]);
 
+        let mut client = Client::new();
+        client.send(Request::get(&url).unwrap()).unwrap();
+        assert_eq!(client.cookie_jar().len(), 1);
+        client.send(Request::get(&url).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_auth() {
+        use crate::http::transport::MockTransport;
+        use crate::http::url::URL;
+
+        let unauthorized = b"HTTP/1.1 401 Unauthorized\r\n\
+            WWW-Authenticate: Basic realm=\"r\", Digest realm=\"r\", qop=\"auth\", nonce=\"abc\", algorithm=SHA-256\r\n\
+            Content-Length: 0\r\n\r\n";
+        let ok = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+        let mock = MockTransport::new();
+        for response in [&unauthorized[..], ok, unauthorized, ok, unauthorized, unauthorized].iter() {
+            mock.push_response(response);
+        }
+        let mut client = Client::new();
+        client.set_transport(Box::new(mock.clone()));
+
+        // from the URL
+        let res = client.send(Request::get("http://Mufasa:pw@example.com/dir").unwrap()).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        // from .netrc, falling back to the prompt
+        client.set_netrc(Some(Netrc::parse("machine example.com login alice password secret")));
+        client.set_prompt(Some(Box::new(|url: &URL, realm: Option<&str>| {
+            assert_eq!((url.host().as_str(), realm), ("other.com", Some("r")));
+            None
+        })));
+        let res = client.send(Request::get("http://example.com/").unwrap()).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        // a cancelled prompt returns the `401` as it is
+        let res = client.send(Request::get("http://other.com/").unwrap()).unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        // the retry is not repeated
+        let mut req = Request::get("http://example.com/").unwrap();
+        req.head_mut().add("Authorization", "Basic eDp5");
+        assert_eq!(client.send(req).unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        let requests: Vec<String> = mock.requests().into_iter().map(|r| String::from_utf8(r).unwrap()).collect();
+        assert_eq!(requests.len(), 6);
+        assert!(!requests[0].contains("Authorization"));
+        assert!(requests[1].contains("Authorization:Digest username=\"Mufasa\", realm=\"r\", uri=\"/dir\", algorithm=SHA-256"));
+        assert!(requests[3].contains("Authorization:Digest username=\"alice\""));
+        assert!(!requests[4].contains("Authorization"));
+    }
+
+    #[test]
+    fn test_insecure_basic() {
+        use crate::http::transport::MockTransport;
+
+        let unauthorized = b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"r\"\r\nContent-Length: 0\r\n\r\n";
+        let mock = MockTransport::new();
+        mock.push_response(unauthorized);
+        mock.push_response(unauthorized);
+        mock.push_response(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let mut client = Client::new();
+        client.set_transport(Box::new(mock.clone()));
+
+        // the password is not sent in the clear by default
+        let res = client.send(Request::get("http://user:pw@example.com/").unwrap()).unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(mock.requests().len(), 1);
+
+        client.set_insecure_basic(true);
+        let res = client.send(Request::get("http://user:pw@example.com/").unwrap()).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let requests = mock.requests();
+        assert!(!String::from_utf8_lossy(&requests[1]).contains("Authorization"));
+        assert!(String::from_utf8_lossy(&requests[2]).contains("Authorization:Basic dXNlcjpwdw==\r\n"));
+    }
+
+    #[test]
+    fn test_cache() {
+        let url = serve(vec![
+            |mut reader, mut stream| {
+                read_request(&mut reader);
+                stream.write_all(b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60, no-cache\r\nEtag: \"v1\"\r\nContent-Length: 4\r\n\r\nbody").unwrap();
+            },
+            |mut reader, mut stream| {
+                let (head, _) = read_request(&mut reader);
+                assert!(head.contains("If-None-Match:\"v1\"\r\n"));
+                stream.write_all(b"HTTP/1.1 304 Not Modified\r\nCache-Control: max-age=60\r\n\r\n").unwrap();
+            },
+            // the third request is served by the cache
+        ]);
+
+        let dir = std::env::temp_dir().join(format!("aktis-client-cache-{}", std::process::id()));
+        let mut client = Client::new();
+        client.set_cache(Some(Cache::open(&dir, 1 << 20).unwrap()));
+        for _ in 0..3 {
+            let res = client.send(Request::get(&url).unwrap()).unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.bytes(), b"body");
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_continue_timeout() {
+        // the server never sends `100 Continue`
+        let url = serve(vec![|mut reader, mut stream| {
+            read_request(&mut reader);
+            let mut body = [0; 5];
+            reader.read_exact(&mut body).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        }]);
+
+        let mut client = Client::new();
+        client.set_expect_continue(Some(1));
+        client.set_continue_timeout(Duration::from_millis(100));
+        let res = client.send(Request::post(&url, "hello").unwrap()).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.interim().is_empty());
+    }
+}
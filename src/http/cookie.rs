@@ -0,0 +1,492 @@
+// Cookie jar (RFC 6265)
+//
+// `CookieJar` stores cookies received in `Set-Cookie` fields and returns the value of
+// the `Cookie` field for outgoing requests. The jar can be saved to and loaded from
+// a Netscape cookies.txt file (the format curl and wget use).
+//
+// ref: https://www.rfc-editor.org/rfc/rfc6265#section-5
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use crate::http::date::HttpDate;
+use crate::http::url::URL;
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum CookieError {
+    InvalidLine(usize),
+}
+
+impl fmt::Display for CookieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLine(n) => write!(f, "Cookie error: invalid cookie file at line {}", n),
+        }
+    }
+}
+
+impl Error for CookieError {}
+// ------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    path: String,
+    // seconds since the UNIX epoch; `None` for session cookies
+    expires: Option<u64>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    // sent only to the exact host which set it (no `Domain` attribute)
+    host_only: bool,
+    creation: u64,
+}
+
+impl Cookie {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn expires(&self) -> Option<HttpDate> {
+        self.expires.map(HttpDate::from_secs)
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    pub fn is_http_only(&self) -> bool {
+        self.http_only
+    }
+
+    pub fn same_site(&self) -> Option<SameSite> {
+        self.same_site
+    }
+
+    pub fn is_host_only(&self) -> bool {
+        self.host_only
+    }
+
+    pub fn is_session(&self) -> bool {
+        self.expires.is_none()
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expires, Some(t) if t <= now)
+    }
+
+    // Parse a `Set-Cookie` field received from `url` (RFC 6265 section 5.2 and 5.3).
+    // Return `None` if the cookie must be ignored.
+    pub fn parse(set_cookie: &str, url: &URL, now: u64) -> Option<Self> {
+        let mut parts = set_cookie.split(';');
+        let (name, value) = match parts.next()?.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => return None,
+        };
+        if name.is_empty() {
+            return None;
+        }
+
+        let host = url.host().to_ascii_lowercase();
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.trim_matches('"').to_string(),
+            domain: host.clone(),
+            path: default_path(&url.path()),
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+            host_only: true,
+            creation: now,
+        };
+
+        let mut max_age = None;
+        let mut expires = None;
+        for attr in parts {
+            let (key, val) = match attr.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => (attr.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "expires" => {
+                    if let Ok(date) = HttpDate::parse(val) {
+                        expires = Some(date.secs());
+                    }
+                }
+                "max-age" => {
+                    if let Ok(secs) = val.parse::<i64>() {
+                        max_age = Some(if secs <= 0 { 0 } else { now.saturating_add(secs as u64) });
+                    }
+                }
+                "domain" => {
+                    let domain = val.trim_start_matches('.').to_ascii_lowercase();
+                    if domain.is_empty() {
+                        continue;
+                    }
+                    if !domain_match(&host, &domain) || is_public_suffix(&domain) {
+                        // a site must not set cookies for other sites
+                        if domain != host {
+                            return None;
+                        }
+                        continue;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if val.starts_with('/') => cookie.path = val.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match val.to_ascii_lowercase().as_str() {
+                        "strict" => Some(SameSite::Strict),
+                        "lax" => Some(SameSite::Lax),
+                        "none" => Some(SameSite::None),
+                        _ => None,
+                    }
+                }
+                _ => {}
+            }
+        }
+        // `Max-Age` takes precedence over `Expires`
+        cookie.expires = max_age.or(expires);
+
+        // an insecure origin must not set secure cookies (RFC 6265bis)
+        let secure_origin = url.scheme() == "https";
+        if cookie.secure && !secure_origin {
+            return None;
+        }
+        // cookie name prefixes
+        if cookie.name.starts_with("__Secure-") && !cookie.secure {
+            return None;
+        }
+        if cookie.name.starts_with("__Host-") && (!cookie.secure || !cookie.host_only || cookie.path != "/") {
+            return None;
+        }
+        Some(cookie)
+    }
+
+    // whether this cookie should be sent with a request to `url`
+    pub fn matches(&self, url: &URL) -> bool {
+        let host = url.host().to_ascii_lowercase();
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+        let path = url.path();
+        let path = path.split('?').next().unwrap_or("/");
+        domain_ok && path_match(path, &self.path) && (!self.secure || url.scheme() == "https")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self { cookies: vec![] }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+
+    // store a `Set-Cookie` field received from `url`
+    pub fn set_cookie(&mut self, url: &URL, set_cookie: &str) {
+        let now = now();
+        if let Some(cookie) = Cookie::parse(set_cookie, url, now) {
+            self.insert(cookie, now);
+        }
+    }
+
+    // replace the cookie with the same name, domain and path (keeping its creation time)
+    fn insert(&mut self, mut cookie: Cookie, now: u64) {
+        let old = self.cookies.iter().position(|c| {
+            c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path
+        });
+        match old {
+            // an expired cookie only removes the old one
+            Some(i) if cookie.is_expired(now) => {
+                self.cookies.remove(i);
+            }
+            Some(i) => {
+                cookie.creation = self.cookies[i].creation;
+                self.cookies[i] = cookie;
+            }
+            None if cookie.is_expired(now) => {}
+            None => self.cookies.push(cookie),
+        }
+    }
+
+    // cookies to send to `url` in the order of RFC 6265 section 5.4
+    // (longer paths first, then earlier creation times first)
+    pub fn cookies_for(&self, url: &URL) -> Vec<&Cookie> {
+        let now = now();
+        let mut cookies = self
+            .cookies
+            .iter()
+            .filter(|c| !c.is_expired(now) && c.matches(url))
+            .collect::<Vec<_>>();
+        cookies.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.creation.cmp(&b.creation)));
+        cookies
+    }
+
+    // value of the `Cookie` field for `url`, if any cookie matches
+    pub fn header_value(&self, url: &URL) -> Option<String> {
+        let cookies = self.cookies_for(url);
+        if cookies.is_empty() {
+            return None;
+        }
+        let pairs = cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<_>>();
+        Some(pairs.join("; "))
+    }
+
+    pub fn evict_expired(&mut self) {
+        let now = now();
+        self.cookies.retain(|c| !c.is_expired(now));
+    }
+
+    // remove session cookies (as a browser does on exit)
+    pub fn remove_session_cookies(&mut self) {
+        self.cookies.retain(|c| !c.is_session());
+    }
+
+    // Example Netscape cookies.txt line (fields are separated by tabs):
+    // ```
+    // # Netscape HTTP Cookie File
+    // .example.com	TRUE	/	FALSE	1735689600	id	a3fWa
+    // #HttpOnly_example.com	FALSE	/	FALSE	0	session	xyz
+    // ```
+    //
+    // The expiry `0` means a session cookie.
+    pub fn to_netscape(&self) -> String {
+        let mut result = String::from("# Netscape HTTP Cookie File\n");
+        for c in &self.cookies {
+            let domain = if c.host_only { c.domain.clone() } else { format!(".{}", c.domain) };
+            result.push_str(&format!(
+                "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                if c.http_only { "#HttpOnly_" } else { "" },
+                domain,
+                if c.host_only { "FALSE" } else { "TRUE" },
+                c.path,
+                if c.secure { "TRUE" } else { "FALSE" },
+                c.expires.unwrap_or(0),
+                c.name,
+                c.value,
+            ));
+        }
+        result
+    }
+
+    pub fn from_netscape(src: &str) -> Result<Self, CookieError> {
+        let mut jar = Self::new();
+        let now = now();
+        for (i, line) in src.lines().enumerate() {
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(rest) => (rest, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() != 7 {
+                return Err(CookieError::InvalidLine(i + 1));
+            }
+            let expires = match fields[4].parse::<u64>() {
+                Ok(0) => None,
+                Ok(t) => Some(t),
+                Err(_) => return Err(CookieError::InvalidLine(i + 1)),
+            };
+            let cookie = Cookie {
+                name: fields[5].to_string(),
+                value: fields[6].to_string(),
+                domain: fields[0].trim_start_matches('.').to_ascii_lowercase(),
+                path: fields[2].to_string(),
+                expires,
+                secure: fields[3] == "TRUE",
+                http_only,
+                same_site: None,
+                host_only: fields[1] != "TRUE",
+                creation: now,
+            };
+            jar.insert(cookie, now);
+        }
+        Ok(jar)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_netscape())?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let src = fs::read_to_string(path)?;
+        Ok(Self::from_netscape(&src)?)
+    }
+}
+
+fn now() -> u64 {
+    HttpDate::now().secs()
+}
+
+// ref: https://www.rfc-editor.org/rfc/rfc6265#section-5.1.3
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    // IP addresses only match exactly
+    let is_ip = host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok();
+    !is_ip && host.ends_with(domain) && host[..host.len() - domain.len()].ends_with('.')
+}
+
+// ref: https://www.rfc-editor.org/rfc/rfc6265#section-5.1.4
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+fn default_path(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or("");
+    if !path.starts_with('/') {
+        return "/".to_string();
+    }
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+// Common second level public suffixes.
+// Without the full Public Suffix List, this (and rejecting single label domains)
+// keeps sites from setting cookies for a whole registry.
+const PUBLIC_SUFFIXES: [&str; 24] = [
+    "co.jp", "ne.jp", "or.jp", "ac.jp", "go.jp", "ed.jp", "ad.jp", "gr.jp", "lg.jp",
+    "co.uk", "org.uk", "ac.uk", "gov.uk", "com.au", "net.au", "org.au", "com.cn", "com.br",
+    "co.kr", "com.tw", "github.io", "gitlab.io", "herokuapp.com", "blogspot.com",
+];
+
+fn is_public_suffix(domain: &str) -> bool {
+    !domain.contains('.') || PUBLIC_SUFFIXES.contains(&domain)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn url(s: &str) -> URL {
+        URL::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let now = 1_000_000;
+        let c = Cookie::parse(
+            "id=a3fWa; Expires=Thu, 21 Oct 2021 07:28:00 GMT; Max-Age=60; Domain=.Example.com; Path=/docs; HttpOnly; SameSite=Lax",
+            &url("http://www.example.com/"),
+            now,
+        ).unwrap();
+        assert_eq!(c.name(), "id");
+        assert_eq!(c.value(), "a3fWa");
+        assert_eq!(c.domain(), "example.com");
+        assert!(!c.is_host_only());
+        assert_eq!(c.path(), "/docs");
+        assert_eq!(c.expires().unwrap().secs(), now + 60);
+        assert!(c.is_http_only());
+        assert_eq!(c.same_site(), Some(SameSite::Lax));
+
+        let c = Cookie::parse("a=b", &url("http://example.com/docs/web/index.html"), now).unwrap();
+        assert_eq!(c.path(), "/docs/web");
+        assert!(c.is_host_only());
+        assert!(c.is_session());
+
+        // other sites, public suffixes and insecure origins
+        assert!(Cookie::parse("a=b; Domain=example.org", &url("http://example.com/"), now).is_none());
+        assert!(Cookie::parse("a=b; Domain=com", &url("http://example.com/"), now).is_none());
+        assert!(Cookie::parse("a=b; Domain=co.jp", &url("http://example.co.jp/"), now).is_none());
+        assert!(Cookie::parse("a=b; Secure", &url("http://example.com/"), now).is_none());
+        assert!(Cookie::parse("__Host-a=b; Secure; Path=/", &url("https://example.com/"), now).is_some());
+        assert!(Cookie::parse("__Host-a=b; Secure; Domain=example.com", &url("https://example.com/"), now).is_none());
+    }
+
+    #[test]
+    fn test_jar() {
+        let mut jar = CookieJar::new();
+        let origin = url("http://www.example.com/docs/index.html");
+        jar.set_cookie(&origin, "a=1; Path=/");
+        jar.set_cookie(&origin, "b=2; Path=/docs");
+        jar.set_cookie(&origin, "c=3; Domain=example.com; Path=/");
+        jar.set_cookie(&origin, "d=4; Max-Age=0");
+
+        assert_eq!(jar.header_value(&url("http://www.example.com/docs/a")), Some("b=2; a=1; c=3".to_string()));
+        assert_eq!(jar.header_value(&url("http://www.example.com/documents")), Some("a=1; c=3".to_string()));
+        assert_eq!(jar.header_value(&url("http://example.com/")), Some("c=3".to_string()));
+        assert_eq!(jar.header_value(&url("http://example.org/")), None);
+
+        // overwrite and delete
+        jar.set_cookie(&origin, "a=5; Path=/");
+        assert_eq!(jar.header_value(&url("http://www.example.com/")), Some("a=5; c=3".to_string()));
+        jar.set_cookie(&origin, "a=; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(jar.header_value(&url("http://www.example.com/")), Some("c=3".to_string()));
+    }
+
+    #[test]
+    fn test_netscape() {
+        let mut jar = CookieJar::new();
+        let origin = url("http://example.com/");
+        jar.set_cookie(&origin, "id=a3fWa; Domain=example.com; Expires=Fri, 01 Jan 2100 00:00:00 GMT");
+        jar.set_cookie(&origin, "session=xyz; HttpOnly");
+
+        let src = jar.to_netscape();
+        assert!(src.contains(".example.com\tTRUE\t/\tFALSE\t4102444800\tid\ta3fWa\n"));
+        assert!(src.contains("#HttpOnly_example.com\tFALSE\t/\tFALSE\t0\tsession\txyz\n"));
+
+        let loaded = CookieJar::from_netscape(&src).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.header_value(&url("http://example.com/")), Some("id=a3fWa; session=xyz".to_string()));
+        assert!(loaded.iter().any(|c| c.is_http_only() && c.is_session()));
+        assert!(CookieJar::from_netscape("example.com\tFALSE\t/").is_err());
+    }
+}
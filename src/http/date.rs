@@ -0,0 +1,205 @@
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum DateError {
+    Invalid(String),
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(s) => write!(f, "Date error: invalid HTTP-date: `{}`", s),
+        }
+    }
+}
+
+impl Error for DateError {}
+// ------------------------------------
+
+// HTTP-date (RFC 9110 section 5.6.7)
+//
+// Three formats are accepted when parsing:
+// ```
+// Sun, 06 Nov 1994 08:49:37 GMT    ; IMF-fixdate
+// Sunday, 06-Nov-94 08:49:37 GMT   ; obsolete RFC 850 format
+// Sun Nov  6 08:49:37 1994         ; ANSI C's asctime() format
+// ```
+// but only IMF-fixdate is generated.
+//
+// The date is held as seconds since the UNIX epoch. Dates before the epoch
+// are clamped to the epoch (they are only ever seen in `Expires: 0`-like headers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HttpDate(u64);
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+impl HttpDate {
+    pub fn now() -> Self {
+        Self::from(SystemTime::now())
+    }
+
+    pub fn from_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    // seconds since the UNIX epoch
+    pub fn secs(&self) -> u64 {
+        self.0
+    }
+
+    pub fn parse(src: &str) -> Result<Self, DateError> {
+        let src = src.trim();
+        let invalid = || DateError::Invalid(src.to_string());
+
+        let words = src.split_whitespace().collect::<Vec<_>>();
+        let (year, month, day, time) = match words.as_slice() {
+            // IMF-fixdate: `Sun, 06 Nov 1994 08:49:37 GMT`
+            [_, day, month, year, time, "GMT"] => {
+                (parse_num(year)?, parse_month(month)?, parse_num(day)?, *time)
+            }
+            // RFC 850: `Sunday, 06-Nov-94 08:49:37 GMT`
+            [_, date, time, "GMT"] => {
+                let parts = date.split('-').collect::<Vec<_>>();
+                if parts.len() != 3 {
+                    return Err(invalid());
+                }
+                let mut year = parse_num(parts[2])?;
+                if parts[2].len() == 2 {
+                    // two digit years are interpreted in a 1970-2069 window
+                    year += if year < 70 { 2000 } else { 1900 };
+                }
+                (year, parse_month(parts[1])?, parse_num(parts[0])?, *time)
+            }
+            // asctime: `Sun Nov  6 08:49:37 1994`
+            [_, month, day, time, year] => {
+                (parse_num(year)?, parse_month(month)?, parse_num(day)?, *time)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let hms = time
+            .split(':')
+            .map(parse_num)
+            .collect::<Result<Vec<_>, _>>()?;
+        let (hour, min, sec) = match hms.as_slice() {
+            [h, m, s] if *h < 24 && *m < 60 && *s <= 60 => (*h, *m, *s),
+            _ => return Err(invalid()),
+        };
+        if !(1..=31).contains(&day) {
+            return Err(invalid());
+        }
+
+        let days = days_from_civil(year as i64, month, day as u32);
+        let secs = days * 86400 + (hour * 3600 + min * 60 + sec) as i64;
+        Ok(Self(if secs < 0 { 0 } else { secs as u64 }))
+    }
+}
+
+impl fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = (self.0 / 86400) as i64;
+        let rem = self.0 % 86400;
+        let (year, month, day) = civil_from_days(days);
+        // 1970-01-01 was a Thursday
+        let weekday = WEEKDAYS[((days + 4) % 7) as usize];
+        write!(
+            f,
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            weekday,
+            day,
+            MONTHS[(month - 1) as usize],
+            year,
+            rem / 3600,
+            rem % 3600 / 60,
+            rem % 60,
+        )
+    }
+}
+
+impl From<SystemTime> for HttpDate {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => Self(d.as_secs()),
+            Err(_) => Self(0),
+        }
+    }
+}
+
+impl From<HttpDate> for SystemTime {
+    fn from(date: HttpDate) -> Self {
+        UNIX_EPOCH + Duration::from_secs(date.0)
+    }
+}
+
+fn parse_num(s: &str) -> Result<u64, DateError> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return Err(DateError::Invalid(s.to_string()));
+    }
+    s.parse::<u64>().map_err(|_| DateError::Invalid(s.to_string()))
+}
+
+fn parse_month(s: &str) -> Result<u32, DateError> {
+    match MONTHS.iter().position(|m| m.eq_ignore_ascii_case(s)) {
+        Some(i) => Ok(i as u32 + 1),
+        None => Err(DateError::Invalid(s.to_string())),
+    }
+}
+
+// days since 1970-01-01 of the proleptic Gregorian date
+// ref: http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// inverse of `days_from_civil`
+// ref: http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let expected = HttpDate::from_secs(784111777);
+        assert_eq!(HttpDate::parse("Sun, 06 Nov 1994 08:49:37 GMT").unwrap(), expected);
+        assert_eq!(HttpDate::parse("Sunday, 06-Nov-94 08:49:37 GMT").unwrap(), expected);
+        assert_eq!(HttpDate::parse("Sun Nov  6 08:49:37 1994").unwrap(), expected);
+
+        assert!(HttpDate::parse("0").is_err());
+        assert!(HttpDate::parse("Sun, 06 Foo 1994 08:49:37 GMT").is_err());
+        assert!(HttpDate::parse("Sun, 06 Nov 1994 25:49:37 GMT").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let raw = "Thu, 17 Oct 2019 07:18:26 GMT";
+        assert_eq!(HttpDate::parse(raw).unwrap().to_string(), raw);
+        assert_eq!(HttpDate::from_secs(0).to_string(), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(HttpDate::parse("Tue, 29 Feb 2000 23:59:59 GMT").unwrap().to_string(),
+            "Tue, 29 Feb 2000 23:59:59 GMT");
+    }
+}
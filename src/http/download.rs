@@ -0,0 +1,401 @@
+// Resumable downloads
+//
+// `Download` streams a response body into a file. While the file is incomplete, the validators
+// of the response are kept next to it (`<file>.aktis-resume`) and the next run asks only for the rest:
+// ```
+// GET /big.iso HTTP/1.1
+// Range: bytes=1048576-
+// If-Range: "3147526947"
+// ```
+// The server answers `206 Partial Content` if the representation is unchanged,
+// otherwise `200 OK` with the whole body, which replaces the partial file.
+//
+// The body is written as it arrives, so files larger than memory can be saved.
+//
+// ref: https://www.rfc-editor.org/rfc/rfc9110#section-14
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::http::client::Client;
+use crate::http::date::HttpDate;
+use crate::http::headers::{ByteRange, ETag, IfRange};
+use crate::http::request::Request;
+use crate::http::response::{Header, ResponseHead};
+use crate::http::status::StatusCode;
+use crate::http::url::{percent_decode, URL};
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum DownloadError {
+    Status(StatusCode),
+    // the server sent a range which does not continue the file
+    RangeMismatch,
+    // the connection closed before the end of the body
+    Incomplete { received: u64, expected: u64 },
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status(code) => write!(f, "Download error: server returned `{}`", code),
+            Self::RangeMismatch => write!(f, "Download error: server returned an unexpected range"),
+            Self::Incomplete { received, expected } => {
+                write!(f, "Download error: received {} of {} bytes (run again to resume)", received, expected)
+            }
+        }
+    }
+}
+
+impl Error for DownloadError {}
+// ------------------------------------
+
+const RESUME_SUFFIX: &str = ".aktis-resume";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Download {
+    url: String,
+    path: PathBuf,
+}
+
+impl Download {
+    pub fn new<P: AsRef<Path>>(url: &str, path: P) -> Self {
+        Self {
+            url: url.to_string(),
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // where the validators of an incomplete file are kept
+    pub fn resume_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(RESUME_SUFFIX);
+        self.path.with_file_name(name)
+    }
+
+    // download into `path()`, continuing an incomplete file if possible.
+    // return the size of the complete file.
+    pub fn run(&self, client: &mut Client) -> Result<u64, Box<dyn Error>> {
+        self.run_with_progress(client, &mut |_| {})
+    }
+
+    // `run` calling `progress` whenever a part of the body is written
+    pub fn run_with_progress(&self, client: &mut Client, progress: &mut dyn FnMut(&Progress)) -> Result<u64, Box<dyn Error>> {
+        let mut req = Request::get(&self.url)?;
+        let resume = self.resume_point();
+        if let Some((offset, if_range)) = &resume {
+            req.head_mut().set_range(&[ByteRange::From(*offset)]);
+            req.head_mut().set_if_range(if_range);
+        }
+        let (head, mut body) = client.open(req)?;
+        self.receive(&head, &mut body, resume.map(|(offset, _)| offset), progress)
+    }
+
+    // save the response to a request for `url()` which has been opened already
+    // (e.g. to look at its `Content-Type` first)
+    pub fn save(&self, head: &ResponseHead, body: &mut dyn Read, progress: &mut dyn FnMut(&Progress)) -> Result<u64, Box<dyn Error>> {
+        self.receive(head, body, None, progress)
+    }
+
+    // write the body to the file, appending to the first `resumed` bytes for `206 Partial Content`
+    fn receive(
+        &self,
+        head: &ResponseHead,
+        body: &mut dyn Read,
+        resumed: Option<u64>,
+        progress: &mut dyn FnMut(&Progress),
+    ) -> Result<u64, Box<dyn Error>> {
+        let headers = head.headers();
+        let (mut file, offset, total) = match (head.status(), resumed) {
+            (StatusCode::PARTIAL_CONTENT, Some(offset)) => {
+                let range = headers.content_range().ok_or(DownloadError::RangeMismatch)?;
+                if range.first() != offset {
+                    return Err(Box::new(DownloadError::RangeMismatch));
+                }
+                let file = OpenOptions::new().append(true).open(&self.path)?;
+                (file, offset, range.complete_length())
+            }
+            // nothing is left after `offset`
+            (StatusCode::RANGE_NOT_SATISFIABLE, Some(offset))
+                if headers.content_range().and_then(|r| r.complete_length()) == Some(offset) =>
+            {
+                self.finish()?;
+                return Ok(offset);
+            }
+            (status, _) if status.is_success() && status != StatusCode::PARTIAL_CONTENT => {
+                (File::create(&self.path)?, 0, headers.content_length())
+            }
+            (status, _) => return Err(Box::new(DownloadError::Status(status))),
+        };
+
+        match ResumeState::from_headers(&self.url, headers) {
+            Some(state) => fs::write(self.resume_path(), state.to_string())?,
+            // the file cannot be validated later
+            None => self.finish()?,
+        }
+
+        let mut state = Progress { offset, received: offset, total };
+        progress(&state);
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = match body.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Box::new(e)),
+            };
+            file.write_all(&buf[..n])?;
+            state.received += n as u64;
+            progress(&state);
+        }
+
+        let received = state.received;
+        if let Some(expected) = total {
+            if received < expected {
+                return Err(Box::new(DownloadError::Incomplete { received, expected }));
+            }
+        }
+        self.finish()?;
+        Ok(received)
+    }
+
+    // (offset, validator) to continue the file from
+    fn resume_point(&self) -> Option<(u64, IfRange)> {
+        let state = ResumeState::parse(&fs::read_to_string(self.resume_path()).ok()?)?;
+        if state.url != self.url {
+            return None;
+        }
+        let offset = fs::metadata(&self.path).ok()?.len();
+        // weak entity tags cannot validate a range
+        let if_range = match (state.etag, state.last_modified) {
+            (Some(etag), _) if !etag.is_weak() => IfRange::ETag(etag),
+            (_, Some(date)) => IfRange::Date(date),
+            _ => return None,
+        };
+        Some((offset, if_range))
+    }
+
+    // forget the validators of a complete (or unresumable) file
+    fn finish(&self) -> io::Result<()> {
+        match fs::remove_file(self.resume_path()) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+// how far a download has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    offset: u64,
+    received: u64,
+    total: Option<u64>,
+}
+
+impl Progress {
+    // bytes which were on disk before (resumed downloads)
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // bytes on disk, including `offset()`
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    // the size of the complete file if known
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+}
+
+// whether a response should be saved rather than displayed:
+// `Content-Disposition: attachment` or a media type which is not text
+pub fn wants_download(headers: &Header) -> bool {
+    if let Some(disposition) = headers.content_disposition() {
+        return disposition.is_attachment();
+    }
+    let media_type = match headers.content_type() {
+        Some(media_type) => media_type,
+        // assume HTML like other browsers without sniffing
+        None => return false,
+    };
+    let textual = media_type.is_text()
+        || media_type.is_html()
+        || ["json", "xml", "javascript", "ecmascript"].contains(&media_type.subtype())
+        || media_type.subtype().ends_with("+xml")
+        || media_type.subtype().ends_with("+json");
+    !textual
+}
+
+// a file name for the response to a request for `url`: the one suggested by
+// `Content-Disposition`, or the last segment of the URL path.
+// Directories and leading dots are removed so the file stays in the current directory.
+pub fn filename(headers: &Header, url: &URL) -> String {
+    let suggested = headers.content_disposition().and_then(|d| d.filename());
+    let name = suggested.unwrap_or_else(|| {
+        let path = url.path();
+        let path = path.split(['?', '#']).next().unwrap_or("");
+        percent_decode(path.rsplit('/').next().unwrap_or(""))
+    });
+    let name = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() {
+        "download".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+// Example resume file:
+// ```
+// url http://example.com/big.iso
+// etag "3147526947"
+// last-modified Thu, 17 Oct 2019 07:18:26 GMT
+// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ResumeState {
+    url: String,
+    etag: Option<ETag>,
+    last_modified: Option<HttpDate>,
+}
+
+impl ResumeState {
+    // `None` if the response has no validator
+    fn from_headers(url: &str, headers: &Header) -> Option<Self> {
+        let state = Self {
+            url: url.to_string(),
+            etag: headers.etag().filter(|e| !e.is_weak()),
+            last_modified: headers.last_modified(),
+        };
+        if state.etag.is_none() && state.last_modified.is_none() {
+            return None;
+        }
+        Some(state)
+    }
+
+    fn parse(src: &str) -> Option<Self> {
+        let mut state = Self {
+            url: String::new(),
+            etag: None,
+            last_modified: None,
+        };
+        for line in src.lines() {
+            match line.split_once(' ') {
+                Some(("url", url)) => state.url = url.to_string(),
+                Some(("etag", etag)) => state.etag = ETag::parse(etag).ok(),
+                Some(("last-modified", date)) => state.last_modified = HttpDate::parse(date).ok(),
+                _ => {}
+            }
+        }
+        if state.url.is_empty() {
+            return None;
+        }
+        Some(state)
+    }
+}
+
+impl fmt::Display for ResumeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "url {}", self.url)?;
+        if let Some(etag) = &self.etag {
+            writeln!(f, "etag {}", etag)?;
+        }
+        if let Some(date) = &self.last_modified {
+            writeln!(f, "last-modified {}", date)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::transport::MockTransport;
+
+    #[test]
+    fn test_resume() {
+        let dir = std::env::temp_dir().join(format!("aktis-download-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let download = Download::new("http://example.com/file", dir.join("file"));
+
+        let mock = MockTransport::new();
+        // the connection drops after 4 of 10 bytes
+        mock.push_response(b"HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 10\r\n\r\n0123");
+        mock.push_response(b"HTTP/1.1 206 Partial Content\r\nETag: \"v1\"\r\nContent-Range: bytes 4-9/10\r\n\r\n456789");
+        mock.push_response(b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */10\r\n\r\n");
+        let mut client = Client::new();
+        client.set_transport(Box::new(mock.clone()));
+
+        let err = download.run(&mut client).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(DownloadError::Incomplete { received: 4, expected: 10 })));
+        assert!(download.resume_path().exists());
+        assert_eq!(download.run(&mut client).unwrap(), 10);
+        assert_eq!(fs::read(download.path()).unwrap(), b"0123456789");
+        assert!(!download.resume_path().exists());
+
+        // an interrupted file which is complete anyway
+        fs::write(download.resume_path(), "url http://example.com/file\netag \"v1\"\n").unwrap();
+        assert_eq!(download.run(&mut client).unwrap(), 10);
+
+        let requests: Vec<String> = mock.requests().into_iter().map(|r| String::from_utf8(r).unwrap()).collect();
+        assert!(!requests[0].contains("Range"));
+        assert!(requests[1].contains("Range:bytes=4-\r\n"));
+        assert!(requests[1].contains("If-Range:\"v1\"\r\n"));
+        assert!(requests[2].contains("Range:bytes=10-\r\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_filename() {
+        let headers = |raw: &str| Header::parse(raw).unwrap();
+        let url = URL::parse("http://example.com/files/report%202024.pdf?download=1").unwrap();
+        assert_eq!(filename(&headers(""), &url), "report 2024.pdf");
+        assert_eq!(filename(&headers("Content-Disposition: attachment; filename=\"../../.bashrc\""), &url), "bashrc");
+        assert_eq!(filename(&headers("Content-Disposition: attachment; filename=C:\\a\\b.txt"), &url), "b.txt");
+        assert_eq!(filename(&headers(""), &URL::parse("http://example.com/").unwrap()), "download");
+
+        assert!(wants_download(&headers("Content-Type: application/pdf")));
+        assert!(wants_download(&headers("Content-Type: text/plain\r\nContent-Disposition: attachment")));
+        assert!(!wants_download(&headers("Content-Type: image/png\r\nContent-Disposition: inline")));
+        assert!(!wants_download(&headers("Content-Type: application/atom+xml")));
+        assert!(!wants_download(&headers("")));
+    }
+
+    #[test]
+    fn test_changed() {
+        let dir = std::env::temp_dir().join(format!("aktis-download-changed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let download = Download::new("http://example.com/file", dir.join("file"));
+        fs::write(download.path(), b"old").unwrap();
+        fs::write(download.resume_path(), "url http://example.com/file\nlast-modified Thu, 17 Oct 2019 07:18:26 GMT\n")
+            .unwrap();
+
+        // the file changed on the server: the whole body replaces the partial file
+        let mock = MockTransport::new();
+        mock.push_response(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nnew");
+        let mut client = Client::new();
+        client.set_transport(Box::new(mock.clone()));
+        let mut reports = vec![];
+        assert_eq!(download.run_with_progress(&mut client, &mut |p| reports.push(*p)).unwrap(), 3);
+        assert_eq!(reports.first().map(|p| (p.received(), p.total())), Some((0, Some(3))));
+        assert_eq!(reports.last().map(|p| p.received()), Some(3));
+        assert_eq!(fs::read(download.path()).unwrap(), b"new");
+        let request = String::from_utf8(mock.requests().remove(0)).unwrap();
+        assert!(request.contains("If-Range:Thu, 17 Oct 2019 07:18:26 GMT\r\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
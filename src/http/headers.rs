@@ -0,0 +1,652 @@
+// Typed values of common HTTP header fields.
+//
+// `response::Header` and `request::Head` keep fields as raw strings; the types in
+// this module are what their typed accessors and setters parse into / build from.
+
+use std::error::Error;
+use std::fmt;
+use crate::http::date::HttpDate;
+use crate::http::url::percent_decode;
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum HeaderValueError {
+    InvalidMediaType(String),
+    InvalidETag(String),
+    InvalidContentRange(String),
+}
+
+impl fmt::Display for HeaderValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMediaType(s) => write!(f, "Header value error: invalid media type: `{}`", s),
+            Self::InvalidETag(s) => write!(f, "Header value error: invalid entity tag: `{}`", s),
+            Self::InvalidContentRange(s) => write!(f, "Header value error: invalid content range: `{}`", s),
+        }
+    }
+}
+
+impl Error for HeaderValueError {}
+// ------------------------------------
+
+// Example media type (`Content-Type`):
+// ```
+// text/html; charset=UTF-8
+// ```
+//
+// `type`, `subtype` and parameter names are case-insensitive, so they are held lowercased.
+// Parameter values keep their case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaType {
+    mime_type: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+}
+
+impl MediaType {
+    pub fn new(mime_type: &str, subtype: &str) -> Self {
+        Self {
+            mime_type: mime_type.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            params: vec![],
+        }
+    }
+
+    pub fn parse(src: &str) -> Result<Self, HeaderValueError> {
+        let mut parts = split_quoted(src, ';').into_iter();
+        let essence = parts.next().unwrap_or_default();
+        let (mime_type, subtype) = match essence.split_once('/') {
+            Some((t, s)) if is_token(t.trim()) && is_token(s.trim()) => (t.trim(), s.trim()),
+            _ => return Err(HeaderValueError::InvalidMediaType(src.to_string())),
+        };
+
+        let mut media_type = Self::new(mime_type, subtype);
+        for param in parts {
+            // parameters without `=` are invalid, but browsers ignore them
+            if let Some((name, value)) = param.split_once('=') {
+                media_type.params.push((name.trim().to_ascii_lowercase(), unquote(value.trim())));
+            }
+        }
+        Ok(media_type)
+    }
+
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.params.retain(|(n, _)| n != &name);
+        self.params.push((name, value.to_string()));
+        self
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    // `type/subtype` without parameters
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.mime_type, self.subtype)
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    pub fn is_html(&self) -> bool {
+        self.essence() == "text/html" || self.essence() == "application/xhtml+xml"
+    }
+
+    pub fn is_text(&self) -> bool {
+        self.mime_type == "text"
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.mime_type, self.subtype)?;
+        for (name, value) in &self.params {
+            write!(f, "; {}={}", name, quote_if_needed(value))?;
+        }
+        Ok(())
+    }
+}
+
+// Example `Cache-Control`:
+// ```
+// max-age=604800, must-revalidate
+// ```
+//
+// Directive names are held lowercased in the received order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CacheControl(Vec<(String, Option<String>)>);
+
+impl CacheControl {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn parse(src: &str) -> Self {
+        let mut cache_control = Self::new();
+        for directive in split_quoted(src, ',') {
+            let directive = directive.trim();
+            if directive.is_empty() {
+                continue;
+            }
+            match directive.split_once('=') {
+                Some((name, value)) => cache_control.0.push((
+                    name.trim().to_ascii_lowercase(),
+                    Some(unquote(value.trim())),
+                )),
+                None => cache_control.0.push((directive.to_ascii_lowercase(), None)),
+            }
+        }
+        cache_control
+    }
+
+    pub fn with(mut self, name: &str, value: Option<&str>) -> Self {
+        self.0.push((name.to_ascii_lowercase(), value.map(|v| v.to_string())));
+        self
+    }
+
+    // merge directives of another `Cache-Control` field line
+    pub fn extend(&mut self, other: Self) {
+        self.0.extend(other.0);
+    }
+
+    pub fn directives(&self) -> &[(String, Option<String>)] {
+        &self.0
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    fn seconds(&self, name: &str) -> Option<u64> {
+        self.get(name).and_then(|v| v.parse::<u64>().ok())
+    }
+
+    pub fn max_age(&self) -> Option<u64> {
+        self.seconds("max-age")
+    }
+
+    pub fn s_maxage(&self) -> Option<u64> {
+        self.seconds("s-maxage")
+    }
+
+    pub fn max_stale(&self) -> Option<u64> {
+        self.seconds("max-stale")
+    }
+
+    pub fn min_fresh(&self) -> Option<u64> {
+        self.seconds("min-fresh")
+    }
+
+    pub fn stale_while_revalidate(&self) -> Option<u64> {
+        self.seconds("stale-while-revalidate")
+    }
+
+    pub fn no_cache(&self) -> bool {
+        self.contains("no-cache")
+    }
+
+    pub fn no_store(&self) -> bool {
+        self.contains("no-store")
+    }
+
+    pub fn must_revalidate(&self) -> bool {
+        self.contains("must-revalidate")
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.contains("private")
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.contains("public")
+    }
+
+    pub fn immutable(&self) -> bool {
+        self.contains("immutable")
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let directives = self
+            .0
+            .iter()
+            .map(|(name, value)| match value {
+                Some(v) => format!("{}={}", name, quote_if_needed(v)),
+                None => name.to_string(),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", directives.join(", "))
+    }
+}
+
+// Example entity tag (`ETag`):
+// ```
+// "3147526947"
+// W/"3147526947"
+// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ETag {
+    weak: bool,
+    tag: String,
+}
+
+impl ETag {
+    pub fn strong(tag: &str) -> Self {
+        Self { weak: false, tag: tag.to_string() }
+    }
+
+    pub fn weak(tag: &str) -> Self {
+        Self { weak: true, tag: tag.to_string() }
+    }
+
+    pub fn parse(src: &str) -> Result<Self, HeaderValueError> {
+        let src = src.trim();
+        let (weak, rest) = match src.strip_prefix("W/") {
+            Some(rest) => (true, rest),
+            None => (false, src),
+        };
+        match rest.strip_prefix('"').and_then(|r| r.strip_suffix('"')) {
+            Some(tag) if !tag.contains('"') => Ok(Self { weak, tag: tag.to_string() }),
+            _ => Err(HeaderValueError::InvalidETag(src.to_string())),
+        }
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    // strong comparison (RFC 9110 section 8.8.3.2): both must be strong
+    pub fn strong_eq(&self, other: &Self) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    // weak comparison: only the opaque tags are compared
+    pub fn weak_eq(&self, other: &Self) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+// Example `Range` (RFC 9110 section 14.2):
+// ```
+// bytes=0-499, 1000-, -200
+// ```
+//
+// The three forms are: the first 500 bytes, everything from offset 1000, the last 200 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    // `first-last` (both inclusive)
+    FromTo(u64, u64),
+    // `first-`
+    From(u64),
+    // `-length`
+    Suffix(u64),
+}
+
+impl ByteRange {
+    // `(first, last)` within a representation of `len` bytes (`None` if unsatisfiable)
+    pub fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+        let (first, last) = match *self {
+            Self::FromTo(first, last) => (first, last.min(len.checked_sub(1)?)),
+            Self::From(first) => (first, len.checked_sub(1)?),
+            Self::Suffix(n) => (len.saturating_sub(n), len.checked_sub(1)?),
+        };
+        if first > last {
+            return None;
+        }
+        Some((first, last))
+    }
+}
+
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FromTo(first, last) => write!(f, "{}-{}", first, last),
+            Self::From(first) => write!(f, "{}-", first),
+            Self::Suffix(n) => write!(f, "-{}", n),
+        }
+    }
+}
+
+// Example `Content-Range` (RFC 9110 section 14.4):
+// ```
+// bytes 0-499/1234
+// bytes 0-499/*
+// bytes */1234
+// ```
+//
+// The last form comes with `416 Range Not Satisfiable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    // `(first, last)`, both inclusive
+    range: Option<(u64, u64)>,
+    complete_length: Option<u64>,
+}
+
+impl ContentRange {
+    pub fn new(first: u64, last: u64, complete_length: Option<u64>) -> Self {
+        Self { range: Some((first, last)), complete_length }
+    }
+
+    pub fn unsatisfied(complete_length: u64) -> Self {
+        Self { range: None, complete_length: Some(complete_length) }
+    }
+
+    pub fn parse(src: &str) -> Result<Self, HeaderValueError> {
+        let invalid = || HeaderValueError::InvalidContentRange(src.to_string());
+        let (unit, rest) = src.trim().split_once(' ').ok_or_else(invalid)?;
+        let (range, complete_length) = rest.trim().split_once('/').ok_or_else(invalid)?;
+        if !unit.eq_ignore_ascii_case("bytes") {
+            return Err(invalid());
+        }
+        let complete_length = match complete_length {
+            "*" => None,
+            n => Some(n.parse::<u64>().map_err(|_| invalid())?),
+        };
+        let range = match range {
+            "*" if complete_length.is_some() => None,
+            _ => {
+                let (first, last) = range.split_once('-').ok_or_else(invalid)?;
+                let first = first.parse::<u64>().map_err(|_| invalid())?;
+                let last = last.parse::<u64>().map_err(|_| invalid())?;
+                if first > last || complete_length.is_some_and(|len| last >= len) {
+                    return Err(invalid());
+                }
+                Some((first, last))
+            }
+        };
+        Ok(Self { range, complete_length })
+    }
+
+    pub fn range(&self) -> Option<(u64, u64)> {
+        self.range
+    }
+
+    // the first byte (0 for unsatisfied ranges)
+    pub fn first(&self) -> u64 {
+        self.range.map_or(0, |(first, _)| first)
+    }
+
+    // the number of bytes in the range
+    pub fn len(&self) -> u64 {
+        self.range.map_or(0, |(first, last)| last - first + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_none()
+    }
+
+    // the length of the whole representation (`None` if unknown)
+    pub fn complete_length(&self) -> Option<u64> {
+        self.complete_length
+    }
+}
+
+impl fmt::Display for ContentRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bytes ")?;
+        match self.range {
+            Some((first, last)) => write!(f, "{}-{}/", first, last)?,
+            None => write!(f, "*/")?,
+        }
+        match self.complete_length {
+            Some(len) => write!(f, "{}", len),
+            None => write!(f, "*"),
+        }
+    }
+}
+
+// Example `If-Range` (RFC 9110 section 13.1.5):
+// ```
+// "3147526947"
+// Thu, 17 Oct 2019 07:18:26 GMT
+// ```
+//
+// Send the range only if the representation is unchanged, otherwise the whole one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfRange {
+    // must be a strong entity tag
+    ETag(ETag),
+    Date(HttpDate),
+}
+
+impl fmt::Display for IfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ETag(etag) => write!(f, "{}", etag),
+            Self::Date(date) => write!(f, "{}", date),
+        }
+    }
+}
+
+// Example `Content-Disposition` (RFC 6266):
+// ```
+// attachment; filename="report.pdf"; filename*=UTF-8''%E5%A0%B1%E5%91%8A.pdf
+// ```
+//
+// `filename*` (RFC 8187) is preferred over `filename`; only its UTF-8 form is supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentDisposition {
+    disposition: String,
+    params: Vec<(String, String)>,
+}
+
+impl ContentDisposition {
+    pub fn parse(src: &str) -> Self {
+        let mut parts = split_quoted(src, ';').into_iter();
+        let disposition = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let params = parts
+            .filter_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                Some((name.trim().to_ascii_lowercase(), unquote(value.trim())))
+            })
+            .collect();
+        Self { disposition, params }
+    }
+
+    // `inline`, `attachment` or an extension (lower case)
+    pub fn disposition(&self) -> &str {
+        &self.disposition
+    }
+
+    // unknown dispositions are handled as `attachment`
+    pub fn is_attachment(&self) -> bool {
+        self.disposition != "inline"
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    // the suggested file name as sent (it may contain a path; see `download::filename`)
+    pub fn filename(&self) -> Option<String> {
+        let extended = self.param("filename*").and_then(|value| {
+            // charset'language'percent-encoded
+            let mut parts = value.splitn(3, '\'');
+            let charset = parts.next()?;
+            let encoded = parts.nth(1)?;
+            if charset.eq_ignore_ascii_case("utf-8") {
+                Some(percent_decode(encoded))
+            } else {
+                None
+            }
+        });
+        extended.or_else(|| self.param("filename").map(|f| f.to_string()))
+    }
+}
+
+// split `src` by `sep` except inside of quoted strings
+pub(crate) fn split_quoted(src: &str, sep: char) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in src.chars() {
+        if escaped {
+            escaped = false;
+        } else if quoted && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c == sep && !quoted {
+            parts.push(std::mem::take(&mut current));
+            continue;
+        }
+        current.push(c);
+    }
+    parts.push(current);
+    parts
+}
+
+// remove surrounding quotes and backslash escapes of a quoted-string
+pub(crate) fn unquote(src: &str) -> String {
+    match src.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(inner) => {
+            let mut result = String::new();
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some(escaped) = chars.next() {
+                            result.push(escaped);
+                        }
+                    }
+                    _ => result.push(c),
+                }
+            }
+            result
+        }
+        None => src.to_string(),
+    }
+}
+
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+fn quote_if_needed(s: &str) -> String {
+    if is_token(s) {
+        s.to_string()
+    } else {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_media_type() {
+        let mime = MediaType::parse("Text/HTML; Charset=\"UTF-8\"; foo=bar").unwrap();
+        assert_eq!(mime.essence(), "text/html");
+        assert_eq!(mime.charset(), Some("UTF-8"));
+        assert_eq!(mime.param("FOO"), Some("bar"));
+        assert!(mime.is_html());
+        assert_eq!(mime.to_string(), "text/html; charset=UTF-8; foo=bar");
+
+        let mime = MediaType::parse("multipart/form-data; boundary=\"a;b\"").unwrap();
+        assert_eq!(mime.param("boundary"), Some("a;b"));
+        assert_eq!(mime.to_string(), "multipart/form-data; boundary=\"a;b\"");
+
+        assert!(MediaType::parse("text").is_err());
+        assert!(MediaType::parse("").is_err());
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let cc = CacheControl::parse("max-age=604800, No-Cache=\"Set-Cookie, Age\", private");
+        assert_eq!(cc.max_age(), Some(604800));
+        assert!(cc.no_cache());
+        assert_eq!(cc.get("no-cache"), Some("Set-Cookie, Age"));
+        assert!(cc.is_private());
+        assert!(!cc.no_store());
+        assert_eq!(cc.to_string(), "max-age=604800, no-cache=\"Set-Cookie, Age\", private");
+    }
+
+    #[test]
+    fn test_etag() {
+        let strong = ETag::parse("\"3147526947\"").unwrap();
+        let weak = ETag::parse("W/\"3147526947\"").unwrap();
+        assert!(!strong.is_weak());
+        assert!(weak.is_weak());
+        assert!(strong.weak_eq(&weak));
+        assert!(!strong.strong_eq(&weak));
+        assert_eq!(weak.to_string(), "W/\"3147526947\"");
+        assert!(ETag::parse("3147526947").is_err());
+    }
+
+    #[test]
+    fn test_range() {
+        assert_eq!(ByteRange::FromTo(0, 499).to_string(), "0-499");
+        assert_eq!(ByteRange::From(1000).resolve(1234), Some((1000, 1233)));
+        assert_eq!(ByteRange::Suffix(200).resolve(100), Some((0, 99)));
+        assert_eq!(ByteRange::FromTo(0, 9999).resolve(1234), Some((0, 1233)));
+        assert_eq!(ByteRange::From(1234).resolve(1234), None);
+
+        let range = ContentRange::parse("bytes 0-499/1234").unwrap();
+        assert_eq!((range.first(), range.len(), range.complete_length()), (0, 500, Some(1234)));
+        assert_eq!(range.to_string(), "bytes 0-499/1234");
+        assert_eq!(ContentRange::parse("bytes 10-19/*").unwrap().complete_length(), None);
+        assert_eq!(ContentRange::parse("bytes */1234").unwrap(), ContentRange::unsatisfied(1234));
+        for invalid in ["bytes 5-4/10", "bytes 0-10/10", "items 0-1/2", "bytes */*", "bytes 0-1"].iter() {
+            assert!(ContentRange::parse(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_content_disposition() {
+        let cd = ContentDisposition::parse(
+            "Attachment; filename=\"EURO rates.txt\"; filename*=utf-8''%e2%82%ac%20rates.txt",
+        );
+        assert_eq!(cd.disposition(), "attachment");
+        assert!(cd.is_attachment());
+        assert_eq!(cd.filename().unwrap(), "€ rates.txt");
+
+        let cd = ContentDisposition::parse("inline; filename=\"a;b.pdf\"; filename*=iso-8859-1'en'%A3.pdf");
+        assert!(!cd.is_attachment());
+        assert_eq!(cd.filename().unwrap(), "a;b.pdf");
+        assert_eq!(ContentDisposition::parse("attachment").filename(), None);
+    }
+}
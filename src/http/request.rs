@@ -1,8 +1,10 @@
 use std::fmt;
 use std::error::Error;
 use std::collections::HashMap;
-use crate::http::Method;
+use crate::http::{Method, Version};
 use crate::http::url::URL;
+use crate::http::date::HttpDate;
+use crate::http::headers::{ByteRange, CacheControl, ETag, IfRange, MediaType};
 
 // ERROR HANDLING -------------------
 #[derive(Debug)]
@@ -40,6 +42,7 @@ impl Error for RequestError {}
 // ```
 
 pub struct Request {
+    url: URL,
     line: RequestLine,
     head: Head,
     body: String,
@@ -52,11 +55,59 @@ impl Request {
         Ok(Self::new(
             &url,
             Method::GET,
-            Head::new(&url.host()),
+            Head::new(&url.authority()),
             None,
         ))
     }
 
+    pub fn post(url: &str, body: &str) -> Result<Self, Box<dyn Error>> {
+        Self::with_body(url, Method::POST, body)
+    }
+
+    pub fn put(url: &str, body: &str) -> Result<Self, Box<dyn Error>> {
+        Self::with_body(url, Method::PUT, body)
+    }
+
+    fn with_body(url: &str, method: Method, body: &str) -> Result<Self, Box<dyn Error>> {
+        let url = URL::parse(url)?;
+        let mut head = Head::new(&url.authority());
+        head.set_content_length(body.len() as u64);
+        Ok(Self::new(&url, method, head, Some(body.to_string())))
+    }
+
+    pub fn url(&self) -> &URL {
+        &self.url
+    }
+
+    pub fn method(&self) -> Method {
+        self.line.method
+    }
+
+    pub fn version(&self) -> Version {
+        self.line.version
+    }
+
+    pub fn set_version(&mut self, version: Version) {
+        self.line.version = version;
+    }
+
+    // replace the request target (`/path` by default)
+    pub fn set_target(&mut self, target: &str) {
+        self.line.path = target.to_string();
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    pub fn head(&self) -> &Head {
+        &self.head
+    }
+
+    pub fn head_mut(&mut self) -> &mut Head {
+        &mut self.head
+    }
+
     pub fn read_host(&self) -> Result<String, RequestError> {
         let head = &self.head;
         let host = match head.0.get("Host") {
@@ -66,12 +117,9 @@ impl Request {
         Ok(host)
     }
 
-    // pub fn post(url: &str, body: &str) -> Self {
-    //
-    // }
-
     pub fn new(url: &URL, method: Method, head: Head, body: Option<String>) -> Self {
         Self {
+            url: url.clone(),
             line: RequestLine::new(method, &url.path(), &url.scheme()),
             head,
             body: match body {
@@ -83,7 +131,13 @@ impl Request {
     }
 
     pub fn build(&mut self) -> Result<String, Box<dyn Error>> {
-        Ok(format!("{}{}\r\n{}", self.line.build(), self.head.build()?, self.body))
+        Ok(format!("{}{}", self.build_head()?, self.body))
+    }
+
+    // request line and head without the body
+    // (used to wait for `100 Continue` before sending the body)
+    pub fn build_head(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(format!("{}{}\r\n", self.line.build(), self.head.build()?))
     }
  
 }
@@ -92,6 +146,7 @@ struct RequestLine {
     method: Method,
     path: String,
     protocol: String,
+    version: Version,
 }
 
 impl RequestLine {
@@ -101,16 +156,12 @@ impl RequestLine {
             method,
             path: path.to_string(),
             protocol: protocol.to_string(),
+            version: Version::HTTP10,
         }
     }
 
     pub fn build(&self) -> String {
-        let method = match self.method {
-            Method::GET => "GET",
-            Method::POST => "POST",
-        };
-
-        format!("{} {} {}\r\n", method, self.path, "HTTP/1.0")
+        format!("{} {} {}\r\n", self.method.as_str(), self.path, self.version.as_str())
     }
 }
 
@@ -151,14 +202,68 @@ impl Head {
         self.0.remove(key);
     }
 
-    pub fn build(&mut self) -> Result<String, HeadError> {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    // typed setters for common fields ----
+
+    pub fn set_content_type(&mut self, media_type: &MediaType) {
+        self.add("Content-Type", &media_type.to_string());
+    }
+
+    pub fn set_content_length(&mut self, len: u64) {
+        self.add("Content-Length", &len.to_string());
+    }
+
+    pub fn set_cache_control(&mut self, cache_control: &CacheControl) {
+        self.add("Cache-Control", &cache_control.to_string());
+    }
+
+    pub fn set_if_none_match(&mut self, etags: &[ETag]) {
+        let etags = etags.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        self.add("If-None-Match", &etags.join(", "));
+    }
+
+    pub fn set_if_modified_since(&mut self, date: HttpDate) {
+        self.add("If-Modified-Since", &date.to_string());
+    }
+
+    // `Range: bytes=0-499, 1000-`
+    pub fn set_range(&mut self, ranges: &[ByteRange]) {
+        let ranges = ranges.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        self.add("Range", &format!("bytes={}", ranges.join(", ")));
+    }
+
+    pub fn set_if_range(&mut self, if_range: &IfRange) {
+        self.add("If-Range", &if_range.to_string());
+    }
+
+    pub fn set_accept(&mut self, media_types: &[MediaType]) {
+        let media_types = media_types.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        self.add("Accept", &media_types.join(", "));
+    }
+
+    pub fn set_user_agent(&mut self, user_agent: &str) {
+        self.add("User-Agent", user_agent);
+    }
+
+    // `Cookie: name1=value1; name2=value2`
+    pub fn set_cookies(&mut self, cookies: &[(&str, &str)]) {
+        let cookies = cookies.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>();
+        self.add("Cookie", &cookies.join("; "));
+    }
+
+    pub fn build(&self) -> Result<String, HeadError> {
         let host = match self.0.get("Host") {
             Some(s) => s,
             None => return Err(HeadError::NoHost),
         };
         let mut result = format!("Host: {}\r\n", host);
-        self.remove("Host");
-        for (k, v) in self.0.drain() {
+        for (k, v) in self.0.iter().filter(|(k, _)| k.as_str() != "Host") {
             result = format!("{}{}:{}\r\n", result, k, v);
         }
         Ok(result)
@@ -191,6 +296,35 @@ mod test {
         println!("{}", raw_req);
     }
 
+    #[test]
+    fn test_post() {
+        let mut req = Request::post("http://127.0.0.1:8080/form", "a=1").unwrap();
+        req.set_version(Version::HTTP11);
+        let raw_req = req.build().unwrap();
+
+        assert!(raw_req.starts_with("POST /form HTTP/1.1\r\nHost: 127.0.0.1:8080\r\n"));
+        assert!(raw_req.contains("Content-Length:3\r\n"));
+        assert!(raw_req.ends_with("\r\n\r\na=1"));
+        // building does not consume the head
+        assert_eq!(req.build().unwrap(), raw_req);
+    }
+
+    #[test]
+    fn test_typed_setters() {
+        let mut head = Head::new("example.com");
+        head.set_content_type(&MediaType::new("text", "plain").with_param("charset", "utf-8"));
+        head.set_content_length(4);
+        head.set_if_none_match(&[ETag::strong("3147526947"), ETag::weak("abc")]);
+        head.set_if_modified_since(HttpDate::parse("Thu, 17 Oct 2019 07:18:26 GMT").unwrap());
+        head.set_cookies(&[("id", "a3fWa"), ("lang", "ja")]);
+
+        assert_eq!(head.get("content-type"), Some("text/plain; charset=utf-8"));
+        assert_eq!(head.get("Content-Length"), Some("4"));
+        assert_eq!(head.get("If-None-Match"), Some("\"3147526947\", W/\"abc\""));
+        assert_eq!(head.get("If-Modified-Since"), Some("Thu, 17 Oct 2019 07:18:26 GMT"));
+        assert_eq!(head.get("Cookie"), Some("id=a3fWa; lang=ja"));
+    }
+
 }
 
 
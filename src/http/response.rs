@@ -1,18 +1,31 @@
-use std::collections::HashMap;
 use crate::utils::consumer::Consumer;
+use crate::utils::charset;
+use crate::http::Version;
+use crate::http::date::HttpDate;
+use crate::http::headers::{CacheControl, ContentDisposition, ContentRange, ETag, MediaType};
+use crate::http::status::StatusCode;
 use std::fmt;
 use std::error::Error;
+use std::io::{self, BufRead, ErrorKind, Read};
 
 // ERROR HANDLING ------------------------
 #[derive(Debug)]
 pub enum ResponseError {
     NoLine,
+    NoHeaderEnd,
+    InvalidChunk,
+    InvalidByteranges,
+    Status(StatusCode),
 }
 
 impl fmt::Display for ResponseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::NoLine => write!(f, "Response error: cannot find the first line, invalid HTTP response"),
+            Self::NoHeaderEnd => write!(f, "Response error: cannot find the end of the header, invalid HTTP response"),
+            Self::InvalidChunk => write!(f, "Response error: invalid chunked transfer coding"),
+            Self::InvalidByteranges => write!(f, "Response error: invalid `206 Partial Content` body"),
+            Self::Status(code) => write!(f, "Response error: server returned `{}`", code),
             // _ => write!(f, "Undefined error"),
         }
     }
@@ -43,38 +56,347 @@ impl Error for ResponseError {}
 // ```
 
 
+#[derive(Debug)]
 pub struct Response {
     status_line: StatusLine,
     header: Header,
-    body: String,
+    body: Vec<u8>,
+    interim: Vec<ResponseHead>,
 }
 
 impl Response {
     
-    pub fn parse(response: &str) -> Result<Self, Box<dyn Error>> {
-        // println!("RAW------:\n{:?}", response);
-        let (first_line, rest) = match response.split_once("\r\n") {
-            Some(v) => v,
-            None => return Err(Box::new(ResponseError::NoLine)),
-        };
-        let status_line = StatusLine::parse(first_line)?;
-        
-        let (header_str, rest) = rest.split_once("\r\n\r\n").unwrap();
-        let header = Header::parse(header_str)?;
+    pub fn parse(response: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut reader = response;
+        Self::read_from(&mut reader)
+    }
+
+    // Read a response from `reader`.
+    // Interim (1xx) responses before the final one are recorded in `interim()`.
+    pub fn read_from<R: BufRead>(reader: &mut R) -> Result<Self, Box<dyn Error>> {
+        Self::read_after(reader, vec![])
+    }
 
+    // continue reading a response after the interim responses already read
+    pub(crate) fn read_after<R: BufRead>(reader: &mut R, mut interim: Vec<ResponseHead>) -> Result<Self, Box<dyn Error>> {
+        loop {
+            let head = ResponseHead::read_from(reader)?;
+            if head.is_interim() {
+                interim.push(head);
+                continue;
+            }
+            return Self::from_head(head, reader, interim);
+        }
+    }
+
+    // read the body of the final response `head`
+    pub(crate) fn from_head<R: BufRead>(head: ResponseHead, reader: &mut R, interim: Vec<ResponseHead>) -> Result<Self, Box<dyn Error>> {
+        let body = read_body(reader, &head)?;
         Ok(Self {
-            status_line,
-            header,
-            body: rest.to_string(),
+            status_line: head.status_line,
+            header: head.header,
+            body,
+            interim,
         })
     }
+
+    pub fn status(&self) -> StatusCode {
+        self.status_line.status_code()
+    }
+
+    // reason phrase sent by the server (may be empty)
+    pub fn reason(&self) -> &str {
+        &self.status_line.status
+    }
+
+    pub fn version(&self) -> Version {
+        self.status_line.version()
+    }
+
+    pub fn headers(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn headers_mut(&mut self) -> &mut Header {
+        &mut self.header
+    }
+
+    // interim (1xx) responses received before this response
+    pub fn interim(&self) -> &[ResponseHead] {
+        &self.interim
+    }
+
+    // headers of `103 Early Hints` responses (usually `Link: </style.css>; rel=preload`)
+    pub fn early_hints(&self) -> Vec<&Header> {
+        self.interim
+            .iter()
+            .filter(|head| head.status() == StatusCode::EARLY_HINTS)
+            .map(|head| head.headers())
+            .collect()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.body
+    }
+
+    // decode the body with the charset of `Content-Type` (UTF-8 if unknown)
+    pub fn text(&self) -> String {
+        let content_type = self.header.content_type();
+        let label = content_type.as_ref().and_then(|m| m.charset());
+        charset::decode(&self.body, label)
+    }
+
+    // the ranges of a `206 Partial Content` response: the body for a single range,
+    // or each part of a `multipart/byteranges` body (empty for other responses)
+    pub fn byteranges(&self) -> Result<Vec<(ContentRange, &[u8])>, ResponseError> {
+        if self.status() != StatusCode::PARTIAL_CONTENT {
+            return Ok(vec![]);
+        }
+        if let Some(range) = self.header.content_range() {
+            return Ok(vec![(range, &self.body)]);
+        }
+        let boundary = match self.header.content_type() {
+            Some(media_type) if media_type.essence() == "multipart/byteranges" => {
+                media_type.param("boundary").map(|b| b.to_string())
+            }
+            _ => None,
+        };
+        boundary
+            .and_then(|boundary| parse_byteranges(&self.body, &boundary))
+            .ok_or(ResponseError::InvalidByteranges)
+    }
+
+    // turn 4xx and 5xx responses into `ResponseError::Status`
+    pub fn error_for_status(self) -> Result<Self, ResponseError> {
+        let status = self.status();
+        if status.is_client_error() || status.is_server_error() {
+            Err(ResponseError::Status(status))
+        } else {
+            Ok(self)
+        }
+    }
 }
 
+// Status line and header of a response, without the body.
+// Interim (1xx) responses consist only of this.
+#[derive(Debug)]
+pub struct ResponseHead {
+    status_line: StatusLine,
+    header: Header,
+}
 
-// ERRROR HANDLING -----------------------
+impl ResponseHead {
+    pub fn read_from<R: BufRead>(reader: &mut R) -> Result<Self, Box<dyn Error>> {
+        let first_line = match read_line(reader)? {
+            Some(line) if !line.is_empty() => line,
+            _ => return Err(Box::new(ResponseError::NoLine)),
+        };
+        let status_line = StatusLine::parse(&first_line)?;
+
+        let mut lines = vec![];
+        loop {
+            match read_line(reader)? {
+                Some(line) if line.is_empty() => break,
+                Some(line) => lines.push(line),
+                None => return Err(Box::new(ResponseError::NoHeaderEnd)),
+            }
+        }
+        let header = Header::parse(&lines.join("\r\n"))?;
+
+        Ok(Self { status_line, header })
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status_line.status_code()
+    }
+
+    pub fn headers(&self) -> &Header {
+        &self.header
+    }
+
+    // 1xx except `101 Switching Protocols`, which is final for HTTP/1.1
+    pub fn is_interim(&self) -> bool {
+        self.status().is_informational() && self.status() != StatusCode::SWITCHING_PROTOCOLS
+    }
+}
+
+// read a line terminated by CRLF (or a bare LF) without the terminator.
+// return `None` on EOF.
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = vec![];
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.ends_with(b"\n") {
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+    }
+    // field values are ASCII (or obsolete ISO-8859-1 text)
+    Ok(Some(charset::decode(&line, Some("iso-8859-1"))))
+}
+
+fn read_body<R: BufRead>(reader: &mut R, head: &ResponseHead) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut body = vec![];
+    BodyReader::new(reader, head).read_to_end(&mut body)?;
+    Ok(body)
+}
+
+// Reads the body of `head` as it arrives, framed by `Transfer-Encoding: chunked`,
+// `Content-Length` or the end of the connection. A body truncated before
+// `Content-Length` ends early without an error.
+// ref: https://www.rfc-editor.org/rfc/rfc9112#section-6.3
 //
-// TODO
-// - validate status line (status, status code, and protocol)
+// Example chunked body:
+// ```
+// 4\r\n
+// Wiki\r\n
+// 0\r\n
+// \r\n
+// ```
+pub struct BodyReader<R> {
+    reader: R,
+    framing: Framing,
+}
+
+enum Framing {
+    // bytes left
+    Length(u64),
+    // bytes left in the current chunk (`None` before a chunk size line)
+    Chunked(Option<u64>),
+    Close,
+    Done,
+}
+
+impl<R: BufRead> BodyReader<R> {
+    pub fn new(reader: R, head: &ResponseHead) -> Self {
+        let status = head.status();
+        let header = head.headers();
+        let chunked = header
+            .get_all("Transfer-Encoding")
+            .iter()
+            .any(|v| v.to_ascii_lowercase().contains("chunked"));
+        let framing = if status == StatusCode::NO_CONTENT || status == StatusCode::NOT_MODIFIED {
+            Framing::Done
+        } else if chunked {
+            Framing::Chunked(None)
+        } else if let Some(len) = header.content_length() {
+            Framing::Length(len)
+        } else {
+            Framing::Close
+        };
+        Self { reader, framing }
+    }
+
+    // the size of the next chunk (chunk extensions are ignored)
+    fn chunk_size(&mut self) -> io::Result<u64> {
+        let line = read_line(&mut self.reader)?.ok_or_else(invalid_chunk)?;
+        let size = line.split(';').next().unwrap_or("").trim();
+        u64::from_str_radix(size, 16).map_err(|_| invalid_chunk())
+    }
+}
+
+impl<R: BufRead> Read for BodyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.framing {
+                Framing::Done => return Ok(0),
+                Framing::Close => return self.reader.read(buf),
+                Framing::Length(left) => {
+                    let max = (buf.len() as u64).min(left) as usize;
+                    let n = self.reader.read(&mut buf[..max])?;
+                    self.framing = if n == 0 { Framing::Done } else { Framing::Length(left - n as u64) };
+                    return Ok(n);
+                }
+                Framing::Chunked(None) => {
+                    let size = self.chunk_size()?;
+                    if size == 0 {
+                        // skip trailer fields
+                        while let Some(line) = read_line(&mut self.reader)? {
+                            if line.is_empty() {
+                                break;
+                            }
+                        }
+                        self.framing = Framing::Done;
+                    } else {
+                        self.framing = Framing::Chunked(Some(size));
+                    }
+                }
+                Framing::Chunked(Some(0)) => {
+                    // CRLF after the chunk data
+                    read_line(&mut self.reader)?;
+                    self.framing = Framing::Chunked(None);
+                }
+                Framing::Chunked(Some(left)) => {
+                    let max = (buf.len() as u64).min(left) as usize;
+                    let n = self.reader.read(&mut buf[..max])?;
+                    if n == 0 && max > 0 {
+                        return Err(invalid_chunk());
+                    }
+                    self.framing = Framing::Chunked(Some(left - n as u64));
+                    return Ok(n);
+                }
+            }
+        }
+    }
+}
+
+fn invalid_chunk() -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, ResponseError::InvalidChunk)
+}
+
+// Example `multipart/byteranges` body (boundary `THIS_STRING_SEPARATES`):
+// ```
+// --THIS_STRING_SEPARATES\r\n
+// Content-Type: application/pdf\r\n
+// Content-Range: bytes 500-999/8000\r\n
+// \r\n
+// ...the first range...\r\n
+// --THIS_STRING_SEPARATES\r\n
+// Content-Type: application/pdf\r\n
+// Content-Range: bytes 7000-7999/8000\r\n
+// \r\n
+// ...the second range\r\n
+// --THIS_STRING_SEPARATES--\r\n
+// ```
+// ref: https://www.rfc-editor.org/rfc/rfc9110#section-14.6
+fn parse_byteranges<'a>(body: &'a [u8], boundary: &str) -> Option<Vec<(ContentRange, &'a [u8])>> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = vec![];
+    let mut pos = find(body, &delimiter, 0)? + delimiter.len();
+    // `--` after a delimiter closes the body
+    while !body[pos..].starts_with(b"--") {
+        // the rest of the delimiter line (transport padding)
+        let line_end = find(body, b"\r\n", pos)?;
+        let head_end = find(body, b"\r\n\r\n", line_end)?;
+        let head = if head_end > line_end { &body[line_end + 2..head_end] } else { &[][..] };
+        let header = Header::parse(&charset::decode(head, Some("iso-8859-1"))).ok()?;
+        let range = header.content_range()?;
+
+        let start = head_end + 4;
+        let end = find(body, &[b"\r\n", &delimiter[..]].concat(), start)?;
+        parts.push((range, &body[start..end]));
+        pos = end + 2 + delimiter.len();
+    }
+    Some(parts)
+}
+
+// the position of `needle` in `haystack` at or after `from`
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+
+// ERRROR HANDLING -----------------------
 #[derive(Debug)]
 pub enum StatusLineError {
     NoStatus,
@@ -99,8 +421,13 @@ impl Error for StatusLineError {}
 // Example status line:
 // ```
 // HTTP/1.1 200 OK
+// HTTP/1.1 404 Not Found
+// HTTP/1.1 200 
 // ```
+//
+// The reason phrase is the rest of the line; it may contain spaces or be empty.
 
+#[derive(Debug)]
 pub struct StatusLine {
     status: String,
     status_code: usize,
@@ -113,27 +440,23 @@ impl StatusLine {
         let mut con = Consumer::new(line);
         
         let proto = match con.next_until_space() {
-            Some(s) => {
+            Some(s) if Version::parse(&s).is_some() => {
                 con.skip_space();
                 s
             },
-            None => return Err(StatusLineError::NoProtocol),
+            _ => return Err(StatusLineError::NoProtocol),
         };
 
         let status_code = match con.to_usize() {
-            Some(code) => {
-                con.skip_space();
-                code
-            },
-            None => return Err(StatusLineError::NoStatusCode),
+            Some(code) if (100..=999).contains(&code) => code,
+            _ => return Err(StatusLineError::NoStatusCode),
         };
 
-        let status = match con.next_until_space() {
-            Some(s) => {
-                con.skip_space();
-                s
-            },
-            None => return Err(StatusLineError::NoStatus),
+        // status code and reason phrase are separated by a space
+        let status = match con.next_char() {
+            Some(' ') => con.rest().trim_end().to_string(),
+            Some(_) => return Err(StatusLineError::NoStatus),
+            None => String::new(),
         };
 
         Ok(Self {
@@ -142,6 +465,20 @@ impl StatusLine {
             proto,
         })
     }
+
+    pub fn status_code(&self) -> StatusCode {
+        // validated in `parse`
+        StatusCode::from_u16(self.status_code as u16).unwrap()
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.status
+    }
+
+    pub fn version(&self) -> Version {
+        // validated in `parse`
+        Version::parse(&self.proto).unwrap()
+    }
 }
 
 // ERROR HANDING -------------------------
@@ -163,22 +500,154 @@ impl Error for HeaderError {}
 // ---------------------------------------
 
 
-pub struct Header(HashMap<String, String>);
+// Field lines are held in the received order so that repeated fields
+// (such as `Set-Cookie`) are kept. Field names are compared case-insensitively.
+#[derive(Debug)]
+pub struct Header(Vec<(String, String)>);
 
 impl Header {
     pub fn parse(src: &str) -> Result<Self, HeaderError> {
-        let mut header = Self(HashMap::new());
+        let mut header = Self(Vec::new());
         let lines = src.split("\r\n");
 
-        for line in lines {
+        for line in lines.filter(|l| !l.is_empty()) {
             let (key, value) = match line.split_once(":") {
                 Some(v) => v,
                 None => return Err(HeaderError::InvalidHeader(line.to_string())),
             };
-            header.0.insert(key.to_string(), value.trim().to_string());
+            header.0.push((key.to_string(), value.trim().to_string()));
         }
         Ok(header)
     }
+
+    // return the first value of the field `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    // return all values of the field `name` in the received order
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn add(&mut self, name: &str, value: &str) {
+        self.0.push((name.to_string(), value.to_string()));
+    }
+
+    // replace all values of the field `name` with `value`
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.remove(name);
+        self.0.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    }
+
+    // `Content-Type: text/html; charset=UTF-8`
+    pub fn content_type(&self) -> Option<MediaType> {
+        self.get("Content-Type").and_then(|v| MediaType::parse(v).ok())
+    }
+
+    // `Content-Length: 648`
+    pub fn content_length(&self) -> Option<u64> {
+        self.get("Content-Length").and_then(|v| v.trim().parse::<u64>().ok())
+    }
+
+    // `Accept-Ranges: bytes`
+    pub fn accepts_ranges(&self) -> bool {
+        self.get("Accept-Ranges")
+            .is_some_and(|v| v.split(',').any(|unit| unit.trim().eq_ignore_ascii_case("bytes")))
+    }
+
+    // `Content-Range: bytes 0-499/1234`
+    pub fn content_range(&self) -> Option<ContentRange> {
+        self.get("Content-Range").and_then(|v| ContentRange::parse(v).ok())
+    }
+
+    // `Content-Disposition: attachment; filename="report.pdf"`
+    pub fn content_disposition(&self) -> Option<ContentDisposition> {
+        self.get("Content-Disposition").map(ContentDisposition::parse)
+    }
+
+    // `Location: http://example.com/`
+    pub fn location(&self) -> Option<&str> {
+        self.get("Location")
+    }
+
+    // `Date: Thu, 06 May 2021 05:24:49 GMT`
+    pub fn date(&self) -> Option<HttpDate> {
+        self.http_date("Date")
+    }
+
+    // `Last-Modified: Thu, 17 Oct 2019 07:18:26 GMT`
+    pub fn last_modified(&self) -> Option<HttpDate> {
+        self.http_date("Last-Modified")
+    }
+
+    // `Expires: Thu, 13 May 2021 05:24:49 GMT`
+    pub fn expires(&self) -> Option<HttpDate> {
+        self.http_date("Expires")
+    }
+
+    fn http_date(&self, name: &str) -> Option<HttpDate> {
+        self.get(name).and_then(|v| HttpDate::parse(v).ok())
+    }
+
+    // `Age: 542257`
+    pub fn age(&self) -> Option<u64> {
+        self.get("Age").and_then(|v| v.trim().parse::<u64>().ok())
+    }
+
+    // `Cache-Control: max-age=604800`
+    // Directives of repeated fields are merged.
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        let values = self.get_all("Cache-Control");
+        if values.is_empty() {
+            return None;
+        }
+        let mut cache_control = CacheControl::new();
+        for value in values {
+            cache_control.extend(CacheControl::parse(value));
+        }
+        Some(cache_control)
+    }
+
+    // `Etag: "3147526947"`
+    pub fn etag(&self) -> Option<ETag> {
+        self.get("ETag").and_then(|v| ETag::parse(v).ok())
+    }
+
+    // `Vary: Accept-Encoding`
+    pub fn vary(&self) -> Vec<String> {
+        self.get_all("Vary")
+            .iter()
+            .flat_map(|v| v.split(','))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
+    // `Set-Cookie: id=a3fWa; Max-Age=2592000`
+    // Each `Set-Cookie` field is returned unparsed.
+    pub fn set_cookies(&self) -> Vec<&str> {
+        self.get_all("Set-Cookie")
+    }
 }
 
 
@@ -206,18 +675,151 @@ X-Cache: HIT\r
 Content-Length: 648\r
 \r
 body";
-        let res = Response::parse(raw_res).unwrap();
+        let res = Response::parse(raw_res.as_bytes()).unwrap();
         
         assert_eq!(res.status_line.status, "OK");
         assert_eq!(res.status_line.status_code, 200);
         assert_eq!(res.status_line.proto, "HTTP/1.1");
-        assert_eq!(res.header.0.get("Age"), Some(&"579161".to_string()));
-        assert_eq!(res.body, "body");
+        assert_eq!(res.header.get("Age"), Some("579161"));
+        assert_eq!(res.body, b"body");
     }
-}
 
+    #[test]
+    fn test_status_line() {
+        let line = StatusLine::parse("HTTP/1.1 404 Not Found").unwrap();
+        assert_eq!(line.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(line.reason(), "Not Found");
+
+        let line = StatusLine::parse("HTTP/1.0 200 ").unwrap();
+        assert_eq!(line.reason(), "");
+        assert_eq!(line.version(), Version::HTTP10);
+        assert_eq!(StatusLine::parse("HTTP/1.1 204").unwrap().reason(), "");
+
+        assert!(StatusLine::parse("HTTP/1.1 20 OK").is_err());
+        assert!(StatusLine::parse("HTTP/1.1 200OK").is_err());
+        assert!(StatusLine::parse("FOO/1.1 200 OK").is_err());
+    }
 
+    #[test]
+    fn test_response_api() {
+        let raw_res = b"HTTP/1.1 404 Not Found\r
+Content-Type: text/plain; charset=ISO-8859-1\r
+\r
+caf\xe9";
+        let res = Response::parse(raw_res).unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.status().is_client_error());
+        assert_eq!(res.version(), Version::HTTP11);
+        assert_eq!(res.text(), "café");
+        assert_eq!(res.bytes(), b"caf\xe9");
+        assert!(res.error_for_status().is_err());
+
+        let res = Response::parse(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+        assert_eq!(res.reason(), "No Content");
+        assert!(res.headers().iter().next().is_none());
+        assert!(res.error_for_status().is_ok());
+    }
 
+    #[test]
+    fn test_interim() {
+        let raw_res = b"HTTP/1.1 100 Continue\r
+\r
+HTTP/1.1 103 Early Hints\r
+Link: </style.css>; rel=preload; as=style\r
+\r
+HTTP/1.1 200 OK\r
+Transfer-Encoding: chunked\r
+\r
+4\r
+Wiki\r
+6;ext=1\r
+pedia \r
+0\r
+\r
+";
+        let res = Response::parse(raw_res).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.interim().len(), 2);
+        assert_eq!(res.interim()[0].status(), StatusCode::CONTINUE);
+        let hints = res.early_hints();
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].get("Link"), Some("</style.css>; rel=preload; as=style"));
+        assert_eq!(res.bytes(), b"Wikipedia ");
+    }
 
+    #[test]
+    fn test_header() {
+        let raw_header = "Content-Type: text/html; charset=UTF-8\r
+Content-Length: 648\r
+Cache-Control: max-age=604800\r
+cache-control: must-revalidate\r
+Date: Thu, 06 May 2021 06:39:14 GMT\r
+Expires: Thursday, 13-May-21 06:39:14 GMT\r
+Last-Modified: Thu Oct 17 07:18:26 2019\r
+Etag: \"3147526947+ident\"\r
+Location: /index.html\r
+Set-Cookie: id=a3fWa; Max-Age=2592000\r
+Set-Cookie: lang=ja; Path=/";
+        let header = Header::parse(raw_header).unwrap();
+
+        let mime = header.content_type().unwrap();
+        assert_eq!(mime.essence(), "text/html");
+        assert_eq!(mime.charset(), Some("UTF-8"));
+        assert_eq!(header.content_length(), Some(648));
+        assert_eq!(header.location(), Some("/index.html"));
+
+        let cache_control = header.cache_control().unwrap();
+        assert_eq!(cache_control.max_age(), Some(604800));
+        assert!(cache_control.must_revalidate());
+
+        assert_eq!(header.date().unwrap().to_string(), "Thu, 06 May 2021 06:39:14 GMT");
+        assert_eq!(header.expires().unwrap().to_string(), "Thu, 13 May 2021 06:39:14 GMT");
+        assert_eq!(header.last_modified().unwrap().to_string(), "Thu, 17 Oct 2019 07:18:26 GMT");
+        assert_eq!(header.etag().unwrap().tag(), "3147526947+ident");
+        assert_eq!(header.set_cookies(), vec!["id=a3fWa; Max-Age=2592000", "lang=ja; Path=/"]);
+        assert_eq!(header.get("CONTENT-LENGTH"), Some("648"));
+    }
 
+    #[test]
+    fn test_byteranges() {
+        let single = b"HTTP/1.1 206 Partial Content\r\nAccept-Ranges: none, bytes\r\nContent-Range: bytes 2-4/10\r\nContent-Length: 3\r\n\r\ncde";
+        let res = Response::parse(single).unwrap();
+        assert!(res.headers().accepts_ranges());
+        assert_eq!(res.byteranges().unwrap(), vec![(ContentRange::new(2, 4, Some(10)), &b"cde"[..])]);
+
+        let multipart = b"HTTP/1.1 206 Partial Content\r\n\
+            Content-Type: multipart/byteranges; boundary=SEP\r\n\r\n\
+            --SEP\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\nab\r\n\
+            --SEP \r\nContent-Range: bytes 8-9/10\r\n\r\nij\r\n\
+            --SEP--\r\n";
+        let res = Response::parse(multipart).unwrap();
+        let ranges = res.byteranges().unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0], (ContentRange::new(0, 1, Some(10)), &b"ab"[..]));
+        assert_eq!(ranges[1], (ContentRange::new(8, 9, Some(10)), &b"ij"[..]));
+
+        let broken = b"HTTP/1.1 206 Partial Content\r\nContent-Type: multipart/byteranges; boundary=SEP\r\n\r\n--SEP\r\n";
+        assert!(Response::parse(broken).unwrap().byteranges().is_err());
+        assert!(Response::parse(b"HTTP/1.1 200 OK\r\n\r\n").unwrap().byteranges().unwrap().is_empty());
+    }
 
+    #[test]
+    fn test_body_reader() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5;ext=1\r\npedia\r\n0\r\nX-Trailer: 1\r\n\r\nnext";
+        let mut reader = &raw[..];
+        let head = ResponseHead::read_from(&mut reader).unwrap();
+        let mut body = BodyReader::new(&mut reader, &head);
+        // small reads see the body piece by piece
+        let mut buf = [0; 3];
+        assert_eq!(body.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf, b"Wik");
+        let mut rest = vec![];
+        body.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"ipedia");
+        // the trailer is consumed but nothing after the body
+        assert_eq!(reader, b"next");
+
+        let truncated = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nA\r\nWiki";
+        assert!(Response::parse(truncated).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+// SOCKS proxy handshakes
+//
+// - SOCKS5 (RFC 1928) with username/password authentication (RFC 1929)
+// - SOCKS4 and SOCKS4a (the proxy resolves the host name)
+//
+// After a successful handshake the stream is connected to the target,
+// so the request is sent as if connected directly.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{IpAddr, ToSocketAddrs};
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum SocksError {
+    NoAcceptableMethod,
+    AuthFailed,
+    Rejected(u8),
+    InvalidReply,
+    TooLong(String),
+    NoAddress(String),
+}
+
+impl fmt::Display for SocksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAcceptableMethod => write!(f, "SOCKS error: no acceptable authentication method"),
+            Self::AuthFailed => write!(f, "SOCKS error: authentication failed"),
+            Self::Rejected(code) => write!(f, "SOCKS error: request rejected: {}", reply_message(*code)),
+            Self::InvalidReply => write!(f, "SOCKS error: invalid reply from the proxy"),
+            Self::TooLong(s) => write!(f, "SOCKS error: too long (max 255 bytes): `{}`", s),
+            Self::NoAddress(s) => write!(f, "SOCKS error: cannot resolve `{}`", s),
+        }
+    }
+}
+
+impl Error for SocksError {}
+// ------------------------------------
+
+fn reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "general SOCKS server failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        // SOCKS4
+        0x5B => "request rejected or failed",
+        0x5C => "identd is not reachable",
+        0x5D => "identd reported a different user id",
+        _ => "unknown error",
+    }
+}
+
+// `[::1]` -> `::1`
+fn bare_host(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+fn resolve(host: &str, port: u16) -> Result<IpAddr, Box<dyn Error>> {
+    let host = bare_host(host);
+    match (host, port).to_socket_addrs()?.next() {
+        Some(addr) => Ok(addr.ip()),
+        None => Err(Box::new(SocksError::NoAddress(host.to_string()))),
+    }
+}
+
+// Example (remote DNS, with authentication):
+// ```
+// client -> 05 02 00 02                  ; version 5, methods: no auth, username/password
+// server <- 05 02                        ; username/password
+// client -> 01 04 user 04 pass           ; RFC 1929
+// server <- 01 00                        ; success
+// client -> 05 01 00 03 0b example.com 00 50
+// server <- 05 00 00 01 00 00 00 00 00 00 ; succeeded, bound address
+// ```
+pub fn socks5_connect<S: Read + Write>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+    remote_dns: bool,
+) -> Result<(), Box<dyn Error>> {
+    // method negotiation
+    let greeting: &[u8] = match credentials {
+        Some(_) => &[0x05, 0x02, 0x00, 0x02],
+        None => &[0x05, 0x01, 0x00],
+    };
+    stream.write_all(greeting)?;
+    let mut choice = [0; 2];
+    stream.read_exact(&mut choice)?;
+    match (choice, credentials) {
+        ([0x05, 0x00], _) => {}
+        ([0x05, 0x02], Some((user, pass))) => {
+            let mut auth = vec![0x01];
+            for field in [user, pass].iter() {
+                if field.len() > 255 {
+                    return Err(Box::new(SocksError::TooLong(field.to_string())));
+                }
+                auth.push(field.len() as u8);
+                auth.extend_from_slice(field.as_bytes());
+            }
+            stream.write_all(&auth)?;
+            let mut status = [0; 2];
+            stream.read_exact(&mut status)?;
+            if status[1] != 0x00 {
+                return Err(Box::new(SocksError::AuthFailed));
+            }
+        }
+        ([0x05, 0xFF], _) => return Err(Box::new(SocksError::NoAcceptableMethod)),
+        _ => return Err(Box::new(SocksError::InvalidReply)),
+    }
+
+    // CONNECT request
+    let mut req = vec![0x05, 0x01, 0x00];
+    let host = bare_host(host);
+    let addr = match host.parse::<IpAddr>() {
+        Ok(addr) => Some(addr),
+        Err(_) if remote_dns => None,
+        Err(_) => Some(resolve(host, port)?),
+    };
+    match addr {
+        Some(IpAddr::V4(v4)) => {
+            req.push(0x01);
+            req.extend_from_slice(&v4.octets());
+        }
+        Some(IpAddr::V6(v6)) => {
+            req.push(0x04);
+            req.extend_from_slice(&v6.octets());
+        }
+        None => {
+            if host.len() > 255 {
+                return Err(Box::new(SocksError::TooLong(host.to_string())));
+            }
+            req.push(0x03);
+            req.push(host.len() as u8);
+            req.extend_from_slice(host.as_bytes());
+        }
+    }
+    req.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&req)?;
+
+    // reply: VER REP RSV ATYP BND.ADDR BND.PORT
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply)?;
+    if reply[0] != 0x05 {
+        return Err(Box::new(SocksError::InvalidReply));
+    }
+    if reply[1] != 0x00 {
+        return Err(Box::new(SocksError::Rejected(reply[1])));
+    }
+    let addr_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        _ => return Err(Box::new(SocksError::InvalidReply)),
+    };
+    let mut bound = vec![0; addr_len + 2];
+    stream.read_exact(&mut bound)?;
+    Ok(())
+}
+
+// Example (SOCKS4a):
+// ```
+// client -> 04 01 00 50 00 00 00 01 user 00 example.com 00
+// server <- 00 5a 00 00 00 00 00 00      ; request granted
+// ```
+//
+// With `remote_dns` false (plain SOCKS4) the host is resolved locally and must be IPv4.
+pub fn socks4_connect<S: Read + Write>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+    user_id: &str,
+    remote_dns: bool,
+) -> Result<(), Box<dyn Error>> {
+    let host = bare_host(host);
+    let mut req = vec![0x04, 0x01];
+    req.extend_from_slice(&port.to_be_bytes());
+
+    let addr = match host.parse::<IpAddr>() {
+        Ok(addr) => Some(addr),
+        Err(_) if remote_dns => None,
+        Err(_) => Some(resolve(host, port)?),
+    };
+    match addr {
+        Some(IpAddr::V4(v4)) => {
+            req.extend_from_slice(&v4.octets());
+            req.extend_from_slice(user_id.as_bytes());
+            req.push(0x00);
+        }
+        Some(IpAddr::V6(_)) => return Err(Box::new(SocksError::NoAddress(host.to_string()))),
+        None => {
+            // 0.0.0.x (x != 0) tells the proxy to resolve the host name
+            req.extend_from_slice(&[0, 0, 0, 1]);
+            req.extend_from_slice(user_id.as_bytes());
+            req.push(0x00);
+            req.extend_from_slice(host.as_bytes());
+            req.push(0x00);
+        }
+    }
+    stream.write_all(&req)?;
+
+    let mut reply = [0; 8];
+    stream.read_exact(&mut reply)?;
+    if reply[0] != 0x00 {
+        return Err(Box::new(SocksError::InvalidReply));
+    }
+    if reply[1] != 0x5A {
+        return Err(Box::new(SocksError::Rejected(reply[1])));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::client::Client;
+    use crate::http::proxy::{Proxy, ProxyConfig};
+    use crate::http::request::Request;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn read_n(stream: &mut TcpStream, n: usize) -> Vec<u8> {
+        let mut buf = vec![0; n];
+        stream.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    fn read_until_nul(stream: &mut TcpStream) -> Vec<u8> {
+        let mut buf = vec![];
+        loop {
+            let b = read_n(stream, 1)[0];
+            if b == 0 {
+                return buf;
+            }
+            buf.push(b);
+        }
+    }
+
+    // answer the HTTP request on an established SOCKS connection
+    fn respond(stream: &mut TcpStream) {
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).unwrap();
+        let line = String::from_utf8_lossy(&buf[..n]).lines().next().unwrap().to_string();
+        write!(stream, "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{}", line.len(), line).unwrap();
+    }
+
+    // a SOCKS5 server which requires `user:pass` and expects `origin.test:80`
+    fn serve_socks5() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            assert_eq!(read_n(&mut stream, 4), [0x05, 0x02, 0x00, 0x02]);
+            stream.write_all(&[0x05, 0x02]).unwrap();
+            assert_eq!(read_n(&mut stream, 11), b"\x01\x04user\x04pass");
+            stream.write_all(&[0x01, 0x00]).unwrap();
+            assert_eq!(read_n(&mut stream, 5), [0x05, 0x01, 0x00, 0x03, 11]);
+            assert_eq!(read_n(&mut stream, 13), b"origin.test\x00\x50");
+            stream.write_all(&[0x05, 0x00, 0x00, 0x03, 4]).unwrap();
+            stream.write_all(b"host\x00\x00").unwrap();
+            respond(&mut stream);
+        });
+        format!("socks5h://user:pass@{}", addr)
+    }
+
+    #[test]
+    fn test_socks5() {
+        let proxy = Proxy::parse(&serve_socks5()).unwrap();
+        let mut client = Client::new();
+        client.set_proxy(ProxyConfig::all(proxy));
+        let res = client.send(Request::get("http://origin.test/a").unwrap()).unwrap();
+        assert_eq!(res.text(), "GET /a HTTP/1.0");
+    }
+
+    #[test]
+    fn test_socks4a() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            assert_eq!(read_n(&mut stream, 8), [0x04, 0x01, 0x00, 0x50, 0, 0, 0, 1]);
+            assert_eq!(read_until_nul(&mut stream), b"aktis");
+            assert_eq!(read_until_nul(&mut stream), b"origin.test");
+            stream.write_all(&[0x00, 0x5A, 0, 0, 0, 0, 0, 0]).unwrap();
+            respond(&mut stream);
+        });
+
+        let proxy = Proxy::parse(&format!("socks4a://aktis@{}", addr)).unwrap();
+        let mut client = Client::new();
+        client.set_proxy(ProxyConfig::all(proxy));
+        let res = client.send(Request::get("http://origin.test/").unwrap()).unwrap();
+        assert_eq!(res.text(), "GET / HTTP/1.0");
+    }
+
+    #[test]
+    fn test_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_n(&mut stream, 3);
+            stream.write_all(&[0x05, 0x00]).unwrap();
+            read_n(&mut stream, 10);
+            stream.write_all(&[0x05, 0x05, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).unwrap();
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let err = socks5_connect(&mut stream, "127.0.0.1", 80, None, true).unwrap_err();
+        assert_eq!(err.to_string(), "SOCKS error: request rejected: connection refused");
+    }
+}
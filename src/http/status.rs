@@ -0,0 +1,168 @@
+use std::fmt;
+
+// HTTP status code (RFC 9110 section 15)
+//
+// Any three digit code is representable; `canonical_reason` knows the registered ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode(u16);
+
+impl StatusCode {
+    pub const CONTINUE: Self = Self(100);
+    pub const SWITCHING_PROTOCOLS: Self = Self(101);
+    pub const EARLY_HINTS: Self = Self(103);
+    pub const OK: Self = Self(200);
+    pub const NO_CONTENT: Self = Self(204);
+    pub const PARTIAL_CONTENT: Self = Self(206);
+    pub const MOVED_PERMANENTLY: Self = Self(301);
+    pub const FOUND: Self = Self(302);
+    pub const SEE_OTHER: Self = Self(303);
+    pub const NOT_MODIFIED: Self = Self(304);
+    pub const TEMPORARY_REDIRECT: Self = Self(307);
+    pub const PERMANENT_REDIRECT: Self = Self(308);
+    pub const BAD_REQUEST: Self = Self(400);
+    pub const UNAUTHORIZED: Self = Self(401);
+    pub const FORBIDDEN: Self = Self(403);
+    pub const NOT_FOUND: Self = Self(404);
+    pub const PROXY_AUTHENTICATION_REQUIRED: Self = Self(407);
+    pub const EXPECTATION_FAILED: Self = Self(417);
+    pub const RANGE_NOT_SATISFIABLE: Self = Self(416);
+    pub const INTERNAL_SERVER_ERROR: Self = Self(500);
+
+    // return `None` unless `code` has three digits
+    pub fn from_u16(code: u16) -> Option<Self> {
+        if (100..=999).contains(&code) {
+            Some(Self(code))
+        } else {
+            None
+        }
+    }
+
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    // 1xx
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    // 2xx
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    // 3xx
+    pub fn is_redirection(&self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    // 4xx
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    // 5xx
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
+
+    // reason phrase registered in the IANA HTTP Status Code Registry
+    pub fn canonical_reason(&self) -> Option<&'static str> {
+        let reason = match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            102 => "Processing",
+            103 => "Early Hints",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            203 => "Non-Authoritative Information",
+            204 => "No Content",
+            205 => "Reset Content",
+            206 => "Partial Content",
+            207 => "Multi-Status",
+            208 => "Already Reported",
+            226 => "IM Used",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            305 => "Use Proxy",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Content Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            421 => "Misdirected Request",
+            422 => "Unprocessable Content",
+            423 => "Locked",
+            424 => "Failed Dependency",
+            425 => "Too Early",
+            426 => "Upgrade Required",
+            428 => "Precondition Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            451 => "Unavailable For Legal Reasons",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            505 => "HTTP Version Not Supported",
+            506 => "Variant Also Negotiates",
+            507 => "Insufficient Storage",
+            508 => "Loop Detected",
+            511 => "Network Authentication Required",
+            _ => return None,
+        };
+        Some(reason)
+    }
+}
+
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.canonical_reason() {
+            Some(reason) => write!(f, "{} {}", self.0, reason),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_class() {
+        assert!(StatusCode::CONTINUE.is_informational());
+        assert!(StatusCode::OK.is_success());
+        assert!(StatusCode::NOT_MODIFIED.is_redirection());
+        assert!(StatusCode::NOT_FOUND.is_client_error());
+        assert!(StatusCode::INTERNAL_SERVER_ERROR.is_server_error());
+        assert!(!StatusCode::from_u16(600).unwrap().is_server_error());
+        assert!(StatusCode::from_u16(99).is_none());
+    }
+
+    #[test]
+    fn test_reason() {
+        assert_eq!(StatusCode::NOT_FOUND.canonical_reason(), Some("Not Found"));
+        assert_eq!(StatusCode::NOT_FOUND.to_string(), "404 Not Found");
+        assert_eq!(StatusCode::from_u16(299).unwrap().to_string(), "299");
+    }
+}
@@ -0,0 +1,365 @@
+// Transports open the byte stream a request is sent over.
+//
+// `Client` asks its `Transport` for a stream to the origin (or proxy) URL;
+// everything above the stream (HTTP, proxies, SOCKS) does not care what it is.
+//
+// - `TcpTransport`: the host OS's TCP socket
+// - `UnixTransport`: a Unix domain socket for `http+unix://` URLs
+//   (`http+unix://%2Fvar%2Frun%2Fdocker.sock/v1.41/info`)
+// - `DefaultTransport`: one of the above by the scheme (used by `Client::new`)
+// - `StackTransport`: our own TCP (`crate::tcp`) over a packet device
+// - `MockTransport`: replays canned responses from memory for tests
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Cursor, ErrorKind, Read, Write};
+use std::net::{self, Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::dns::resolver::Resolver;
+use crate::tcp::stack::{Connection, Stack};
+use crate::http::url::{percent_decode, URL};
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum TransportError {
+    UnsupportedScheme(String),
+    NoMockResponse(String),
+    NotIpv4(String),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedScheme(s) => write!(f, "Transport error: unsupported scheme `{}`", s),
+            Self::NoMockResponse(s) => write!(f, "Transport error: no mock response left for `{}`", s),
+            Self::NotIpv4(s) => write!(f, "Transport error: `{}` is not an IPv4 address", s),
+        }
+    }
+}
+
+impl Error for TransportError {}
+// ------------------------------------
+
+// a connected, bidirectional byte stream
+pub trait Stream: Read + Write + Send {
+    // used to wait for `100 Continue`; streams without timeouts never time out
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub trait Transport: Send + Sync {
+    fn connect(&self, url: &URL) -> Result<Box<dyn Stream>, Box<dyn Error>>;
+}
+
+// the peer may have closed the connection first
+fn ignore_not_connected(res: io::Result<()>) -> io::Result<()> {
+    match res {
+        Err(e) if e.kind() == ErrorKind::NotConnected => Ok(()),
+        res => res,
+    }
+}
+
+impl Stream for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        ignore_not_connected(TcpStream::shutdown(self, net::Shutdown::Both))
+    }
+}
+
+// Host names are resolved by the OS's resolver unless a `Resolver` is given.
+#[derive(Debug, Clone, Default)]
+pub struct TcpTransport {
+    resolver: Option<Arc<Resolver>>,
+}
+
+impl TcpTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_resolver(mut self, resolver: Arc<Resolver>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+}
+
+impl Transport for TcpTransport {
+    fn connect(&self, url: &URL) -> Result<Box<dyn Stream>, Box<dyn Error>> {
+        let stream = match &self.resolver {
+            Some(resolver) => {
+                let port = url.port() as u16;
+                let addrs: Vec<SocketAddr> = resolver
+                    .lookup_ip(&url.host())?
+                    .into_iter()
+                    .map(|ip| SocketAddr::new(ip, port))
+                    .collect();
+                // tries each address in order
+                TcpStream::connect(&addrs[..])?
+            }
+            // get IP addresses using OS's DNS resolver and connect using OS's TCP socket
+            None => TcpStream::connect(format!("{}:{}", url.host(), url.port()))?,
+        };
+        Ok(Box::new(stream))
+    }
+}
+
+#[cfg(unix)]
+impl Stream for std::os::unix::net::UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        ignore_not_connected(std::os::unix::net::UnixStream::shutdown(self, net::Shutdown::Both))
+    }
+}
+
+// The host of the URL is the percent-encoded path of the socket.
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnixTransport;
+
+#[cfg(unix)]
+impl Transport for UnixTransport {
+    fn connect(&self, url: &URL) -> Result<Box<dyn Stream>, Box<dyn Error>> {
+        let path = percent_decode(&url.host());
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        Ok(Box::new(stream))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DefaultTransport {
+    tcp: TcpTransport,
+}
+
+impl DefaultTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // resolve host names for TCP connections with `resolver`
+    pub fn with_resolver(mut self, resolver: Arc<Resolver>) -> Self {
+        self.tcp = self.tcp.with_resolver(resolver);
+        self
+    }
+}
+
+impl Transport for DefaultTransport {
+    fn connect(&self, url: &URL) -> Result<Box<dyn Stream>, Box<dyn Error>> {
+        match url.scheme().as_str() {
+            #[cfg(unix)]
+            "http+unix" => UnixTransport.connect(url),
+            // `https` needs TLS, which we do not have yet: never send it in plaintext
+            "http" | "socks4" | "socks4a" | "socks5" | "socks5h" => self.tcp.connect(url),
+            scheme => Err(Box::new(TransportError::UnsupportedScheme(scheme.to_string()))),
+        }
+    }
+}
+
+impl Stream for Connection {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        Connection::set_read_timeout(self, timeout)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        self.close()
+    }
+}
+
+// The stack has no resolver: hosts must be IPv4 addresses.
+#[derive(Clone)]
+pub struct StackTransport {
+    stack: Stack,
+}
+
+impl StackTransport {
+    pub fn new(stack: Stack) -> Self {
+        Self { stack }
+    }
+}
+
+impl Transport for StackTransport {
+    fn connect(&self, url: &URL) -> Result<Box<dyn Stream>, Box<dyn Error>> {
+        let ip = match url.host().parse::<Ipv4Addr>() {
+            Ok(ip) => ip,
+            Err(_) => return Err(Box::new(TransportError::NotIpv4(url.host()))),
+        };
+        let conn = self.stack.connect(SocketAddrV4::new(ip, url.port() as u16))?;
+        Ok(Box::new(conn))
+    }
+}
+
+// Responses are replayed in the order they were pushed, one per connection.
+// Cloned handles share the state, so a test can keep one and give another to `Client`.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: Vec<Vec<u8>>,
+    // (connected URL, bytes written by the client) per connection
+    requests: Vec<(String, Arc<Mutex<Vec<u8>>>)>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // queue the raw bytes the server sends on the next connection
+    pub fn push_response(&self, raw: &[u8]) {
+        self.state.lock().unwrap().responses.push(raw.to_vec());
+    }
+
+    // URLs connected to, in order
+    pub fn connections(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.requests.iter().map(|(url, _)| url.clone()).collect()
+    }
+
+    // bytes written on each connection, in order
+    pub fn requests(&self) -> Vec<Vec<u8>> {
+        let state = self.state.lock().unwrap();
+        state.requests.iter().map(|(_, buf)| buf.lock().unwrap().clone()).collect()
+    }
+}
+
+impl Transport for MockTransport {
+    fn connect(&self, url: &URL) -> Result<Box<dyn Stream>, Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        if state.responses.is_empty() {
+            return Err(Box::new(TransportError::NoMockResponse(url.to_string())));
+        }
+        let response = state.responses.remove(0);
+        let written = Arc::new(Mutex::new(vec![]));
+        state.requests.push((url.to_string(), written.clone()));
+        Ok(Box::new(MockStream {
+            response: Cursor::new(response),
+            written,
+        }))
+    }
+}
+
+struct MockStream {
+    response: Cursor<Vec<u8>>,
+    written: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.response.read(buf)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Stream for MockStream {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::client::Client;
+    use crate::http::request::Request;
+    use crate::http::status::StatusCode;
+
+    #[test]
+    fn test_mock() {
+        let mock = MockTransport::new();
+        mock.push_response(b"HTTP/1.1 302 Found\r\nSet-Cookie: id=1\r\nContent-Length: 0\r\n\r\n");
+        mock.push_response(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+
+        let mut client = Client::new();
+        client.set_transport(Box::new(mock.clone()));
+        let res = client.send(Request::get("http://example.com/login").unwrap()).unwrap();
+        assert_eq!(res.status(), StatusCode::FOUND);
+        let res = client.send(Request::get("http://example.com/").unwrap()).unwrap();
+        assert_eq!(res.text(), "ok");
+        assert!(client.send(Request::get("http://example.com/").unwrap()).is_err());
+
+        assert_eq!(mock.connections(), vec!["http://example.com/login", "http://example.com/"]);
+        let requests = mock.requests();
+        assert!(requests[0].starts_with(b"GET /login HTTP/1.0\r\nHost: example.com\r\n"));
+        let second = String::from_utf8(requests[1].clone()).unwrap();
+        assert!(second.contains("Cookie:id=1\r\n"));
+    }
+
+    #[test]
+    fn test_stack() {
+        use crate::tcp::device::{Impairment, LoopbackDevice};
+
+        let (a, b) = LoopbackDevice::pair(Impairment::new().with_loss(0.02).with_seed(3));
+        let server = Stack::new(Arc::new(b), Ipv4Addr::new(192, 168, 0, 2));
+        let listener = server.listen(80).unwrap();
+        std::thread::spawn(move || {
+            let mut conn = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let n = conn.read(&mut buf).unwrap();
+            assert!(buf[..n].starts_with(b"GET /stack HTTP/1.0\r\n"));
+            conn.write_all(b"HTTP/1.0 200 OK\r\n\r\nover our own TCP").unwrap();
+        });
+
+        let mut client = Client::new();
+        client.set_transport(Box::new(StackTransport::new(Stack::new(Arc::new(a), Ipv4Addr::new(192, 168, 0, 1)))));
+        let res = client.send(Request::get("http://192.168.0.2/stack").unwrap()).unwrap();
+        assert_eq!(res.text(), "over our own TCP");
+        assert!(client.send(Request::get("http://example.com/").unwrap()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix() {
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("aktis-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).unwrap();
+            assert!(buf[..n].starts_with(b"GET /v1.41/info HTTP/1.0\r\n"));
+            stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n{}").unwrap();
+        });
+
+        let encoded = path.to_str().unwrap().replace('/', "%2F");
+        let res = Client::get(&format!("http+unix://{}/v1.41/info", encoded)).unwrap();
+        assert_eq!(res.text(), "{}");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_https() {
+        let url = URL::parse("https://example.com/").unwrap();
+        assert!(DefaultTransport::new().connect(&url).is_err());
+
+        let mock = MockTransport::new();
+        mock.push_response(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+        let mut client = Client::new();
+        client.set_transport(Box::new(mock.clone()));
+        let err = client.send(Request::get("https://example.com/").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "Transport error: unsupported scheme `https`");
+        assert!(mock.connections().is_empty());
+        assert!(mock.requests().is_empty());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod dns;
+pub mod http;
+pub mod utils;
+pub mod renderer;
+pub mod tcp;
@@ -1,9 +1,14 @@
+mod cli;
+mod profile;
+mod ui;
 
-pub mod http;
-pub mod utils;
-pub mod renderer;
-
+use std::env;
+use std::process;
 
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = cli::run(&args) {
+        eprintln!("aktis: {}", e);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,37 @@
+// Files kept across sessions
+//
+// ```
+// $AKTIS_HOME (or $XDG_DATA_HOME/aktis, ~/.local/share/aktis)
+// ├─ bookmarks    url, title, folder, tags and date of each bookmark
+// └─ history      every page visited, with the time and the title
+// ```
+
+pub mod bookmarks;
+pub mod history;
+
+use std::env;
+use std::path::PathBuf;
+
+// the directory of the files (`None` without a home directory)
+pub fn dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("AKTIS_HOME") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return Some(PathBuf::from(dir).join("aktis"));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share/aktis"))
+}
+
+pub fn bookmarks_path() -> Option<PathBuf> {
+    dir().map(|dir| dir.join("bookmarks"))
+}
+
+pub fn history_path() -> Option<PathBuf> {
+    dir().map(|dir| dir.join("history"))
+}
+
+// a field of the tab-separated files (tabs and line breaks would break the line)
+fn field(s: &str) -> String {
+    s.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
@@ -0,0 +1,382 @@
+// Bookmarks: one line per bookmark
+//
+// ```
+// # aktis bookmarks
+// https://doc.rust-lang.org/std/	std - Rust	Dev/Rust	rust,docs	1760842200
+// ```
+// (URL, title, folder, tags and the date added, separated by tabs)
+//
+// Folders are paths like `Dev/Rust`; the empty folder is the top. The Netscape bookmark file format
+// exported by browsers can be imported and exported:
+// ```
+// <!DOCTYPE NETSCAPE-Bookmark-file-1>
+// <DL><p>
+//     <DT><H3>Dev</H3>
+//     <DL><p>
+//         <DT><A HREF="https://doc.rust-lang.org/std/" ADD_DATE="1760842200" TAGS="rust,docs">std - Rust</A>
+//     </DL><p>
+// </DL><p>
+// ```
+// ref: https://learn.microsoft.com/en-us/previous-versions/windows/internet-explorer/ie-developer/platform-apis/aa753582(v=vs.85)
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use aktis::http::date::HttpDate;
+use aktis::renderer::html_parser::{self, TokenType};
+use super::field;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    url: String,
+    title: String,
+    folder: String,
+    tags: Vec<String>,
+    added: u64,
+}
+
+impl Bookmark {
+    // a bookmark at the top, added now
+    pub fn new(url: &str, title: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            title: title.to_string(),
+            folder: String::new(),
+            tags: vec![],
+            added: HttpDate::now().secs(),
+        }
+    }
+
+    // `Dev/Rust` (slashes at the ends and empty names are dropped)
+    pub fn with_folder(mut self, folder: &str) -> Self {
+        self.folder = folder.split('/').map(|s| s.trim()).filter(|s| !s.is_empty()).collect::<Vec<_>>().join("/");
+        self
+    }
+
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        let tag = tag.trim().trim_start_matches('#').replace(',', " ");
+        if !tag.is_empty() && !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+        self
+    }
+
+    pub fn with_added(mut self, added: u64) -> Self {
+        self.added = added;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    // whether all the words of `query` are in the URL, title, folder or tags (ignoring case);
+    // `#word` only matches a tag
+    pub fn matches(&self, query: &str) -> bool {
+        let text = format!("{} {} {} {}", self.url, self.title, self.folder, self.tags.join(" ")).to_lowercase();
+        query.split_whitespace().all(|word| match word.strip_prefix('#') {
+            Some(tag) => self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            None => text.contains(&word.to_lowercase()),
+        })
+    }
+
+    // whether the bookmark is in `folder` or one of its subfolders
+    pub fn is_in(&self, folder: &str) -> bool {
+        let folder = folder.trim_matches('/');
+        folder.is_empty() || self.folder == folder || self.folder.starts_with(&format!("{}/", folder))
+    }
+
+    // `std - Rust  https://doc.rust-lang.org/std/  (Dev/Rust #rust #docs)`
+    pub fn summary(&self) -> String {
+        let mut notes = vec![];
+        if !self.folder.is_empty() {
+            notes.push(self.folder.clone());
+        }
+        notes.extend(self.tags.iter().map(|t| format!("#{}", t)));
+        let notes = if notes.is_empty() { String::new() } else { format!("  ({})", notes.join(" ")) };
+        format!("{}  {}{}", self.title, self.url, notes)
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return None;
+        }
+        let bookmark = Self::new(fields[0], fields[1]).with_folder(fields[2]).with_added(fields[4].parse().ok()?);
+        Some(fields[3].split(',').fold(bookmark, |b, tag| b.with_tag(tag)))
+    }
+}
+
+impl fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}",
+            field(&self.url),
+            field(&self.title),
+            field(&self.folder),
+            field(&self.tags.join(",")),
+            self.added
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bookmarks(Vec<Bookmark>);
+
+impl Bookmarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // lines that cannot be read are skipped
+    pub fn parse(src: &str) -> Self {
+        Self(
+            src.lines()
+                .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+                .filter_map(Bookmark::parse)
+                .collect(),
+        )
+    }
+
+    // no file yet is an empty list; any other error is returned, so the file is not saved over
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(src) => Ok(Self::parse(&src)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    // the directory is created if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[Bookmark] {
+        &self.0
+    }
+
+    pub fn get(&self, url: &str) -> Option<&Bookmark> {
+        self.0.iter().find(|b| b.url == url)
+    }
+
+    // add `bookmark`, replacing the one with the same URL
+    pub fn add(&mut self, bookmark: Bookmark) {
+        match self.0.iter_mut().find(|b| b.url == bookmark.url) {
+            Some(b) => *b = bookmark,
+            None => self.0.push(bookmark),
+        }
+    }
+
+    pub fn remove(&mut self, url: &str) -> Option<Bookmark> {
+        let i = self.0.iter().position(|b| b.url == url)?;
+        Some(self.0.remove(i))
+    }
+
+    // add all of `other` (replacing bookmarks with the same URLs)
+    pub fn extend(&mut self, other: Self) {
+        for bookmark in other.0 {
+            self.add(bookmark);
+        }
+    }
+
+    pub fn search(&self, query: &str) -> Vec<&Bookmark> {
+        self.0.iter().filter(|b| b.matches(query)).collect()
+    }
+
+    // Netscape bookmark file, read with our own tokenizer
+    // (folders are `<H3>` followed by a `<DL>`, bookmarks are `<A>`)
+    pub fn from_netscape(html: &str) -> Self {
+        let mut bookmarks = Self::new();
+        let mut folders: Vec<String> = vec![];
+        // for each open <DL>, the depth of the folder it is in
+        let mut lists: Vec<usize> = vec![];
+        let mut folder: Option<String> = None;
+        let mut text: Option<String> = None;
+        let mut link: Option<Bookmark> = None;
+
+        for token in html_parser::tokenize(html) {
+            match (token.token_type(), token.name().unwrap_or_default()) {
+                (TokenType::StartTag, "h3") => text = Some(String::new()),
+                (TokenType::EndTag, "h3") => {
+                    // a slash would read as a subfolder
+                    folder = text.take().map(|name| collapse(&name).replace('/', "-"));
+                }
+                (TokenType::StartTag, "dl") => {
+                    lists.push(folders.len());
+                    folders.extend(folder.take());
+                }
+                (TokenType::EndTag, "dl") => folders.truncate(lists.pop().unwrap_or(0)),
+                (TokenType::StartTag, "a") => {
+                    text = Some(String::new());
+                    link = token.attr("href").map(|href| {
+                        let added = token.attr("add_date").and_then(|d| d.trim().parse().ok()).unwrap_or(0);
+                        let tags = token.attr("tags").unwrap_or_default();
+                        let bookmark = Bookmark::new(href.trim(), "").with_folder(&folders.join("/")).with_added(added);
+                        tags.split(',').fold(bookmark, |b, tag| b.with_tag(tag))
+                    });
+                }
+                (TokenType::EndTag, "a") => {
+                    if let Some(mut bookmark) = link.take() {
+                        bookmark.title = collapse(&text.take().unwrap_or_default());
+                        bookmarks.add(bookmark);
+                    }
+                }
+                (TokenType::Content, _) => {
+                    if let Some(text) = &mut text {
+                        text.push_str(token.data().unwrap_or_default());
+                    }
+                }
+                _ => {}
+            }
+        }
+        bookmarks
+    }
+
+    pub fn to_netscape(&self) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+             <!-- This is an automatically generated file.\n     It will be read and overwritten.\n     DO NOT EDIT! -->\n\
+             <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+             <TITLE>Bookmarks</TITLE>\n\
+             <H1>Bookmarks</H1>\n",
+        );
+        self.write_folder(&mut out, "", 1);
+        out
+    }
+
+    // the bookmarks of `folder`, then its subfolders in the order they first appear
+    fn write_folder(&self, out: &mut String, folder: &str, depth: usize) {
+        let indent = "    ".repeat(depth);
+        out.push_str(&format!("{}<DL><p>\n", &indent[4..]));
+        for b in self.0.iter().filter(|b| b.folder == folder) {
+            out.push_str(&format!("{}<DT><A HREF=\"{}\" ADD_DATE=\"{}\"", indent, escape(&b.url), b.added));
+            if !b.tags.is_empty() {
+                out.push_str(&format!(" TAGS=\"{}\"", escape(&b.tags.join(","))));
+            }
+            out.push_str(&format!(">{}</A>\n", escape(&b.title)));
+        }
+
+        let mut children: Vec<&str> = vec![];
+        for b in self.0.iter().filter(|b| b.is_in(folder) && b.folder != folder) {
+            let rest = if folder.is_empty() { &b.folder[..] } else { &b.folder[folder.len() + 1..] };
+            let child = rest.split('/').next().unwrap_or_default();
+            if !children.contains(&child) {
+                children.push(child);
+            }
+        }
+        for child in children {
+            out.push_str(&format!("{}<DT><H3>{}</H3>\n", indent, escape(child)));
+            let path = if folder.is_empty() { child.to_string() } else { format!("{}/{}", folder, child) };
+            self.write_folder(out, &path, depth + 1);
+        }
+        out.push_str(&format!("{}</DL><p>\n", &indent[4..]));
+    }
+}
+
+impl fmt::Display for Bookmarks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# aktis bookmarks")?;
+        for bookmark in &self.0 {
+            writeln!(f, "{}", bookmark)?;
+        }
+        Ok(())
+    }
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Bookmarks {
+        let mut bookmarks = Bookmarks::new();
+        bookmarks.add(Bookmark::new("http://example.com/", "Example").with_added(1));
+        bookmarks.add(Bookmark::new("https://go.dev/", "Go & \"more\"").with_folder("Dev").with_added(2));
+        bookmarks.add(
+            Bookmark::new("https://doc.rust-lang.org/std/", "std - Rust")
+                .with_folder("/Dev/Rust/")
+                .with_tag("rust")
+                .with_tag("#docs")
+                .with_added(3),
+        );
+        bookmarks
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let mut bookmarks = sample();
+        assert_eq!(Bookmarks::parse(&bookmarks.to_string()), bookmarks);
+        assert_eq!(
+            bookmarks.to_string().lines().nth(3),
+            Some("https://doc.rust-lang.org/std/\tstd - Rust\tDev/Rust\trust,docs\t3")
+        );
+        let std = bookmarks.get("https://doc.rust-lang.org/std/").unwrap();
+        assert_eq!(std.summary(), "std - Rust  https://doc.rust-lang.org/std/  (Dev/Rust #rust #docs)");
+
+        let urls = |found: Vec<&Bookmark>| found.iter().map(|b| b.url().to_string()).collect::<Vec<_>>();
+        assert_eq!(urls(bookmarks.search("#RUST")), vec!["https://doc.rust-lang.org/std/"]);
+        assert_eq!(urls(bookmarks.search("dev")).len(), 2);
+        assert!(bookmarks.search("#dev").is_empty());
+        assert_eq!(bookmarks.entries().iter().filter(|b| b.is_in("Dev")).count(), 2);
+        assert!(!std.is_in("De"));
+
+        // adding the same URL replaces the bookmark
+        bookmarks.add(Bookmark::new("http://example.com/", "Example 2"));
+        assert_eq!(bookmarks.entries().len(), 3);
+        assert_eq!(bookmarks.get("http://example.com/").unwrap().title, "Example 2");
+        assert!(bookmarks.remove("http://example.com/").is_some());
+        assert!(bookmarks.remove("http://example.com/").is_none());
+
+        let dir = std::env::temp_dir();
+        assert_eq!(Bookmarks::load(dir.join("aktis-no-bookmarks")).unwrap(), Bookmarks::new());
+        // not a file
+        assert!(Bookmarks::load(dir).is_err());
+    }
+
+    #[test]
+    fn test_netscape() {
+        let bookmarks = sample();
+        let html = bookmarks.to_netscape();
+        assert!(html.contains(
+            "    <DT><H3>Dev</H3>\n    <DL><p>\n        <DT><A HREF=\"https://go.dev/\" ADD_DATE=\"2\">Go &amp; &quot;more&quot;</A>\n"
+        ));
+        // (the sample is already grouped by folder as the export is)
+        assert_eq!(Bookmarks::from_netscape(&html), bookmarks);
+
+        // as exported by Firefox (unclosed <DT> and <p>, extra attributes)
+        let firefox = "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+<DL><p>
+    <DT><H3 ADD_DATE=\"1\" PERSONAL_TOOLBAR_FOLDER=\"true\">Bookmarks Toolbar</H3>
+    <DL><p>
+        <DT><A HREF=\"https://www.mozilla.org/\" ADD_DATE=\"5\" ICON=\"data:image/png;base64,AAA\">Mozilla</A>
+    </DL><p>
+    <DT><A HREF=\"http://example.com/?a=1&amp;b=2\" TAGS=\"x,y\">Example
+        page</A>
+    <DD>a description
+</DL>";
+        let imported = Bookmarks::from_netscape(firefox);
+        let entries = imported.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].summary(), "Mozilla  https://www.mozilla.org/  (Bookmarks Toolbar)");
+        assert_eq!(entries[0].added, 5);
+        assert_eq!(entries[1].summary(), "Example page  http://example.com/?a=1&b=2  (#x #y)");
+    }
+}
@@ -0,0 +1,90 @@
+// Global history: one line per visit, appended to the file
+//
+// ```
+// 1760842200	https://www.rust-lang.org/	Rust Programming Language
+// ```
+// (seconds since the UNIX epoch, URL and title, separated by tabs)
+
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use aktis::http::date::HttpDate;
+use super::field;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Visit {
+    time: u64,
+    url: String,
+    title: String,
+}
+
+impl Visit {
+    // a visit now
+    pub fn new(url: &str, title: &str) -> Self {
+        Self {
+            time: HttpDate::now().secs(),
+            url: url.to_string(),
+            title: title.to_string(),
+        }
+    }
+
+    // `None` for a broken line
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let time = fields.next()?.parse().ok()?;
+        let url = fields.next()?.to_string();
+        let title = fields.next().unwrap_or_default().to_string();
+        Some(Self { time, url, title })
+    }
+
+    // whether all the words of `query` are in the URL or the title (ignoring case)
+    pub fn matches(&self, query: &str) -> bool {
+        let text = format!("{} {}", self.url, self.title).to_lowercase();
+        query.split_whitespace().all(|word| text.contains(&word.to_lowercase()))
+    }
+
+    // `Sun, 19 Oct 2026 02:10:00 GMT  Title  https://...`
+    pub fn summary(&self) -> String {
+        format!("{}  {}  {}", HttpDate::from_secs(self.time), self.title, self.url)
+    }
+}
+
+// append `visit` to the file at `path` (created with its directory if needed)
+pub fn record<P: AsRef<Path>>(path: P, visit: &Visit) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}\t{}\t{}", visit.time, field(&visit.url), field(&visit.title))?;
+    Ok(())
+}
+
+// all the visits from the oldest (broken lines are skipped)
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Visit>, Box<dyn Error>> {
+    Ok(fs::read_to_string(path)?.lines().filter_map(Visit::parse).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let dir = std::env::temp_dir().join(format!("aktis-history-{}", std::process::id()));
+        let path = dir.join("history");
+        record(&path, &Visit::new("http://example.com/", "Example\tDomain")).unwrap();
+        record(&path, &Visit::new("https://www.rust-lang.org/", "Rust")).unwrap();
+
+        let visits = load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(visits.len(), 2);
+        assert_eq!(visits[0].title, "Example Domain");
+        assert!(visits[1].time > 0);
+        assert!(visits[0].matches("EXAMPLE domain"));
+        assert!(!visits[1].matches("rust python"));
+
+        assert_eq!(Visit::parse("x\ty"), None);
+        assert_eq!(Visit::parse("0\thttp://a/").unwrap().summary(), "Thu, 01 Jan 1970 00:00:00 GMT    http://a/");
+    }
+}
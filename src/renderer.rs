@@ -1,2 +1,9 @@
+pub mod ansi_generator;
+pub mod backend;
+pub mod dom;
 pub mod html_parser;
+pub mod json_generator;
+pub mod layout;
 pub mod markdown_generator;
+pub mod plain_generator;
+pub mod table;
@@ -0,0 +1,393 @@
+// ANSI generator
+//
+// Renders the document tree for a terminal, with escape sequences (SGR) instead of Markdown marks:
+//
+// ```
+// Manual                              headings in bold (<h1> also underlined)
+//
+// Read the install guide[1] first.    links underlined in blue, their numbers dimmed
+//
+// • Download[2]                       <em> in italic, <strong> in bold
+//   1. More                           code blocks and inline code dimmed
+// │ quoted
+// ──────────────────────────────
+//
+// [1]: /install.html
+// ```
+//
+// Lines are wrapped to the width given, as `layout.rs` does for Markdown; code blocks are not.
+// Whether the output should be styled at all (a terminal, no `NO_COLOR`) is up to the caller.
+
+use crate::renderer::backend::{self, Block, Flow, Inline, Layout, Renderer};
+use crate::renderer::dom::Element;
+use crate::renderer::layout;
+use crate::renderer::table;
+use crate::utils::width;
+
+// SGR parameters
+const BOLD: &str = "1";
+const DIM: &str = "2";
+const TITLE: &str = "1;4";
+const LINK: &str = "4;34";
+
+// characters `start..end` of a line in a style
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Span {
+    start: usize,
+    end: usize,
+    sgr: &'static str,
+}
+
+// a line of text before it is wrapped
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Line {
+    // quote bars and list markers before the first row, and before the others
+    lead: String,
+    indent: String,
+    text: String,
+    spans: Vec<Span>,
+    // shown as it is (code blocks, rules, link references)
+    pre: bool,
+}
+
+impl Line {
+    fn pre(lead: String, text: String, sgr: &'static str) -> Self {
+        let end = text.chars().count();
+        Self {
+            indent: lead.clone(),
+            lead,
+            text,
+            spans: vec![Span { start: 0, end, sgr }],
+            pre: true,
+        }
+    }
+}
+
+// the document as lines of at most `width` cells (longer code lines aside)
+pub fn generate(document: &Element, width: usize) -> Vec<String> {
+    let gen = Generator {
+        width: width.max(1),
+        ..Generator::default()
+    };
+    backend::render(document, gen)
+}
+
+#[derive(Default)]
+struct Generator {
+    width: usize,
+    lines: Vec<Line>,
+    // the current block, and the styles in its text (byte ranges)
+    flow: Flow,
+    spans: Vec<Span>,
+    // where the open inline elements start in the text
+    open: Vec<usize>,
+}
+
+impl Renderer for Generator {
+    type Output = Vec<String>;
+
+    fn start(&mut self, block: &Block) {
+        self.enter(block);
+        match block {
+            Block::Heading(_) => self.open.push(self.flow.inline.len()),
+            Block::Rule => {
+                let lead = self.flow.indent();
+                let rule = "─".repeat(self.width.saturating_sub(width::width(&lead)).max(1));
+                self.push_line(Line::pre(lead, rule, DIM));
+                self.flow.gap = true;
+            }
+            Block::Code(text, _) => {
+                for (i, line) in text.lines().enumerate() {
+                    let lead = if i == 0 { self.flow.lead() } else { self.flow.indent() };
+                    self.push_line(Line::pre(lead, line.replace('\t', "    "), DIM));
+                }
+                self.flow.gap = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, block: &Block) {
+        match block {
+            Block::Heading(level) => {
+                let start = self.open.pop().unwrap_or_default();
+                let end = self.flow.inline.len();
+                let sgr = if *level == 1 { TITLE } else { BOLD };
+                self.spans.push(Span { start, end, sgr });
+                self.leave(block);
+            }
+            // without styles in the cells
+            Block::Cell { .. } => {
+                self.leave(block);
+                self.spans.clear();
+            }
+            // a grid, its lines dimmed
+            Block::Table => {
+                let rows = self.flow.rows();
+                let room = self.width.saturating_sub(width::width(&self.flow.indent()));
+                // the marker of a list item on the first line
+                for (i, text) in table::grid(&rows, room).into_iter().enumerate() {
+                    let lead = if i == 0 { self.flow.lead() } else { self.flow.indent() };
+                    let mut line = Line::pre(lead, text, DIM);
+                    line.spans = borders(&line.text);
+                    self.push_line(line);
+                }
+                self.flow.gap = true;
+            }
+            _ => self.leave(block),
+        }
+    }
+
+    fn text(&mut self, s: &str) {
+        self.flow.text(s);
+    }
+
+    fn start_inline(&mut self, _inline: &Inline) {
+        self.open.push(self.flow.inline.len());
+    }
+
+    // links as `text[n]`, the text underlined
+    fn end_inline(&mut self, inline: &Inline) {
+        let start = self.open.pop().unwrap_or_default();
+        let sgr = match inline {
+            Inline::Strong => BOLD,
+            Inline::Emphasis => "3",
+            Inline::Code => DIM,
+            Inline::Underline => "4",
+            Inline::Strike => "9",
+            // no style that terminals agree on
+            Inline::Subscript | Inline::Superscript => return,
+            Inline::Link(n, _) => return self.link(start, *n),
+        };
+        let end = self.flow.inline.len();
+        self.spans.push(Span { start, end, sgr });
+    }
+
+    fn image(&mut self, alt: &str, _src: &str) {
+        self.word(&format!("[{}]", alt), DIM);
+    }
+
+    fn line_break(&mut self) {
+        self.flush();
+    }
+
+    fn finish(mut self, _title: Option<String>, links: Vec<String>) -> Vec<String> {
+        self.flush();
+        if !links.is_empty() {
+            self.lines.push(Line::default());
+            for (i, link) in links.iter().enumerate() {
+                let number = format!("[{}]:", i + 1);
+                let end = number.chars().count();
+                self.lines.push(Line {
+                    text: format!("{} {}", number, link),
+                    spans: vec![Span { start: 0, end, sgr: DIM }],
+                    pre: true,
+                    ..Line::default()
+                });
+            }
+        }
+        self.lines.iter().flat_map(|line| rows(line, self.width)).collect()
+    }
+}
+
+impl Layout for Generator {
+    fn flow(&mut self) -> &mut Flow {
+        &mut self.flow
+    }
+
+    // the styles go with the text
+    fn flush(&mut self) {
+        let spans = std::mem::take(&mut self.spans);
+        let text = match self.flow.take() {
+            Some(text) => text,
+            None => return,
+        };
+        // byte ranges to character ranges
+        let chars = |i: usize| text[..i.min(text.len())].chars().count();
+        let spans = spans
+            .into_iter()
+            .map(|s| Span { start: chars(s.start), end: chars(s.end), sgr: s.sgr })
+            .filter(|s| s.start < s.end)
+            .collect();
+        let line = Line {
+            lead: self.flow.lead(),
+            indent: self.flow.indent(),
+            text,
+            spans,
+            pre: false,
+        };
+        self.push_line(line);
+    }
+
+    fn quote(&self) -> &'static str {
+        "│ "
+    }
+
+    fn marker(&self, number: Option<usize>) -> String {
+        match number {
+            Some(n) => format!("{}. ", n),
+            None => "• ".to_string(),
+        }
+    }
+}
+
+impl Generator {
+    // the link `n` from `start` in the text
+    fn link(&mut self, start: usize, n: usize) {
+        let inline = &mut self.flow.inline;
+        // the spaces around the text are not underlined
+        let space = inline.len() > start && inline.ends_with(' ');
+        if space {
+            inline.pop();
+        }
+        let start = if inline[start..].starts_with(' ') { start + 1 } else { start };
+        if inline.len() <= start {
+            inline.push_str("link");
+        }
+        let end = inline.len();
+        self.spans.push(Span { start, end, sgr: LINK });
+        self.word(&format!("[{}]", n), DIM);
+        if space {
+            self.flow.inline.push(' ');
+        }
+    }
+
+    // add text that is not split, in a style
+    fn word(&mut self, s: &str, sgr: &'static str) {
+        let start = self.flow.inline.len();
+        self.flow.inline.push_str(s);
+        self.spans.push(Span { start, end: self.flow.inline.len(), sgr });
+    }
+
+    fn push_line(&mut self, line: Line) {
+        if std::mem::take(&mut self.flow.gap) && !self.lines.is_empty() {
+            // blank lines inside a quote keep the bar
+            let prefix = self.flow.indent().trim_end().to_string();
+            let inside = self.lines.last().is_some_and(|last| last.indent.starts_with(&prefix));
+            let prefix = if inside { prefix } else { String::new() };
+            self.lines.push(Line {
+                indent: prefix.clone(),
+                lead: prefix,
+                ..Line::default()
+            });
+        }
+        self.lines.push(line);
+    }
+}
+
+// `line` wrapped to `width`, with escape sequences
+fn rows(line: &Line, width: usize) -> Vec<String> {
+    let chars = line.text.chars().collect::<Vec<_>>();
+    if line.pre {
+        return vec![format!("{}{}", bars(&line.lead), style(&chars, &line.spans, 0, chars.len()))];
+    }
+    let room = width.saturating_sub(width::width(&line.lead)).max(width / 2).max(1);
+    layout::wrap(&line.text, room)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start, end))| {
+            let lead = if i == 0 { &line.lead } else { &line.indent };
+            format!("{}{}", bars(lead), style(&chars, &line.spans, start, end))
+        })
+        .collect()
+}
+
+// the box-drawing characters of a line of a grid, dimmed
+fn borders(text: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = vec![];
+    for (i, c) in text.chars().enumerate() {
+        if !"─│┌┐└┘├┤┬┴┼".contains(c) {
+            continue;
+        }
+        match spans.last_mut() {
+            Some(span) if span.end == i => span.end += 1,
+            _ => spans.push(Span { start: i, end: i + 1, sgr: DIM }),
+        }
+    }
+    spans
+}
+
+// quote bars dimmed
+fn bars(lead: &str) -> String {
+    lead.replace('│', "\x1b[2m│\x1b[m")
+}
+
+// `chars[start..end]` with the styles of `spans` in it
+fn style(chars: &[char], spans: &[Span], start: usize, end: usize) -> String {
+    let mut out = String::new();
+    let mut current = String::new();
+    for (i, c) in chars.iter().enumerate().take(end).skip(start) {
+        let sgr = spans
+            .iter()
+            .filter(|s| s.start <= i && i < s.end)
+            .map(|s| s.sgr)
+            .collect::<Vec<_>>()
+            .join(";");
+        if sgr != current {
+            if !current.is_empty() {
+                out.push_str("\x1b[m");
+            }
+            if !sgr.is_empty() {
+                out.push_str(&format!("\x1b[{}m", sgr));
+            }
+            current = sgr;
+        }
+        out.push(*c);
+    }
+    if !current.is_empty() {
+        out.push_str("\x1b[m");
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::dom;
+
+    #[test]
+    fn test_generate() {
+        let html = "\
+<h1>Manual</h1>
+<p>Read the <a href=\"/install.html\"> install <em>guide</em> </a> first.</p>
+<ul><li>one<li>two</ul>
+<blockquote><p>quoted</p><p>twice</p></blockquote>
+<hr>
+<pre>
+let x = 1;
+</pre>";
+        assert_eq!(
+            generate(&dom::parse(html), 12),
+            vec![
+                "\x1b[1;4mManual\x1b[m",
+                "",
+                "Read the",
+                "\x1b[4;34minstall\x1b[m",
+                "\x1b[3;4;34mguide\x1b[m\x1b[2m[1]\x1b[m",
+                "first.",
+                "",
+                "• one",
+                "• two",
+                "",
+                "\x1b[2m│\x1b[m quoted",
+                "\x1b[2m│\x1b[m",
+                "\x1b[2m│\x1b[m twice",
+                "",
+                "\x1b[2m────────────\x1b[m",
+                "",
+                "\x1b[2mlet x = 1;\x1b[m",
+                "",
+                "\x1b[2m[1]:\x1b[m /install.html",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let html = "<ol><li>a list item that wraps</ol><p><strong>bold text</strong> here</p>";
+        assert_eq!(
+            generate(&dom::parse(html), 12),
+            vec!["1. a list", "   item that", "   wraps", "", "\x1b[1mbold text\x1b[m", "here"]
+        );
+    }
+}
@@ -0,0 +1,551 @@
+// Renderer backends
+//
+// `render` walks the document tree and tells a `Renderer` about the structure it finds, leaving
+// the output format to it:
+//
+// ```
+// <h1>Manual</h1>                        start(Heading(1)) text("Manual") end(Heading(1))
+// <ol><li>Read <a href=a.html>this</a>   start(List) start(Item(Some(1))) text("Read ")
+//                                        start_inline(Link(1, "a.html")) text("this") ...
+// ```
+//
+// Which elements are blocks, hidden, or styled is decided here once; links are numbered in
+// document order. Text is given as it is in the page: backends collapse white space themselves
+// (they know where their lines start).
+//
+// Backends: `markdown_generator.rs` (Markdown with reference links), `plain_generator.rs` (text
+// like `lynx -dump`), `ansi_generator.rs` (styled for a terminal) and `json_generator.rs` (the
+// outline of the document as JSON). Those writing lines of text share how blocks are laid out
+// through `Layout`, and only say how their lines look.
+
+use crate::renderer::dom::{Element, Node};
+use crate::renderer::table::Cell;
+use crate::utils::width;
+
+// elements that start a new block of text
+const BLOCKS: &[&str] = &[
+    "address", "article", "aside", "body", "center", "details", "dialog", "div", "dl", "dt", "dd",
+    "fieldset", "figcaption", "figure", "footer", "form", "header", "html", "main", "nav", "p",
+    "section", "summary", "table", "tr", "td", "th", "caption",
+];
+
+// elements whose content is never shown
+const HIDDEN: &[&str] = &["head", "script", "style", "template", "title", "select", "datalist"];
+
+// inline elements with a style
+const STYLES: &[(&str, Inline)] = &[
+    ("b", Inline::Strong),
+    ("strong", Inline::Strong),
+    ("em", Inline::Emphasis),
+    ("i", Inline::Emphasis),
+    ("cite", Inline::Emphasis),
+    ("var", Inline::Emphasis),
+    ("code", Inline::Code),
+    ("kbd", Inline::Code),
+    ("samp", Inline::Code),
+    ("tt", Inline::Code),
+    ("u", Inline::Underline),
+    ("ins", Inline::Underline),
+    ("del", Inline::Strike),
+    ("s", Inline::Strike),
+    ("strike", Inline::Strike),
+    ("sub", Inline::Subscript),
+    ("sup", Inline::Superscript),
+];
+
+// prefixes of the classes naming the language of code
+const LANGUAGES: &[&str] = &["language-", "lang-", "highlight-source-", "highlight-"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    // any other block element (<p>, <div> ...)
+    Paragraph,
+    Heading(usize),
+    Quote,
+    // <ul> or <ol>
+    List,
+    // the number of an item of <ol>, `None` for <ul>
+    Item(Option<usize>),
+    // <hr>, with nothing inside
+    Rule,
+    // <pre>, with its text as it is (without the newline right after the tag), and the language
+    // given by a class of it or of its <code>
+    Code(String, Option<String>),
+    // a table with rows of cells; the text of a cell comes as inline content only
+    Table,
+    Row,
+    Cell { header: bool, colspan: usize, rowspan: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    Emphasis,
+    Strong,
+    Code,
+    Underline,
+    Strike,
+    Subscript,
+    Superscript,
+    // number (from 1) and target
+    Link(usize, String),
+}
+
+pub trait Renderer {
+    type Output;
+
+    fn start(&mut self, block: &Block);
+    fn end(&mut self, block: &Block);
+    fn text(&mut self, text: &str);
+
+    fn start_inline(&mut self, _inline: &Inline) {}
+    fn end_inline(&mut self, _inline: &Inline) {}
+
+    // <img> with an alternative text
+    fn image(&mut self, alt: &str, _src: &str) {
+        self.text(alt);
+    }
+
+    // <br>
+    fn line_break(&mut self) {
+        self.text(" ");
+    }
+
+    // the output, given the title of the document and the targets of its links (`[n]` is
+    // `links[n - 1]`)
+    fn finish(self, title: Option<String>, links: Vec<String>) -> Self::Output;
+}
+
+// The layout of blocks shared by the backends writing lines: the text of the current block, the
+// prefixes of the containers it is in, the marker of a list item, blank lines between blocks, and
+// the cells of a table
+//
+// ```
+// <blockquote><ul><li>a<li>b</ul>    prefixes ["> ", "  "], the marker "- " replacing the second
+//                                    in the lead of the first line of each item: "> - a", "> - b"
+// ```
+#[derive(Debug, Default)]
+pub struct Flow {
+    // text of the current block
+    pub inline: String,
+    // prefixes of the open containers, the lines of a quote or a list item after the first
+    pub prefixes: Vec<String>,
+    // whether a blank line goes before the next line
+    pub gap: bool,
+    // marker of a list item not written yet, and the prefix it replaces
+    marker: Option<(usize, String)>,
+    // lists open
+    depth: usize,
+    // rows of the open table
+    table: Option<Vec<Vec<Cell>>>,
+}
+
+impl Flow {
+    // add text, collapsing white space like CSS `white-space: normal`
+    pub fn text(&mut self, s: &str) {
+        let mut space = false;
+        for c in s.chars() {
+            if c.is_ascii_whitespace() {
+                space = true;
+                continue;
+            }
+            if space {
+                self.space();
+                space = false;
+            }
+            self.inline.push(c);
+        }
+        if space {
+            self.space();
+        }
+    }
+
+    // a space, if something comes before it on the line
+    pub fn space(&mut self) {
+        if !self.inline.is_empty() && !self.inline.ends_with(' ') && !self.inline.ends_with('\n') {
+            self.inline.push(' ');
+        }
+    }
+
+    // the text of the current block without the white space ending it, if there is any
+    pub fn take(&mut self) -> Option<String> {
+        let inline = std::mem::take(&mut self.inline);
+        let text = inline.trim_end_matches([' ', '\n']);
+        if text.is_empty() { None } else { Some(text.to_string()) }
+    }
+
+    // what goes before the next line: the prefixes, with the marker of the list item not written
+    // yet in its place
+    pub fn lead(&mut self) -> String {
+        let mut lead = String::new();
+        for (i, prefix) in self.prefixes.iter().enumerate() {
+            match &self.marker {
+                Some((at, marker)) if *at == i => lead.push_str(marker),
+                _ => lead.push_str(prefix),
+            }
+        }
+        self.marker = None;
+        lead
+    }
+
+    // what goes before the lines after the first
+    pub fn indent(&self) -> String {
+        self.prefixes.concat()
+    }
+
+    // the rows of the table ended
+    pub fn rows(&mut self) -> Vec<Vec<Cell>> {
+        self.table.take().unwrap_or_default()
+    }
+}
+
+// a backend writing lines, with its `Flow`
+pub trait Layout {
+    fn flow(&mut self) -> &mut Flow;
+
+    // write out the current block (`Flow::take`)
+    fn flush(&mut self);
+
+    // prefix of the lines in a quote, and marker of a list item
+    fn quote(&self) -> &'static str;
+    fn marker(&self, number: Option<usize>) -> String;
+
+    // write out the current block, and put a blank line before the next one
+    fn end_block(&mut self) {
+        self.flush();
+        self.flow().gap = true;
+    }
+
+    // at the start of `block`: the text before it written out, and its container opened
+    fn enter(&mut self, block: &Block) {
+        match block {
+            Block::Paragraph | Block::Heading(_) | Block::Rule | Block::Code(..) => self.end_block(),
+            Block::Quote => {
+                self.end_block();
+                let quote = self.quote();
+                self.flow().prefixes.push(quote.to_string());
+            }
+            Block::List => {
+                // nested lists stay tight
+                if self.flow().depth > 0 { self.flush() } else { self.end_block() }
+                self.flow().depth += 1;
+            }
+            Block::Item(number) => {
+                self.flush();
+                let marker = self.marker(*number);
+                let flow = self.flow();
+                flow.prefixes.push(" ".repeat(width::width(&marker)));
+                flow.marker = Some((flow.prefixes.len() - 1, marker));
+            }
+            Block::Table => {
+                self.end_block();
+                self.flow().table = Some(vec![]);
+            }
+            Block::Row => self.flow().table.get_or_insert_with(Vec::new).push(vec![]),
+            Block::Cell { .. } => {}
+        }
+    }
+
+    // at the end of `block`: its text written out, or kept as a cell; a table is left to the
+    // backend (`Flow::rows`)
+    fn leave(&mut self, block: &Block) {
+        match block {
+            Block::Paragraph | Block::Heading(_) => self.end_block(),
+            Block::Quote => {
+                self.end_block();
+                self.flow().prefixes.pop();
+            }
+            Block::List => {
+                self.flow().depth -= 1;
+                if self.flow().depth > 0 { self.flush() } else { self.end_block() }
+            }
+            Block::Item(_) => {
+                self.flush();
+                let flow = self.flow();
+                flow.prefixes.pop();
+                flow.marker = None;
+            }
+            Block::Cell { header, colspan, rowspan } => {
+                let flow = self.flow();
+                let text = flow.take().unwrap_or_default();
+                if let Some(row) = flow.table.as_mut().and_then(|t| t.last_mut()) {
+                    row.push(Cell {
+                        text,
+                        header: *header,
+                        colspan: *colspan,
+                        rowspan: *rowspan,
+                    });
+                }
+            }
+            Block::Rule | Block::Code(..) | Block::Table | Block::Row => {}
+        }
+    }
+}
+
+// walk `document` with `renderer`
+pub fn render<R: Renderer>(document: &Element, renderer: R) -> R::Output {
+    let mut walker = Walker {
+        renderer,
+        cell: false,
+        lists: vec![],
+        links: vec![],
+    };
+    walker.children(document);
+    let title = document.find("title").map(|t| collapse(&t.text())).filter(|t| !t.is_empty());
+    walker.renderer.finish(title, walker.links)
+}
+
+struct Walker<R> {
+    renderer: R,
+    // whether in a table cell, where blocks are only spaces
+    cell: bool,
+    // open lists: the next number for <ol>, `None` for <ul>
+    lists: Vec<Option<usize>>,
+    links: Vec<String>,
+}
+
+impl<R: Renderer> Walker<R> {
+    fn children(&mut self, element: &Element) {
+        for child in element.children() {
+            match child {
+                Node::Text(s) => self.renderer.text(s),
+                Node::Element(e) => self.element(e),
+            }
+        }
+    }
+
+    fn element(&mut self, e: &Element) {
+        let name = e.name();
+        match name {
+            _ if HIDDEN.contains(&name) => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                self.block(e, Block::Heading(level));
+            }
+            "blockquote" => self.block(e, Block::Quote),
+            "ul" | "ol" => {
+                let start = e.attr("start").and_then(|s| s.trim().parse().ok()).unwrap_or(1);
+                self.lists.push(if name == "ol" { Some(start) } else { None });
+                self.block(e, Block::List);
+                self.lists.pop();
+            }
+            "li" => {
+                let number = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        Some(*n - 1)
+                    }
+                    _ => None,
+                };
+                self.block(e, Block::Item(number));
+            }
+            "hr" => self.block(e, Block::Rule),
+            "pre" => {
+                let text = e.text();
+                let text = text.strip_prefix("\r\n").or_else(|| text.strip_prefix('\n')).unwrap_or(&text);
+                if self.cell {
+                    self.renderer.text(text);
+                } else {
+                    let code = Block::Code(text.to_string(), language(e));
+                    self.renderer.start(&code);
+                    self.renderer.end(&code);
+                }
+            }
+            // a table in a table is for the layout: cells are blocks then
+            "table" if e.find("table").is_none() => self.table(e),
+            "a" => match e.attr("href").map(|h| h.trim()) {
+                Some(href) if !href.is_empty() => {
+                    self.links.push(href.to_string());
+                    self.inline(e, Inline::Link(self.links.len(), href.to_string()));
+                }
+                _ => self.children(e),
+            },
+            "img" => {
+                let alt = collapse(e.attr("alt").unwrap_or_default());
+                if !alt.is_empty() {
+                    self.renderer.image(&alt, e.attr("src").unwrap_or_default().trim());
+                }
+            }
+            "br" if self.cell => self.renderer.text(" "),
+            "br" => self.renderer.line_break(),
+            _ if BLOCKS.contains(&name) => self.block(e, Block::Paragraph),
+            _ => match STYLES.iter().find(|(n, _)| *n == name) {
+                Some((_, inline)) => self.inline(e, inline.clone()),
+                None => self.children(e),
+            },
+        }
+    }
+
+    fn block(&mut self, e: &Element, block: Block) {
+        if self.cell {
+            self.renderer.text(" ");
+            self.children(e);
+            self.renderer.text(" ");
+            return;
+        }
+        self.renderer.start(&block);
+        self.children(e);
+        self.renderer.end(&block);
+    }
+
+    // the caption as a paragraph, then the rows: <thead> first, <tfoot> last
+    fn table(&mut self, table: &Element) {
+        let mut rows = vec![];
+        let mut foot = vec![];
+        for child in elements(table) {
+            match child.name() {
+                "caption" => self.block(child, Block::Paragraph),
+                "tr" => rows.push((child, false)),
+                "thead" => rows.extend(elements(child).filter(|e| e.name() == "tr").map(|e| (e, true))),
+                "tbody" => rows.extend(elements(child).filter(|e| e.name() == "tr").map(|e| (e, false))),
+                "tfoot" => foot.extend(elements(child).filter(|e| e.name() == "tr").map(|e| (e, false))),
+                _ => {}
+            }
+        }
+        rows.append(&mut foot);
+
+        self.renderer.start(&Block::Table);
+        for (row, head) in rows {
+            self.renderer.start(&Block::Row);
+            for cell in elements(row).filter(|e| e.name() == "td" || e.name() == "th") {
+                let span = |name| cell.attr(name).and_then(|s| s.trim().parse().ok()).unwrap_or(1).clamp(1, 100);
+                let block = Block::Cell {
+                    header: head || cell.name() == "th",
+                    colspan: span("colspan"),
+                    rowspan: span("rowspan"),
+                };
+                self.renderer.start(&block);
+                self.cell = true;
+                self.children(cell);
+                self.cell = false;
+                self.renderer.end(&block);
+            }
+            self.renderer.end(&Block::Row);
+        }
+        self.renderer.end(&Block::Table);
+    }
+
+    fn inline(&mut self, e: &Element, inline: Inline) {
+        self.renderer.start_inline(&inline);
+        self.children(e);
+        self.renderer.end_inline(&inline);
+    }
+}
+
+// the child elements of `e`
+fn elements(e: &Element) -> impl Iterator<Item = &Element> {
+    e.children().iter().filter_map(|child| match child {
+        Node::Element(e) => Some(e),
+        Node::Text(_) => None,
+    })
+}
+
+// the language of <pre>, from a class of the <code> in it or of itself
+//
+// ```
+// <pre><code class="language-rust">          ->  rust
+// <pre class="lang-py">, "highlight-source-sh"  ->  py, sh
+// ```
+fn language(pre: &Element) -> Option<String> {
+    let code = elements(pre).find(|e| e.name() == "code");
+    code.into_iter()
+        .chain(Some(pre))
+        .flat_map(|e| e.attr("class").unwrap_or_default().split_ascii_whitespace())
+        .find_map(|class| LANGUAGES.iter().find_map(|p| class.strip_prefix(p)))
+        .filter(|language| !language.is_empty() && !language.contains('`'))
+        .map(|language| language.to_string())
+}
+
+// `  a \n b ` -> `a b`
+pub fn collapse(s: &str) -> String {
+    s.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::dom;
+
+    // the calls as a string
+    #[derive(Default)]
+    struct Trace(String);
+
+    impl Renderer for Trace {
+        type Output = String;
+
+        fn start(&mut self, block: &Block) {
+            self.0.push_str(&format!("<{:?}>", block));
+        }
+
+        fn end(&mut self, block: &Block) {
+            self.0.push_str(&format!("</{:?}>", block));
+        }
+
+        fn text(&mut self, text: &str) {
+            self.0.push_str(text);
+        }
+
+        fn start_inline(&mut self, inline: &Inline) {
+            self.0.push_str(&format!("<{:?}>", inline));
+        }
+
+        fn finish(self, title: Option<String>, links: Vec<String>) -> String {
+            format!("{:?} {} {:?}", title, self.0, links)
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let html = "<title> A  page </title><h2>Hi</h2><ol start=3><li><em>a</em><li><a href=' b.html '>b</a></ol>\
+                    <pre class='lang-sh'>\nx</pre><script>no</script>";
+        assert_eq!(
+            render(&dom::parse(html), Trace::default()),
+            "Some(\"A page\") <Heading(2)>Hi</Heading(2)><List><Item(Some(3))><Emphasis>a</Item(Some(3))>\
+             <Item(Some(4))><Link(1, \"b.html\")>b</Item(Some(4))></List><Code(\"x\", Some(\"sh\"))></Code(\"x\", Some(\"sh\"))> [\"b.html\"]"
+        );
+    }
+
+    // the lines as `lead|text`
+    #[derive(Default)]
+    struct Lines(Flow, Vec<String>);
+
+    impl Layout for Lines {
+        fn flow(&mut self) -> &mut Flow {
+            &mut self.0
+        }
+
+        fn flush(&mut self) {
+            if let Some(text) = self.0.take() {
+                let line = format!("{}|{}", self.0.lead(), text);
+                self.1.push(line);
+            }
+        }
+
+        fn quote(&self) -> &'static str {
+            "> "
+        }
+
+        fn marker(&self, number: Option<usize>) -> String {
+            format!("{}. ", number.unwrap_or_default())
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut lines = Lines::default();
+        lines.enter(&Block::Quote);
+        lines.enter(&Block::List);
+        lines.enter(&Block::Item(Some(1)));
+        lines.0.text("  a \n b ");
+        lines.enter(&Block::Paragraph);
+        lines.0.text("c");
+        lines.leave(&Block::Paragraph);
+        lines.leave(&Block::Item(Some(1)));
+        lines.enter(&Block::Table);
+        lines.enter(&Block::Row);
+        lines.enter(&Block::Cell { header: true, colspan: 1, rowspan: 1 });
+        lines.0.text(" d ");
+        lines.leave(&Block::Cell { header: true, colspan: 1, rowspan: 1 });
+        assert_eq!(lines.0.rows(), vec![vec![Cell::new("d", true)]]);
+        assert_eq!(lines.1, vec!["> 1. |a b", ">    |c"]);
+    }
+}
@@ -0,0 +1,286 @@
+// Document tree built from the tokens of `html_parser`
+//
+// ```
+// #document
+// └─ html
+//    └─ body
+//       ├─ h1
+//       │  └─ "Hello"
+//       └─ p
+//          └─ "world"
+// ```
+//
+// Like browsers, building the tree never fails. Void elements have no children, stray end tags are
+// ignored, and elements whose end tag may be omitted (<p>, <li>, <td> ...) are closed where the
+// next one starts.
+// ref: https://html.spec.whatwg.org/multipage/syntax.html#optional-tags
+
+use crate::renderer::html_parser::{self, Token, TokenType};
+
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+// start tags that close an open <p>
+const CLOSES_P: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "div", "dl", "fieldset", "figcaption", "figure",
+    "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "menu", "nav", "ol",
+    "p", "pre", "section", "table", "ul",
+];
+
+// groups of table rows
+const SECTIONS: [&str; 3] = ["thead", "tbody", "tfoot"];
+
+// elements an implied end tag does not look beyond
+const SCOPE: &[&str] = &["html", "table", "td", "th", "caption", "template", "object", "marquee", "applet"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    pub fn new(name: &str, attrs: Vec<(String, String)>) -> Self {
+        Self {
+            name: name.to_string(),
+            attrs,
+            children: vec![],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn attrs(&self) -> &[(String, String)] {
+        &self.attrs
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    pub fn push(&mut self, node: Node) {
+        // keep adjacent text in one node
+        if let (Node::Text(s), Some(Node::Text(last))) = (&node, self.children.last_mut()) {
+            last.push_str(s);
+            return;
+        }
+        self.children.push(node);
+    }
+
+    // all the text inside, as it is
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for child in &self.children {
+            match child {
+                Node::Text(s) => text.push_str(s),
+                Node::Element(e) => text.push_str(&e.text()),
+            }
+        }
+        text
+    }
+
+    // the first element named `name` inside (depth-first)
+    pub fn find(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find_map(|child| match child {
+            Node::Element(e) if e.name == name => Some(e),
+            Node::Element(e) => e.find(name),
+            Node::Text(_) => None,
+        })
+    }
+}
+
+// parse HTML into a tree under a `#document` element
+pub fn parse(html: &str) -> Element {
+    let mut builder = Builder {
+        stack: vec![Element::new("#document", vec![])],
+    };
+    for token in html_parser::tokenize(html) {
+        builder.token(token);
+    }
+    builder.finish()
+}
+
+struct Builder {
+    // open elements, the document first
+    stack: Vec<Element>,
+}
+
+impl Builder {
+    fn token(&mut self, token: Token) {
+        match token.token_type() {
+            TokenType::StartTag => self.start(&token),
+            TokenType::EndTag => self.end(token.name().unwrap_or_default()),
+            TokenType::Content => {
+                let text = token.data().unwrap_or_default().to_string();
+                self.top().push(Node::Text(text));
+            }
+            TokenType::Doctype | TokenType::Comment => {}
+        }
+    }
+
+    fn start(&mut self, token: &Token) {
+        let name = token.name().unwrap_or_default();
+        if CLOSES_P.contains(&name) {
+            self.close_in_scope("p", &[]);
+        }
+        match name {
+            "li" => self.close_in_scope("li", &["ul", "ol"]),
+            "dt" | "dd" => {
+                self.close_in_scope("dt", &["dl"]);
+                self.close_in_scope("dd", &["dl"]);
+            }
+            "tr" => {
+                self.close_cell();
+                self.close_in_scope("tr", &SECTIONS);
+            }
+            "thead" | "tbody" | "tfoot" => {
+                self.close_cell();
+                self.close_in_scope("tr", &SECTIONS);
+                for section in SECTIONS {
+                    self.close_in_scope(section, &[]);
+                }
+            }
+            "td" | "th" => self.close_cell(),
+            "option" => self.close_in_scope("option", &[]),
+            // headings do not nest
+            _ if is_heading(name) && is_heading(self.top().name()) => self.pop(),
+            _ => {}
+        }
+
+        let attrs = token
+            .attrs()
+            .iter()
+            .map(|a| (a.name().to_string(), a.value().to_string()))
+            .collect();
+        let element = Element::new(name, attrs);
+        if VOID.contains(&name) || token.self_closing() {
+            self.top().push(Node::Element(element));
+        } else {
+            self.stack.push(element);
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        // `</h2>` closes any heading
+        let found = self
+            .stack
+            .iter()
+            .skip(1)
+            .rposition(|e| e.name == name || (is_heading(name) && is_heading(&e.name)));
+        if let Some(i) = found {
+            while self.stack.len() > i + 1 {
+                self.pop();
+            }
+        }
+    }
+
+    // close the innermost `name`, unless a scope boundary or one of `boundaries` comes first
+    fn close_in_scope(&mut self, name: &str, boundaries: &[&str]) {
+        let mut found = None;
+        for (i, e) in self.stack.iter().enumerate().skip(1).rev() {
+            if e.name == name {
+                found = Some(i);
+                break;
+            }
+            if SCOPE.contains(&e.name.as_str()) || boundaries.contains(&e.name.as_str()) {
+                break;
+            }
+        }
+        if let Some(i) = found {
+            while self.stack.len() > i {
+                self.pop();
+            }
+        }
+    }
+
+    // close an open <td> or <th> of the current row
+    fn close_cell(&mut self) {
+        self.close_in_scope("td", &["tr"]);
+        self.close_in_scope("th", &["tr"]);
+    }
+
+    fn top(&mut self) -> &mut Element {
+        self.stack.last_mut().unwrap()
+    }
+
+    // close the current element (never the document)
+    fn pop(&mut self) {
+        if self.stack.len() > 1 {
+            let element = self.stack.pop().unwrap();
+            self.top().push(Node::Element(element));
+        }
+    }
+
+    fn finish(mut self) -> Element {
+        while self.stack.len() > 1 {
+            self.pop();
+        }
+        self.stack.pop().unwrap()
+    }
+}
+
+fn is_heading(name: &str) -> bool {
+    matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // element names and text in a compact form: `p("a" b("c"))`
+    fn outline(node: &Node) -> String {
+        match node {
+            Node::Text(s) => format!("{:?}", s),
+            Node::Element(e) => {
+                let children = e.children().iter().map(outline).collect::<Vec<_>>();
+                format!("{}({})", e.name(), children.join(" "))
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let doc = parse("<!DOCTYPE html><html><body><h1>Hi</h1><p>a<br>b</p></body></html>");
+        assert_eq!(outline(&Node::Element(doc)), "#document(html(body(h1(\"Hi\") p(\"a\" br() \"b\"))))");
+
+        // omitted end tags
+        let doc = parse("<p>one<p>two<ul><li>a<li>b<ul><li>c</ul></ul><h2>x</h3>");
+        assert_eq!(
+            outline(&Node::Element(doc)),
+            "#document(p(\"one\") p(\"two\") ul(li(\"a\") li(\"b\" ul(li(\"c\")))) h2(\"x\"))"
+        );
+
+        // rows and cells
+        let doc = parse("<table><thead><tr><th>a<tbody><tr><td>b<td>c<tr><td>d</table>");
+        assert_eq!(
+            outline(&Node::Element(doc)),
+            "#document(table(thead(tr(th(\"a\"))) tbody(tr(td(\"b\") td(\"c\")) tr(td(\"d\")))))"
+        );
+
+        // stray end tags are ignored, unclosed elements are closed at the end
+        let doc = parse("</div>a<b>b<i>c</b>d");
+        assert_eq!(outline(&Node::Element(doc)), "#document(\"a\" b(\"b\" i(\"c\")) \"d\")");
+    }
+
+    #[test]
+    fn test_find() {
+        let doc = parse("<html><head><title>T &amp; C</title></head><body><a href=/x>link</a></body></html>");
+        assert_eq!(doc.find("title").map(|e| e.text()), Some("T & C".to_string()));
+        assert_eq!(doc.find("a").and_then(|e| e.attr("href")), Some("/x"));
+        assert!(doc.find("table").is_none());
+    }
+}
@@ -2,7 +2,7 @@
 // support tags
 //
 // <html>, <head>, <body>
-// <title> 
+// <title>
 // <h1>, <h2>, <h3>, <h4>, <h5> -> #
 // <p>
 // <a> -> []()
@@ -11,32 +11,62 @@
 // <hr> -> ---
 // <code> -> ```
 //
-
-// TODO:
-// - split off tag name state from 
+// Like browsers, the tokenizer never fails: malformed markup ends up as text or is dropped.
+// The contents of <script> and <style> are raw text, and <title> and <textarea> contain no tags.
 
 use crate::utils::consumer::Consumer;
 
-
+// elements whose content is not markup (up to the matching end tag)
+const RAW_TEXT: &[&str] = &["script", "style", "xmp", "iframe", "noembed", "noframes"];
+// the same, but character references are decoded
+const ESCAPABLE_RAW_TEXT: &[&str] = &["title", "textarea"];
+
+// named character references (the ones common in practice)
+const ENTITIES: &[(&str, &str)] = &[
+    ("amp", "&"), ("lt", "<"), ("gt", ">"), ("quot", "\""), ("apos", "'"), ("nbsp", "\u{a0}"),
+    ("copy", "©"), ("reg", "®"), ("trade", "™"), ("hellip", "…"), ("mdash", "—"), ("ndash", "–"),
+    ("lsquo", "‘"), ("rsquo", "’"), ("ldquo", "“"), ("rdquo", "”"), ("laquo", "«"), ("raquo", "»"),
+    ("middot", "·"), ("bull", "•"), ("times", "×"), ("divide", "÷"), ("deg", "°"), ("plusmn", "±"),
+    ("sect", "§"), ("para", "¶"), ("cent", "¢"), ("pound", "£"), ("yen", "¥"), ("euro", "€"),
+    ("larr", "←"), ("rarr", "→"), ("uarr", "↑"), ("darr", "↓"), ("harr", "↔"), ("shy", "\u{ad}"),
+    ("ensp", "\u{2002}"), ("emsp", "\u{2003}"), ("thinsp", "\u{2009}"), ("zwj", "\u{200d}"), ("zwnj", "\u{200c}"),
+];
+
+// Example:
+// ```rust
+// let tokens = tokenize("<p class=note>Hello</p>");
+// assert_eq!(tokens[0].name(), Some("p"));
+// assert_eq!(tokens[0].attr("class"), Some("note"));
+// assert_eq!(tokens[1].data(), Some("Hello"));
+// ```
+pub fn tokenize(src: &str) -> Vec<Token> {
+    Tokenizer::new(src).execute()
+}
 
 // reference: https://html.spec.whatwg.org/multipage/parsing.html#tokenization
-struct Tokenizer {
+pub struct Tokenizer {
     con: Consumer,
     state: StateType,
     tokens: Vec<Token>,
     current: Option<Token>,
-    current_attr: Attribute,
+    current_attr: Option<Attribute>,
+    // character data not emitted yet
+    text: String,
+    // name of the element whose raw text is being read
+    raw_tag: String,
 }
 
 impl Tokenizer {
-    
+
     pub fn new(src: &str) -> Self {
         Self {
             con: Consumer::new(src),
             state: StateType::Data,
             tokens: vec![],
             current: None,
-            current_attr: Attribute::new(),
+            current_attr: None,
+            text: String::new(),
+            raw_tag: String::new(),
         }
     }
 
@@ -44,285 +74,475 @@ impl Tokenizer {
         while self.con.peek_char().is_some() {
             match self.state {
                 StateType::Data => self.data(),
+                StateType::RawText => self.raw_text(),
                 StateType::TagOpen => self.tag_open(),
                 StateType::EndTagOpen => self.end_tag_open(),
                 StateType::TagName => self.tag_name(),
                 StateType::BeforeAttributeName => self.before_attr_name(),
                 StateType::AttributeName => self.attr_name(),
+                StateType::AfterAttributeName => self.after_attr_name(),
                 StateType::BeforeAttributeValue => self.before_attr_val(),
-                StateType::AttributeValueQuoted => self.attr_val_quoted(),
+                StateType::AttributeValueQuoted(quote) => self.attr_val_quoted(quote),
                 StateType::AttributeValueUnquoted => self.attr_val_unquoted(),
                 StateType::AfterAttributeValue => self.after_attr_val(),
                 StateType::SelfClosingStartTag => self.self_closing_start_tag(),
                 StateType::MarkupDeclarationOpen => self.markup_declaration_open(),
                 StateType::Comment => self.comment(),
+                StateType::BogusComment => self.bogus_comment(),
                 StateType::Doctype => self.doctype(),
             }
         }
-        
-        // self.tokens.push(std::mem::replace(&mut self.current, Token::default()));
+        self.eof();
         std::mem::take(&mut self.tokens)
     }
 
     fn data(&mut self) {
-        if self.current.is_none() {
-            self.current = Some(Token::new(TokenType::Content));
-        }
         // consume the next input character
         match self.con.next_char() {
-            Some(c) => match c {
-                '<' => {
-                    // switch to the tag open state
-                    self.state = StateType::TagOpen;
-                    // TODO: is really the following code needed?
-                    // self.tokens.push(std::mem::take(&mut self.current));
-                },
-                // emit the current input charater as a character token
-                _ => self.current.as_mut().unwrap().push_data(c),
+            // switch to the tag open state
+            Some('<') => self.state = StateType::TagOpen,
+            Some('&') => {
+                let s = self.char_ref(false);
+                self.text.push_str(&s);
             }
-            None => return,
+            // emit the current input charater as a character token
+            Some(c) => self.text.push(c),
+            None => {}
         }
     }
 
-    fn tag_open(&mut self) {
-        if let Some(token) = &mut self.current {
-            if token.data.is_some() {
-                self.tokens.push(self.current.take().unwrap());
+    // the content of <script>, <title> etc. up to `</name`
+    fn raw_text(&mut self) {
+        let end = format!("</{}", self.raw_tag);
+        if self.con.peek_n(end.len()).is_some_and(|s| s.eq_ignore_ascii_case(&end)) {
+            self.con.next_n(2);
+            self.current = Some(Token::new(TokenType::EndTag));
+            self.state = StateType::TagName;
+            return;
+        }
+        match self.con.next_char() {
+            Some('&') if ESCAPABLE_RAW_TEXT.contains(&self.raw_tag.as_str()) => {
+                let s = self.char_ref(false);
+                self.text.push_str(&s);
             }
+            Some(c) => self.text.push(c),
+            None => {}
         }
-        self.current = Some(Token::new(TokenType::StartTag));
-        // consume the next input character
+    }
+
+    // ref: https://html.spec.whatwg.org/multipage/parsing.html#tag-open-state
+    fn tag_open(&mut self) {
         match self.con.peek_char() {
-            Some(c) => match c {
-                '!' => {
-                    // switch to the markup declaration open state
-                    self.con.next_char().unwrap();
-                    self.state = StateType::MarkupDeclarationOpen;
-                },
-                '/' => {
-                    // switch to the end tag open state
-                    self.con.next_char().unwrap();
-                    self.state = StateType::EndTagOpen;
-                },
-                // 
-                c if c.is_alphabetic() => self.state = StateType::TagName,
-                _ => panic!("Error: Invalid first character of tag name"),
-            },
-            None => panic!("Error: EOF before tag name"),
+            Some('!') => {
+                // switch to the markup declaration open state
+                self.con.next_char();
+                self.state = StateType::MarkupDeclarationOpen;
+            }
+            Some('/') => {
+                // switch to the end tag open state
+                self.con.next_char();
+                self.state = StateType::EndTagOpen;
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.current = Some(Token::new(TokenType::StartTag));
+                self.state = StateType::TagName;
+            }
+            Some('?') => {
+                self.current = Some(Token::new(TokenType::Comment));
+                self.state = StateType::BogusComment;
+            }
+            // not a tag: `a < b`
+            _ => {
+                self.text.push('<');
+                self.state = StateType::Data;
+            }
         }
     }
 
+    // ref: https://html.spec.whatwg.org/multipage/parsing.html#end-tag-open-state
     fn end_tag_open(&mut self) {
-        self.current.as_mut().unwrap().set_type(TokenType::EndTag);
         match self.con.peek_char() {
-            Some(c) => match c {
-                '>' => panic!("Error: Missing end tag name"),
-                c if c.is_alphabetic() => self.state = StateType::TagName,
-                _ => panic!("Error: Invalid first character of tag name"),
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.current = Some(Token::new(TokenType::EndTag));
+                self.state = StateType::TagName;
+            }
+            // `</>` is ignored
+            Some('>') => {
+                self.con.next_char();
+                self.state = StateType::Data;
+            }
+            _ => {
+                self.current = Some(Token::new(TokenType::Comment));
+                self.state = StateType::BogusComment;
             }
-            None => panic!("Error: EOF before tag name"),
         }
-
     }
 
+    // ref: https://html.spec.whatwg.org/multipage/parsing.html#tag-name-state
     fn tag_name(&mut self) {
         match self.con.next_char() {
-            Some(c) => match c {
-                ' ' => self.state = StateType::BeforeAttributeName,
-                '/' => self.state = StateType::SelfClosingStartTag,
-                '>' => {
-                    self.state = StateType::Data;
-                    self.tokens.push(self.current.take().unwrap());
-                },
-                _ => self.current.as_mut().unwrap().push_name(c),
-            }
-            None => panic!("Error: EOF in tag"),
+            Some(c) if is_space(c) => self.state = StateType::BeforeAttributeName,
+            Some('/') => self.state = StateType::SelfClosingStartTag,
+            Some('>') => self.emit_current(),
+            Some(c) => self.current_mut().push_name(c.to_ascii_lowercase()),
+            None => {}
         }
     }
-    
+
     // ref: https://html.spec.whatwg.org/multipage/parsing.html#before-attribute-name-state
     fn before_attr_name(&mut self) {
         match self.con.peek_char() {
-            Some(c) => match c {
-                ' ' => self.con.skip_space(),
-                '=' => panic!("Error: Unexpected equals sign before attribute name"),
-                _ => self.state = StateType::AttributeName,
+            Some(c) if is_space(c) => {
+                self.con.next_char();
+            }
+            Some('/') | Some('>') => self.state = StateType::AfterAttributeName,
+            // `<a =b>` makes an attribute named `=`
+            Some('=') => {
+                self.con.next_char();
+                let mut attr = Attribute::new();
+                attr.push_name('=');
+                self.current_attr = Some(attr);
+                self.state = StateType::AttributeName;
+            }
+            _ => {
+                self.current_attr = Some(Attribute::new());
+                self.state = StateType::AttributeName;
             }
-            //
-            None => panic!("Error: Unexpected EOF"),
         }
     }
-    
+
     // ref: https://html.spec.whatwg.org/multipage/parsing.html#attribute-name-state
     fn attr_name(&mut self) {
-        match self.con.next_char() {
-            Some(c) => match c {
-                c if c.is_alphabetic() => self.current_attr.push_name(c),
-                '=' => self.state = StateType::BeforeAttributeValue,
-                _ => panic!("Error: Unexpected character in attribute name"),
+        match self.con.peek_char() {
+            Some(c) if is_space(c) || c == '/' || c == '>' => self.state = StateType::AfterAttributeName,
+            Some('=') => {
+                self.con.next_char();
+                self.state = StateType::BeforeAttributeValue;
+            }
+            Some(c) => {
+                self.con.next_char();
+                if let Some(attr) = &mut self.current_attr {
+                    attr.push_name(c.to_ascii_lowercase());
+                }
+            }
+            None => {}
+        }
+    }
+
+    // ref: https://html.spec.whatwg.org/multipage/parsing.html#after-attribute-name-state
+    fn after_attr_name(&mut self) {
+        match self.con.peek_char() {
+            Some(c) if is_space(c) => {
+                self.con.next_char();
+            }
+            Some('/') => {
+                self.con.next_char();
+                self.finish_attr();
+                self.state = StateType::SelfClosingStartTag;
+            }
+            Some('=') => {
+                self.con.next_char();
+                self.state = StateType::BeforeAttributeValue;
+            }
+            Some('>') => {
+                self.con.next_char();
+                self.finish_attr();
+                self.emit_current();
+            }
+            // a boolean attribute followed by another one
+            _ => {
+                self.finish_attr();
+                self.current_attr = Some(Attribute::new());
+                self.state = StateType::AttributeName;
             }
-            // EOF -> Reconsume in the after attribute name state
-            None => panic!("Error: Unexpected EOF"),
         }
     }
-        
+
     // ref: https://html.spec.whatwg.org/multipage/parsing.html#before-attribute-value-state
     fn before_attr_val(&mut self) {
         match self.con.peek_char() {
-            Some(c) => match c {
-                ' ' => self.con.skip_space(),
-                '\'' | '"' => {
-                    self.state = StateType::AttributeValueQuoted;
-                    self.con.next_char().unwrap();
-                },
-                '>' => panic!("Error: Missing attribute value"),
-                _ => self.state = StateType::AttributeValueUnquoted,
+            Some(c) if is_space(c) => {
+                self.con.next_char();
+            }
+            Some(quote) if quote == '\'' || quote == '"' => {
+                self.con.next_char();
+                self.state = StateType::AttributeValueQuoted(quote);
             }
-            None => panic!("Error: Unexpected EOF"),
+            // missing attribute value
+            Some('>') => {
+                self.con.next_char();
+                self.finish_attr();
+                self.emit_current();
+            }
+            _ => self.state = StateType::AttributeValueUnquoted,
         }
     }
-    
+
     // ref: https://html.spec.whatwg.org/multipage/parsing.html#attribute-value-(double-quoted)-state
     // ref: https://html.spec.whatwg.org/multipage/parsing.html#attribute-value-(single-quoted)-state
-    fn attr_val_quoted(&mut self) {
+    fn attr_val_quoted(&mut self, quote: char) {
         match self.con.next_char() {
-            Some(c) => match c {
-                '\'' | '"' => {
-                    self.state = StateType::AfterAttributeValue;
-                    let attr = std::mem::replace(&mut self.current_attr, Attribute::new());
-                    self.current.as_mut().unwrap().push_attr(attr);
-                },
-                _ => self.current_attr.push_val(c),
-                // _ => panic!("Error: Invalid character in attribute value; `{}`", c),
+            Some(c) if c == quote => {
+                self.finish_attr();
+                self.state = StateType::AfterAttributeValue;
             }
-            None => panic!("Error: EOF in tag"),
+            Some('&') => {
+                let s = self.char_ref(true);
+                self.push_attr_val(&s);
+            }
+            Some(c) => self.push_attr_val(&c.to_string()),
+            None => {}
         }
     }
 
     // ref: https://html.spec.whatwg.org/multipage/parsing.html#attribute-value-(unquoted)-state
     fn attr_val_unquoted(&mut self) {
         match self.con.next_char() {
-            Some(c) => match c {
-                ' ' | '\t' | '\r' | '\n' => self.state = StateType::BeforeAttributeName,
-                '>' => {
-                    self.state = StateType::Data;
-                    self.tokens.push(self.current.take().unwrap());
-                },
-                c if c.is_alphanumeric() => self.current_attr.push_val(c),
-                _ => panic!("Error: Unexpected character in unquoted attribute value"),
+            Some(c) if is_space(c) => {
+                self.finish_attr();
+                self.state = StateType::BeforeAttributeName;
+            }
+            Some('&') => {
+                let s = self.char_ref(true);
+                self.push_attr_val(&s);
             }
-            None => panic!("Error: EOF in tag"),
+            Some('>') => {
+                self.finish_attr();
+                self.emit_current();
+            }
+            Some(c) => self.push_attr_val(&c.to_string()),
+            None => {}
         }
     }
 
-
     // ref: https://html.spec.whatwg.org/multipage/parsing.html#after-attribute-value-(quoted)-state
     fn after_attr_val(&mut self) {
-        match self.con.next_char() {
-            Some(c) => match c {
-                ' ' | '\r' | '\t' | '\n' => self.state = StateType::BeforeAttributeName,
-                '/' => {
-                    self.state = StateType::SelfClosingStartTag;
-                    self.current.as_mut().unwrap().set_self_closing(true);
-                },
-                '>' => {self.state = StateType::Data},
-                _ => panic!("Error: Missing whitespace between attributes"),
+        match self.con.peek_char() {
+            Some(c) if is_space(c) => {
+                self.con.next_char();
+                self.state = StateType::BeforeAttributeName;
+            }
+            Some('/') => {
+                self.con.next_char();
+                self.state = StateType::SelfClosingStartTag;
+            }
+            Some('>') => {
+                self.con.next_char();
+                self.emit_current();
             }
-            None => panic!("Error: EOF in tag"),
+            // missing whitespace between attributes
+            _ => self.state = StateType::BeforeAttributeName,
         }
     }
-    
-    // ref: https://html.spec.whatwg.org/multipage/parsing.html#after-attribute-value-(quoted)-state
+
+    // ref: https://html.spec.whatwg.org/multipage/parsing.html#self-closing-start-tag-state
     fn self_closing_start_tag(&mut self) {
-        match self.con.next_char() {
-            Some(c) => match c {
-                '>' => {
-                    self.current.as_mut().unwrap().set_self_closing(true);
-                    self.state = StateType::Data;
-                    self.tokens.push(self.current.take().unwrap());
-                }
-                _ => panic!("Error: Unexpected solidus in tag"),
+        match self.con.peek_char() {
+            Some('>') => {
+                self.con.next_char();
+                self.current_mut().set_self_closing(true);
+                self.emit_current();
             }
-            None => panic!("Error: EOF in tag"),
+            // unexpected solidus in tag
+            _ => self.state = StateType::BeforeAttributeName,
         }
     }
-    
-    // ref: https://html.spec.whatwg.org/multipage/parsing.html#after-attribute-value-(quoted)-state
+
+    // ref: https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
     fn markup_declaration_open(&mut self) {
+        if self.con.peek_n(2).is_some_and(|s| s == "--") {
+            self.con.next_n(2);
+            self.current = Some(Token::new(TokenType::Comment));
+            self.state = StateType::Comment;
+        } else if self.con.peek_n(7).is_some_and(|s| s.eq_ignore_ascii_case("DOCTYPE")) {
+            self.con.next_n(7);
+            self.current = Some(Token::new(TokenType::Doctype));
+            self.state = StateType::Doctype;
+        } else {
+            // incorrectly opened comment (`<![CDATA[` included)
+            self.current = Some(Token::new(TokenType::Comment));
+            self.state = StateType::BogusComment;
+        }
+    }
+
+    fn comment(&mut self) {
+        let end_comment = "-->";
+        if self.con.peek_n(end_comment.len()).is_some_and(|s| s == end_comment) {
+            self.con.next_n(end_comment.len());
+            self.emit_current();
+            return;
+        }
+        if let Some(c) = self.con.next_char() {
+            self.current_mut().push_data(c);
+        }
+    }
+
+    // ref: https://html.spec.whatwg.org/multipage/parsing.html#bogus-comment-state
+    fn bogus_comment(&mut self) {
+        match self.con.next_char() {
+            Some('>') => self.emit_current(),
+            Some(c) => self.current_mut().push_data(c),
+            None => {}
+        }
+    }
 
-        let doctype_len = 7; // == "DOCTYPE".len();
-        if let Some(s) = self.con.peek_n(doctype_len) {
-            if s == "DOCTYPE" {
-                self.con.next_n(doctype_len).unwrap();
-                self.state = StateType::Doctype;
-                self.current.as_mut().unwrap().set_type(TokenType::Doctype);
+    // `<!DOCTYPE html PUBLIC ...>`: only the name is kept
+    fn doctype(&mut self) {
+        match self.con.next_char() {
+            Some('>') => self.emit_current(),
+            // ignore the public and system identifiers
+            Some(c) if is_space(c) && self.current_mut().name.is_some() => self.current_mut().push_data(c),
+            Some(c) if is_space(c) => {}
+            Some(c) => {
+                let token = self.current_mut();
+                if token.data.is_none() {
+                    token.push_name(c.to_ascii_lowercase());
+                }
             }
+            None => {}
         }
+    }
 
-        let comment_tag_len = 2; // == "--".len(); // <- already read "<!"
-        if let Some(s) = self.con.next_n(comment_tag_len) {
-            if s == "--" {
-                self.state = StateType::Comment;
-                self.current.as_mut().unwrap().set_type(TokenType::Comment);
+    // what is left at the end of the input
+    fn eof(&mut self) {
+        match self.state {
+            StateType::TagOpen => self.text.push('<'),
+            StateType::EndTagOpen => self.text.push_str("</"),
+            StateType::Comment | StateType::BogusComment | StateType::Doctype => self.emit_current(),
+            // a tag cut off in the middle is dropped
+            _ => {}
+        }
+        self.flush_text();
+    }
+
+    // ref: https://html.spec.whatwg.org/multipage/parsing.html#character-reference-state
+    // after `&`: the referenced character, or `&` if this is not a reference
+    fn char_ref(&mut self, in_attr: bool) -> String {
+        if self.con.peek_char() == Some('#') {
+            return self.numeric_char_ref();
+        }
+        for (name, value) in ENTITIES {
+            if self.con.peek_n(name.len()).as_deref() != Some(name) {
+                continue;
+            }
+            let next = self.con.peek_n(name.len() + 1).and_then(|s| s.chars().last());
+            match next {
+                Some(';') => {
+                    self.con.next_n(name.len() + 1);
+                    return value.to_string();
+                }
+                // `?a=1&copyright=2` in an attribute is not a reference
+                Some(c) if in_attr && (c.is_ascii_alphanumeric() || c == '=') => break,
+                Some(c) if c.is_ascii_alphanumeric() => continue,
+                _ => {
+                    self.con.next_n(name.len());
+                    return value.to_string();
+                }
             }
         }
-        panic!("Error: Incorrectly opened comment");
+        "&".to_string()
     }
 
-    fn comment(&mut self) {
-        let end_comment = "-->";
-        match self.con.next_until(end_comment) {
-            Some(s) => {
-                self.con.next_n(end_comment.len()).unwrap();
-                s.chars().for_each(|c| self.current.as_mut().unwrap().push_data(c));
+    // `&#38;` or `&#x26;`
+    fn numeric_char_ref(&mut self) -> String {
+        let hex = self.con.peek_n(2).is_some_and(|s| s.eq_ignore_ascii_case("#x"));
+        let prefix = if hex { 2 } else { 1 };
+        let radix = if hex { 16 } else { 10 };
+        let mut digits = String::new();
+        while let Some(c) = self.con.peek_n(prefix + digits.len() + 1).and_then(|s| s.chars().last()) {
+            if !c.is_digit(radix) {
+                break;
             }
-            None => panic!("Error: EOF in comment tag"),
+            digits.push(c);
+        }
+        if digits.is_empty() {
+            return "&".to_string();
         }
+        self.con.next_n(prefix + digits.len());
+        if self.con.peek_char() == Some(';') {
+            self.con.next_char();
+        }
+        let c = u32::from_str_radix(&digits, radix)
+            .ok()
+            .filter(|&n| n != 0)
+            .and_then(char::from_u32)
+            .unwrap_or('\u{fffd}');
+        c.to_string()
     }
 
-    fn doctype(&mut self) {
-        if let Some(c) = self.con.next_char() {
-            if !"\t\r\n ".contains(c) {
-                panic!("Error: Missing whitespece before DOCTYPE name");
+    fn current_mut(&mut self) -> &mut Token {
+        self.current.get_or_insert_with(Token::default)
+    }
+
+    fn push_attr_val(&mut self, s: &str) {
+        if let Some(attr) = &mut self.current_attr {
+            s.chars().for_each(|c| attr.push_val(c));
+        }
+    }
+
+    // add the attribute to the tag, unless the tag already has one of the same name
+    fn finish_attr(&mut self) {
+        if let Some(attr) = self.current_attr.take() {
+            let token = self.current_mut();
+            if !attr.name.is_empty() && token.attr(&attr.name).is_none() {
+                token.push_attr(attr);
             }
-        } else {
-            panic!("Error: EOF in DOCTYPE");
         }
+    }
+
+    fn flush_text(&mut self) {
+        if !self.text.is_empty() {
+            let mut token = Token::new(TokenType::Content);
+            token.data = Some(std::mem::take(&mut self.text));
+            self.tokens.push(token);
+        }
+    }
 
-        match self.con.next_until(">") {
-            Some(s) => {
-                self.con.next().unwrap();
-                s.chars().for_each(|c| {
-                    if !c.is_alphanumeric() {
-                        panic!("Error: Invalid character in DOCTYPE name");
-                    }
-                    self.current.as_mut().unwrap().push_name(c);
-                });
+    // emit the current tag or comment and return to the data state
+    // (or to the raw text state after <script> etc.)
+    fn emit_current(&mut self) {
+        self.flush_text();
+        self.state = StateType::Data;
+        if let Some(token) = self.current.take() {
+            if let (TokenType::StartTag, Some(name)) = (&token.token_type, &token.name) {
+                if RAW_TEXT.contains(&name.as_str()) || ESCAPABLE_RAW_TEXT.contains(&name.as_str()) {
+                    self.raw_tag = name.clone();
+                    self.state = StateType::RawText;
+                }
             }
-            None => panic!("Error: EOF in DOCTYPE name"),
+            self.tokens.push(token);
         }
     }
 }
 
+fn is_space(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0c')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum StateType {
     Data,
+    RawText,
     TagOpen,
     EndTagOpen,
     TagName,
     BeforeAttributeName,
     AttributeName,
+    AfterAttributeName,
     BeforeAttributeValue,
-    AttributeValueQuoted,
+    AttributeValueQuoted(char),
     AttributeValueUnquoted,
     AfterAttributeValue,
     SelfClosingStartTag,
     MarkupDeclarationOpen,
     Comment,
+    BogusComment,
     Doctype,
 }
 
 
-struct Token {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
     token_type: TokenType,
     data: Option<String>,
     name: Option<String>,
@@ -347,6 +567,32 @@ impl Token {
         }
     }
 
+    pub fn token_type(&self) -> &TokenType {
+        &self.token_type
+    }
+
+    // tag name (lowercase) or DOCTYPE name
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // text of content and comment tokens
+    pub fn data(&self) -> Option<&str> {
+        self.data.as_deref()
+    }
+
+    pub fn attrs(&self) -> &[Attribute] {
+        &self.attr
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attr.iter().find(|a| a.name == name).map(|a| a.value.as_str())
+    }
+
+    pub fn self_closing(&self) -> bool {
+        self.self_closing
+    }
+
     pub fn set_type(&mut self, token_type: TokenType) {
         self.token_type = token_type;
     }
@@ -374,8 +620,8 @@ impl Token {
     }
 }
 
-#[derive(Debug)]
-enum TokenType {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenType {
     Doctype,
     Comment,
     StartTag,
@@ -383,12 +629,18 @@ enum TokenType {
     Content,
 }
 
-#[derive(Debug)]
-struct Attribute {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
     name: String,
     value: String,
 }
 
+impl Default for Attribute {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Attribute {
 
     pub fn new() -> Self {
@@ -398,6 +650,14 @@ impl Attribute {
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
     pub fn push_name(&mut self, c: char) {
         self.name.push(c);
 
@@ -412,7 +672,7 @@ impl Attribute {
 #[cfg(test)]
 mod test {
     use super::*;
-    
+
     #[test]
     fn test_tokenize() {
         tokenize("<body><h1>Hello</h1></body>");
@@ -446,9 +706,65 @@ mod test {
         println!("{:?}", datas);
         println!("{:?}", attrs);
     }
-}
-
-
 
+    // (type, name or data) of each token
+    fn summary(src: &str) -> Vec<(TokenType, String)> {
+        super::tokenize(src)
+            .into_iter()
+            .map(|t| {
+                let s = t.name().or_else(|| t.data()).unwrap_or_default().to_string();
+                (t.token_type, s)
+            })
+            .collect()
+    }
 
+    #[test]
+    fn test_tags() {
+        use TokenType::*;
+        assert_eq!(
+            summary("<!DOCTYPE html><!-- a -- b --><BODY><h1>Hello</h1></body>"),
+            vec![
+                (Doctype, "html".to_string()),
+                (Comment, " a -- b ".to_string()),
+                (StartTag, "body".to_string()),
+                (StartTag, "h1".to_string()),
+                (Content, "Hello".to_string()),
+                (EndTag, "h1".to_string()),
+                (EndTag, "body".to_string()),
+            ]
+        );
+
+        let tokens = super::tokenize("<a href=/x?a=1&copy=2 title='&lt;&#x41;&#66;&gt;' data-x = \"y\" hidden>");
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].attr("href"), Some("/x?a=1&copy=2"));
+        assert_eq!(tokens[0].attr("title"), Some("<AB>"));
+        assert_eq!(tokens[0].attr("data-x"), Some("y"));
+        assert_eq!(tokens[0].attr("hidden"), Some(""));
+
+        let tokens = super::tokenize("<img src='a.png' alt=\"a b\"/>");
+        assert!(tokens[0].self_closing());
+        assert_eq!(tokens[0].attr("alt"), Some("a b"));
+    }
 
+    #[test]
+    fn test_text() {
+        use TokenType::*;
+        assert_eq!(
+            summary("<script>if (a < b && c) {}</script>&amp; &copy 1 < 2 &bogus; &#0;"),
+            vec![
+                (StartTag, "script".to_string()),
+                (Content, "if (a < b && c) {}".to_string()),
+                (EndTag, "script".to_string()),
+                (Content, "& © 1 < 2 &bogus; \u{fffd}".to_string()),
+            ]
+        );
+        assert_eq!(summary("<title>a <b> &amp; c</title>")[1], (Content, "a <b> & c".to_string()));
+
+        // malformed input never panics
+        for src in ["<", "</", "</>", "<a", "<a href=", "<a href='x", "<!", "<!--", "<!DOCTYPE", "<?xml?>", "<a =b>", "a <3 b", "&#", "&#x;"] {
+            super::tokenize(src);
+        }
+        assert_eq!(summary("a <3 b"), vec![(Content, "a <3 b".to_string())]);
+        assert_eq!(summary("x</"), vec![(Content, "x</".to_string())]);
+    }
+}
@@ -0,0 +1,347 @@
+// JSON generator
+//
+// Dumps the outline of the document: sections nested by their headings, the blocks of text in
+// each, and the links.
+//
+// ```
+// {
+//   "title": "Manual",
+//   "blocks": [],
+//   "sections": [
+//     {
+//       "level": 1,
+//       "heading": "Manual",
+//       "blocks": [
+//         {
+//           "type": "paragraph",
+//           "text": "Read the install guide first.",
+//           "links": [1]
+//         }
+//       ],
+//       "sections": []
+//     }
+//   ],
+//   "links": [
+//     "/install.html"
+//   ]
+// }
+// ```
+//
+// Blocks are `paragraph`, `quote` (text inside a <blockquote>), `item`, `code` (with its
+// `language`, or null), `rule` and
+// `table` (with `rows` of cell texts instead of `text`); `links` gives the numbers of the links in
+// the text, `[n]` being the n-th target of the document.
+
+use std::fmt::{self, Write};
+use crate::renderer::backend::{self, collapse, Block, Inline, Renderer};
+use crate::renderer::dom::Element;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Json {
+    Null,
+    Number(usize),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let pad = "  ".repeat(depth + 1);
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::String(s) => write_string(f, s),
+            Self::Array(items) if items.is_empty() => write!(f, "[]"),
+            // arrays of numbers stay on one line
+            Self::Array(items) if items.iter().all(|i| matches!(i, Self::Number(_))) => {
+                let items = items.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Self::Array(items) => {
+                writeln!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    f.write_str(&pad)?;
+                    item.write(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < items.len() { "," } else { "" })?;
+                }
+                write!(f, "{}]", "  ".repeat(depth))
+            }
+            Self::Object(fields) => {
+                writeln!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    f.write_str(&pad)?;
+                    write_string(f, name)?;
+                    f.write_str(": ")?;
+                    value.write(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < fields.len() { "," } else { "" })?;
+                }
+                write!(f, "{}}}", "  ".repeat(depth))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+// `s` quoted, with `"`, `\` and control characters escaped
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+// the outline of the document as JSON
+pub fn generate(document: &Element) -> String {
+    let gen = Generator {
+        sections: vec![Section::default()],
+        ..Generator::default()
+    };
+    backend::render(document, gen)
+}
+
+#[derive(Default)]
+struct Section {
+    level: usize,
+    heading: String,
+    blocks: Vec<Json>,
+    sections: Vec<Json>,
+}
+
+impl Section {
+    fn into_json(self) -> Json {
+        Json::Object(vec![
+            ("level", Json::Number(self.level)),
+            ("heading", Json::String(self.heading)),
+            ("blocks", Json::Array(self.blocks)),
+            ("sections", Json::Array(self.sections)),
+        ])
+    }
+}
+
+#[derive(Default)]
+struct Generator {
+    // open sections, the document first
+    sections: Vec<Section>,
+    // open blocks
+    blocks: Vec<Block>,
+    // text of the current block, and its links
+    text: String,
+    links: Vec<usize>,
+    // rows of the open table
+    table: Option<Vec<Vec<Json>>>,
+}
+
+impl Renderer for Generator {
+    type Output = String;
+
+    fn start(&mut self, block: &Block) {
+        // the text of the cells is kept until the end of the table
+        match block {
+            Block::Row => self.table.get_or_insert_with(Vec::new).push(vec![]),
+            Block::Cell { .. } => self.text.clear(),
+            _ => self.flush(),
+        }
+        match block {
+            Block::Heading(level) => {
+                // a heading closes the sections of its level and below
+                while self.sections.len() > 1 && self.sections.last().is_some_and(|s| s.level >= *level) {
+                    self.close_section();
+                }
+                self.sections.push(Section {
+                    level: *level,
+                    ..Section::default()
+                });
+            }
+            Block::Rule => self.push_block("rule", Json::Null),
+            Block::Code(text, language) => {
+                let text = text.strip_suffix('\n').unwrap_or(text).to_string();
+                self.push_block("code", Json::String(text));
+                if let Some(Json::Object(fields)) = self.sections.last_mut().and_then(|s| s.blocks.last_mut()) {
+                    fields.push(("language", language.clone().map_or(Json::Null, Json::String)));
+                }
+            }
+            Block::Table => self.table = Some(vec![]),
+            _ => {}
+        }
+        self.blocks.push(block.clone());
+    }
+
+    fn end(&mut self, block: &Block) {
+        match block {
+            Block::Heading(_) => {
+                let heading = collapse(&std::mem::take(&mut self.text));
+                self.links.clear();
+                if let Some(section) = self.sections.last_mut() {
+                    section.heading = heading;
+                }
+            }
+            Block::Row => {}
+            Block::Cell { .. } => {
+                let text = collapse(&std::mem::take(&mut self.text));
+                if let Some(row) = self.table.as_mut().and_then(|t| t.last_mut()) {
+                    row.push(Json::String(text));
+                }
+            }
+            Block::Table => {
+                let rows = self.table.take().unwrap_or_default();
+                let links = Json::Array(std::mem::take(&mut self.links).into_iter().map(Json::Number).collect());
+                self.push_block("table", Json::Null);
+                if let Some(Json::Object(fields)) = self.sections.last_mut().and_then(|s| s.blocks.last_mut()) {
+                    fields.push(("rows", Json::Array(rows.into_iter().map(Json::Array).collect())));
+                    fields.push(("links", links));
+                }
+            }
+            _ => self.flush(),
+        }
+        self.blocks.pop();
+    }
+
+    fn text(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    fn start_inline(&mut self, inline: &Inline) {
+        if let Inline::Link(n, _) = inline {
+            self.links.push(*n);
+        }
+    }
+
+    fn finish(mut self, title: Option<String>, links: Vec<String>) -> String {
+        self.flush();
+        while self.sections.len() > 1 {
+            self.close_section();
+        }
+        let root = self.sections.pop().unwrap_or_default();
+        let json = Json::Object(vec![
+            ("title", title.map_or(Json::Null, Json::String)),
+            ("blocks", Json::Array(root.blocks)),
+            ("sections", Json::Array(root.sections)),
+            ("links", Json::Array(links.into_iter().map(Json::String).collect())),
+        ]);
+        json.to_string()
+    }
+}
+
+impl Generator {
+    // end the block of text going on
+    fn flush(&mut self) {
+        let text = collapse(&std::mem::take(&mut self.text));
+        let links = std::mem::take(&mut self.links);
+        if text.is_empty() {
+            return;
+        }
+        // named after the innermost container
+        let kind = self
+            .blocks
+            .iter()
+            .rev()
+            .find_map(|b| match b {
+                Block::Item(_) => Some("item"),
+                Block::Quote => Some("quote"),
+                _ => None,
+            })
+            .unwrap_or("paragraph");
+        let links = Json::Array(links.into_iter().map(Json::Number).collect());
+        self.push_block(kind, Json::String(text));
+        if let Some(Json::Object(fields)) = self.sections.last_mut().and_then(|s| s.blocks.last_mut()) {
+            fields.push(("links", links));
+        }
+    }
+
+    fn push_block(&mut self, kind: &str, text: Json) {
+        let mut fields = vec![("type", Json::String(kind.to_string()))];
+        if text != Json::Null {
+            fields.push(("text", text));
+        }
+        if let Some(section) = self.sections.last_mut() {
+            section.blocks.push(Json::Object(fields));
+        }
+    }
+
+    fn close_section(&mut self) {
+        if let Some(section) = self.sections.pop() {
+            if let Some(parent) = self.sections.last_mut() {
+                parent.sections.push(section.into_json());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::dom;
+
+    #[test]
+    fn test_generate() {
+        let html = "<title>Doc</title><p>Intro</p><h1>One</h1><p>See <a href=a.html>a</a>.</p>\
+                    <h2>Two</h2><ul><li>item</ul><h1>Three \"3\"</h1><hr>";
+        assert_eq!(
+            generate(&dom::parse(html)),
+            r#"{
+  "title": "Doc",
+  "blocks": [
+    {
+      "type": "paragraph",
+      "text": "Intro",
+      "links": []
+    }
+  ],
+  "sections": [
+    {
+      "level": 1,
+      "heading": "One",
+      "blocks": [
+        {
+          "type": "paragraph",
+          "text": "See a.",
+          "links": [1]
+        }
+      ],
+      "sections": [
+        {
+          "level": 2,
+          "heading": "Two",
+          "blocks": [
+            {
+              "type": "item",
+              "text": "item",
+              "links": []
+            }
+          ],
+          "sections": []
+        }
+      ]
+    },
+    {
+      "level": 1,
+      "heading": "Three \"3\"",
+      "blocks": [
+        {
+          "type": "rule"
+        }
+      ],
+      "sections": []
+    }
+  ],
+  "links": [
+    "a.html"
+  ]
+}"#
+        );
+    }
+}
@@ -0,0 +1,296 @@
+// Layout: Markdown lines wrapped to a width in terminal cells
+//
+// ```
+// > A quote long enough to be         - A list item long enough to
+// > wrapped keeps its marker.           be wrapped hangs under its text.
+// ```
+//
+// Lines break at spaces, and also between CJK characters, which take two cells
+// (see `utils/width.rs`). Kinsoku shori: no line starts with closing brackets and punctuation
+// like `。、」` or small kana, and none ends with opening brackets like `「`; the character
+// before is moved down instead. Words longer than the width are cut.
+//
+// Fenced code blocks, table rows and link references are left as they are.
+
+use crate::utils::width::{char_width, is_wide, width};
+
+// never at the start of a line
+const NO_START: &str = "、。，．,.:;?!)]}）］｝〕〉》」』】〙〗〟’”｠»・：；？！ーｰ‐゠–〜～…‥ヽヾゝゞ々〻\
+                        ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶㇰㇱㇲㇳㇴㇵㇶㇷㇸㇹㇺㇻㇼㇽㇾㇿ";
+// never at the end of a line
+const NO_END: &str = "([{（［｛〔〈《「『【〘〖〝‘“｟«";
+
+// a row of the output: `indent` followed by the characters `start..end` of the line `line`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub line: usize,
+    pub indent: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+// the rows of `lines` wrapped to `width` cells
+pub fn rows(lines: &[String], width: usize) -> Vec<Row> {
+    let mut rows = vec![];
+    let mut fence: Option<String> = None;
+    for (line, text) in lines.iter().enumerate() {
+        let chars = text.chars().collect::<Vec<_>>();
+        let whole = Row {
+            line,
+            indent: String::new(),
+            start: 0,
+            end: chars.len(),
+        };
+
+        // inside ``` ... ``` (or ~~~), the closing fence being at least as long
+        let trimmed = text.trim_start();
+        let marker = fence_marker(trimmed);
+        match &fence {
+            Some(open) => {
+                if marker.is_some_and(|m| m.starts_with(open.as_str()) && trimmed.trim_end() == m) {
+                    fence = None;
+                }
+                rows.push(whole);
+                continue;
+            }
+            None if marker.is_some() => {
+                fence = marker.map(|m| m.to_string());
+                rows.push(whole);
+                continue;
+            }
+            None => {}
+        }
+
+        let (prefix, indent) = prefix(text);
+        let body = &text[prefix..];
+        if body.starts_with('|') || is_reference(body) {
+            rows.push(whole);
+            continue;
+        }
+
+        let keep = text[..prefix].chars().count();
+        let rest = match width.checked_sub(self::width(&indent)) {
+            // a deep indent on a narrow screen is dropped
+            Some(rest) if rest >= width / 2 && rest > 0 => rest,
+            _ => width,
+        };
+        let indent = if rest == width { String::new() } else { indent };
+        for (i, (start, end)) in wrap_from(&chars, keep, width, rest).into_iter().enumerate() {
+            rows.push(Row {
+                line,
+                indent: if i == 0 { String::new() } else { indent.clone() },
+                start,
+                end,
+            });
+        }
+    }
+    rows
+}
+
+// `lines` wrapped to `width` cells
+pub fn layout(lines: &[String], width: usize) -> Vec<String> {
+    rows(lines, width)
+        .into_iter()
+        .map(|row| {
+            let text = lines[row.line].chars().skip(row.start).take(row.end - row.start).collect::<String>();
+            format!("{}{}", row.indent, text)
+        })
+        .collect()
+}
+
+// character ranges of the lines `text` is wrapped into (spaces at the breaks are left out)
+pub fn wrap(text: &str, width: usize) -> Vec<(usize, usize)> {
+    wrap_from(&text.chars().collect::<Vec<_>>(), 0, width, width)
+}
+
+// character ranges of `text` cut at `width` cells, wherever that is
+pub fn cut(text: &str, width: usize) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let (mut start, mut used) = (0, 0);
+    for (i, c) in text.chars().enumerate() {
+        let w = char_width(c);
+        if used + w > width && i > start {
+            ranges.push((start, i));
+            start = i;
+            used = 0;
+        }
+        used += w;
+    }
+    ranges.push((start, text.chars().count()));
+    ranges
+}
+
+// `wrap` with `first` cells for the first line and `rest` for the others, not breaking within the
+// first `keep` characters (a list marker)
+fn wrap_from(chars: &[char], keep: usize, first: usize, rest: usize) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    let mut room = first;
+    loop {
+        // the end of the longest run fitting in `room`
+        let mut end = start;
+        let mut used = 0;
+        while end < chars.len() && used + char_width(chars[end]) <= room {
+            used += char_width(chars[end]);
+            end += 1;
+        }
+        if end == chars.len() {
+            ranges.push((start, end));
+            return ranges;
+        }
+
+        let min = if ranges.is_empty() { keep.max(start + 1) } else { start + 1 };
+        let cut = (min..=end).rev().find(|&b| can_break(chars, b));
+        // a word longer than the line, or a line of characters that cannot be broken
+        let cut = cut.unwrap_or_else(|| end.max(start + 1));
+        let mut line_end = cut;
+        while line_end > start && chars[line_end - 1] == ' ' {
+            line_end -= 1;
+        }
+        ranges.push((start, line_end));
+
+        start = cut;
+        while start < chars.len() && chars[start] == ' ' {
+            start += 1;
+        }
+        if start == chars.len() {
+            return ranges;
+        }
+        room = rest;
+    }
+}
+
+// whether a line can end before `chars[b]`
+fn can_break(chars: &[char], b: usize) -> bool {
+    let (before, after) = (chars[b - 1], chars[b]);
+    if after == ' ' {
+        return before != ' ';
+    }
+    if before == ' ' {
+        return !NO_START.contains(after);
+    }
+    if NO_START.contains(after) || NO_END.contains(before) {
+        return false;
+    }
+    is_wide(before) || is_wide(after)
+}
+
+// the length in bytes of the quote and list markers starting `line`, and the indent of the lines
+// wrapped from it
+//
+// ```
+// > > - item    ->  (8, "> >   ")
+// 12. item      ->  (4, "    ")
+// ```
+fn prefix(line: &str) -> (usize, String) {
+    let mut quote = 0;
+    let bytes = line.as_bytes();
+    while bytes.get(quote) == Some(&b'>') {
+        quote += 1;
+        if bytes.get(quote) == Some(&b' ') {
+            quote += 1;
+        }
+    }
+    let rest = &line[quote..];
+    let spaces = rest.len() - rest.trim_start_matches(' ').len();
+    let rest = &rest[spaces..];
+
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let marker = if rest.starts_with("- ") || rest.starts_with("* ") || rest.starts_with("+ ") {
+        2
+    } else if (1..=9).contains(&digits) && (rest[digits..].starts_with(". ") || rest[digits..].starts_with(") ")) {
+        digits + 2
+    } else {
+        0
+    };
+    let end = quote + spaces + marker;
+    let mut indent = line[..quote].to_string();
+    if !indent.is_empty() && !indent.ends_with(' ') {
+        indent.push(' ');
+    }
+    indent.push_str(&" ".repeat(spaces + marker));
+    (end, indent)
+}
+
+// "```" or "~~~" (or longer) starting a line
+fn fence_marker(line: &str) -> Option<&str> {
+    for c in ['`', '~'] {
+        let n = line.len() - line.trim_start_matches(c).len();
+        if n >= 3 {
+            return Some(&line[..n]);
+        }
+    }
+    None
+}
+
+// `[12]: http://...`
+fn is_reference(line: &str) -> bool {
+    match line.strip_prefix('[').and_then(|s| s.split_once("]: ")) {
+        Some((n, _)) => !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(src: &str) -> Vec<String> {
+        src.lines().map(|l| l.to_string()).collect()
+    }
+
+    fn pieces(text: &str, width: usize) -> Vec<String> {
+        let chars = text.chars().collect::<Vec<_>>();
+        wrap(text, width).into_iter().map(|(s, e)| chars[s..e].iter().collect()).collect()
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(pieces("a quick brown fox", 7), vec!["a quick", "brown", "fox"]);
+        assert_eq!(pieces("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(pieces("", 4), vec![""]);
+        assert_eq!(pieces("a  b", 1), vec!["a", "b"]);
+
+        // two cells each, breaking anywhere between them
+        assert_eq!(pieces("日本語の文章です", 7), vec!["日本語", "の文章", "です"]);
+        assert_eq!(pieces("Rustの本", 5), vec!["Rust", "の本"]);
+        // `。` and `」` do not start a line, `「` does not end one
+        assert_eq!(pieces("今日は晴れ。明日は雨", 10), vec!["今日は晴", "れ。明日は", "雨"]);
+        assert_eq!(pieces("彼は「はい」と言った", 6), vec!["彼は", "「は", "い」と", "言った"]);
+        assert_eq!(pieces("「「「「「", 4), vec!["「「", "「「", "「"]);
+        assert_eq!(cut("日本語abc", 3), vec![(0, 1), (1, 2), (2, 4), (4, 6)]);
+    }
+
+    #[test]
+    fn test_layout() {
+        let src = lines(
+            "# A heading that wraps\n\
+             > > quoted text that wraps\n\
+             \x20\x20- an item in a nested list\n\
+             10. a numbered item here\n\
+             ```\n\
+             code that is not wrapped at all\n\
+             ```\n\
+             [1]: http://example.com/a/long/path",
+        );
+        let expected = lines(
+            "# A heading that\n\
+             wraps\n\
+             > > quoted text\n\
+             > > that wraps\n\
+             \x20\x20- an item in a\n\
+             \x20\x20\x20\x20nested list\n\
+             10. a numbered\n\
+             \x20\x20\x20\x20item here\n\
+             ```\n\
+             code that is not wrapped at all\n\
+             ```\n\
+             [1]: http://example.com/a/long/path",
+        );
+        assert_eq!(layout(&src, 16), expected);
+
+        // the indent is dropped when it leaves too little room
+        assert_eq!(layout(&lines("> > > > a b c d"), 10), lines("> > > > a\nb c d"));
+        assert_eq!(rows(&lines("- 日本語"), 4)[1], Row { line: 0, indent: "  ".to_string(), start: 3, end: 4 });
+    }
+}
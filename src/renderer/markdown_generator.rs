@@ -1,4 +1,550 @@
+// Markdown generator
+//
+// Renders the document tree as Markdown. Links are numbered in document order and written as
+// reference links, so that they can be followed by number:
+//
+// ```
+// # Manual
+//
+// Read the [install guide][1] first.
+//
+// - [Download][2]
+// - [FAQ][3]
+//
+// [1]: /install.html
+// [2]: https://example.com/download
+// [3]: faq.html
+// ```
+//
+// Link targets are kept as they are written in the page; resolve them with `URL::join`.
+//
+// <pre> is a fenced code block, its info string the language named by a class (`language-rust`).
+// Inline elements get their marks: `*em*`, `**strong**`, `~~del~~`, and code in as many backticks
+// as it takes (``` `` a`b `` ```). <sub> and <sup> become Unicode characters when they can
+// (`H₂O`, `x²`), and stay as HTML otherwise; <br> is a hard break, a `\` at the end of the line.
+// Marks of one kind next to each other are one run (`*ab*`), and the characters of marks in the
+// text of the page are escaped (`\*`), as is what would start a list or a heading (`1\. a`).
 
+use std::fmt;
+use crate::renderer::backend::{self, Block, Flow, Inline, Layout, Renderer};
+use crate::renderer::dom::{self, Element};
+use crate::renderer::table;
 
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Page {
+    title: Option<String>,
+    lines: Vec<String>,
+    // link targets; `[n]` refers to `links[n - 1]`
+    links: Vec<String>,
+}
 
+impl Page {
+    pub fn from_html(html: &str) -> Self {
+        generate(&dom::parse(html))
+    }
 
+    // a text document shown as it is
+    pub fn from_text(text: &str) -> Self {
+        Self {
+            title: None,
+            lines: text.lines().map(|l| l.to_string()).collect(),
+            links: vec![],
+        }
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn links(&self) -> &[String] {
+        &self.links
+    }
+
+    // target of the link numbered `n` (from 1)
+    pub fn link(&self, n: usize) -> Option<&str> {
+        n.checked_sub(1).and_then(|i| self.links.get(i)).map(|s| s.as_str())
+    }
+
+    // the body followed by the link references
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = self.lines.clone();
+        if !self.links.is_empty() {
+            lines.push(String::new());
+            for (i, link) in self.links.iter().enumerate() {
+                lines.push(format!("[{}]: {}", i + 1, link));
+            }
+        }
+        lines
+    }
+}
+
+impl fmt::Display for Page {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in self.lines() {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn generate(document: &Element) -> Page {
+    backend::render(document, Generator::default())
+}
+
+#[derive(Default)]
+struct Generator {
+    lines: Vec<String>,
+    flow: Flow,
+    // open inline elements, and where their text starts
+    marks: Vec<(usize, Inline)>,
+    // the last marks written: where they start and end, and their text
+    last: Option<(usize, usize, Inline, String)>,
+}
+
+impl Renderer for Generator {
+    type Output = Page;
+
+    fn start(&mut self, block: &Block) {
+        self.last = None;
+        self.enter(block);
+        match block {
+            Block::Rule => {
+                self.push_line("---".to_string());
+                self.flow.gap = true;
+            }
+            // fenced, with more backticks than in any run in the code
+            Block::Code(text, language) => {
+                let fence = "`".repeat(backticks(text).max(2) + 1);
+                // the marker of a list item on the opening fence
+                let start = self.flow.lead();
+                self.push_line(format!("{}{}{}", start, fence, language.as_deref().unwrap_or_default()));
+                let lead = self.flow.indent();
+                for line in text.lines() {
+                    // blank lines in a quote keep the `>`
+                    let lead = if line.is_empty() { lead.trim_end() } else { &lead };
+                    self.push_line(format!("{}{}", lead, line));
+                }
+                self.push_line(format!("{}{}", lead, fence));
+                self.flow.gap = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, block: &Block) {
+        self.last = None;
+        match block {
+            Block::Heading(level) => {
+                self.flush_with(&format!("{} ", "#".repeat(*level)));
+                self.flow.gap = true;
+            }
+            // a pipe table, or a grid in a code block if cells span
+            Block::Table => {
+                let mut rows = self.flow.rows();
+                rows.retain(|row| !row.is_empty());
+                let lines = match table::gfm(&rows) {
+                    Some(lines) => lines,
+                    None if rows.is_empty() => vec![],
+                    None => {
+                        let mut lines = vec!["```".to_string()];
+                        lines.extend(table::grid(&rows, usize::MAX));
+                        lines.push("```".to_string());
+                        lines
+                    }
+                };
+                // the marker of a list item on the first line
+                for (i, line) in lines.iter().enumerate() {
+                    let lead = if i == 0 { self.flow.lead() } else { self.flow.indent() };
+                    self.push_line(format!("{}{}", lead, line));
+                }
+                self.flow.gap = true;
+            }
+            _ => self.leave(block),
+        }
+    }
+
+    // marks in the text escaped, but in code
+    fn text(&mut self, s: &str) {
+        if self.marks.iter().any(|(_, open)| *open == Inline::Code) {
+            self.flow.text(s);
+        } else {
+            self.flow.text(&escape(s));
+        }
+    }
+
+    fn start_inline(&mut self, inline: &Inline) {
+        self.marks.push((self.flow.inline.len(), inline.clone()));
+    }
+
+    // the text between marks (`[text][n]`, `*text*` ...), the white space around it outside
+    fn end_inline(&mut self, inline: &Inline) {
+        let start = self.marks.pop().map_or(0, |(start, _)| start);
+        // marks would be shown as they are in code, and `**` is not `*` twice
+        let link = matches!(inline, Inline::Link(..));
+        if !link && self.marks.iter().any(|(_, open)| *open == Inline::Code || open == inline) {
+            return;
+        }
+        let start = start.min(self.flow.inline.len());
+        let label = self.flow.inline.split_off(start);
+        let mut text = label.trim().to_string();
+        let space = &label[..label.len() - label.trim_start().len()];
+        // right after the same marks, one run of them: `*ab*`, not `*a**b*`
+        let merge = matches!(inline, Inline::Emphasis | Inline::Strong | Inline::Strike | Inline::Code);
+        match self.last.take() {
+            Some((from, end, last, before)) if merge && end == start && space.is_empty() && last == *inline => {
+                self.flow.inline.truncate(from);
+                text = before + &text;
+            }
+            _ => self.edge(space),
+        }
+        let from = self.flow.inline.len();
+        match inline {
+            Inline::Link(n, _) => {
+                let label = if text.is_empty() { "link" } else { &text };
+                self.flow.inline.push_str(&format!("[{}][{}]", label, n));
+            }
+            _ if text.is_empty() => {}
+            inline => self.flow.inline.push_str(&mark(inline, &text)),
+        }
+        self.last = Some((from, self.flow.inline.len(), inline.clone(), text));
+        self.edge(&label[label.trim_end().len()..]);
+    }
+
+    // a hard break, kept as a newline until the block is written out
+    fn line_break(&mut self) {
+        let inline = &mut self.flow.inline;
+        inline.truncate(inline.trim_end_matches(' ').len());
+        if !inline.is_empty() && !inline.ends_with('\n') {
+            inline.push('\n');
+        }
+    }
+
+    fn image(&mut self, alt: &str, src: &str) {
+        self.flow.inline.push_str(&format!("![{}]({})", escape(alt), src));
+    }
+
+    fn finish(mut self, title: Option<String>, links: Vec<String>) -> Page {
+        self.flush();
+        Page {
+            title,
+            lines: self.lines,
+            links,
+        }
+    }
+}
+
+impl Layout for Generator {
+    fn flow(&mut self) -> &mut Flow {
+        &mut self.flow
+    }
+
+    fn flush(&mut self) {
+        self.flush_with("");
+    }
+
+    fn quote(&self) -> &'static str {
+        "> "
+    }
+
+    fn marker(&self, number: Option<usize>) -> String {
+        match number {
+            Some(n) => format!("{}. ", n),
+            None => "- ".to_string(),
+        }
+    }
+}
+
+impl Generator {
+    // white space cut from the edge of an inline element: a break or a space
+    fn edge(&mut self, space: &str) {
+        if space.contains('\n') {
+            self.line_break();
+        } else if !space.is_empty() {
+            self.flow.space();
+        }
+    }
+
+    // write out the current block with `head` (`## ` etc.) in front of it, a heading on one line
+    fn flush_with(&mut self, head: &str) {
+        let mut text = match self.flow.take() {
+            Some(text) => text,
+            None => return,
+        };
+        if !head.is_empty() {
+            text = text.replace('\n', " ");
+        }
+        let lead = self.flow.lead();
+        let indent = self.flow.indent();
+        let count = text.split('\n').count();
+        for (i, line) in text.split('\n').enumerate() {
+            let lead = if i == 0 { format!("{}{}", lead, head) } else { indent.clone() };
+            let line = if head.is_empty() { escape_start(line) } else { line.to_string() };
+            let end = if i + 1 < count { "\\" } else { "" };
+            self.push_line(format!("{}{}{}", lead, line, end));
+        }
+    }
+
+    fn push_line(&mut self, line: String) {
+        if std::mem::take(&mut self.flow.gap) && !self.lines.is_empty() {
+            // blank lines inside a quote keep the `>`
+            let prefix = self.flow.indent().trim_end().to_string();
+            let inside = self.lines.last().is_some_and(|last| last.starts_with(&prefix));
+            self.lines.push(if inside { prefix } else { String::new() });
+        }
+        self.lines.push(line);
+    }
+}
+
+// `text` between the marks of `inline` (not a link)
+fn mark(inline: &Inline, text: &str) -> String {
+    match inline {
+        Inline::Emphasis => format!("*{}*", text),
+        Inline::Strong => format!("**{}**", text),
+        Inline::Strike => format!("~~{}~~", text),
+        Inline::Code => code_span(text),
+        Inline::Subscript => script(text, SUBSCRIPTS).unwrap_or_else(|| format!("<sub>{}</sub>", text)),
+        Inline::Superscript => script(text, SUPERSCRIPTS).unwrap_or_else(|| format!("<sup>{}</sup>", text)),
+        // no mark for these
+        Inline::Underline | Inline::Link(..) => text.to_string(),
+    }
+}
+
+// `text` with the characters taken for marks, HTML or entities escaped: `a\*b\*`, `\<b>`, `\&lt;`
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for (i, c) in text.char_indices() {
+        if "\\`*_[]~<".contains(c) || (c == '&' && entity_like(&text[i + 1..])) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// whether `rest` (after a `&`) would be read as an entity: `lt;`, `#60;` or `#x3c;`
+fn entity_like(rest: &str) -> bool {
+    let (digits, rest): (fn(char) -> bool, &str) = match rest.strip_prefix('#') {
+        Some(hex) if hex.starts_with(['x', 'X']) => (|c| c.is_ascii_hexdigit(), &hex[1..]),
+        Some(dec) => (|c| c.is_ascii_digit(), dec),
+        None => (|c| c.is_ascii_alphanumeric(), rest),
+    };
+    let name = rest.len() - rest.trim_start_matches(digits).len();
+    name > 0 && rest[name..].starts_with(';')
+}
+
+// `line` with what would start a heading, a quote or a list escaped
+//
+// ```
+// # a  ->  \# a      - a  ->  \- a      1. a  ->  1\. a      ---  ->  \---
+// ```
+fn escape_start(line: &str) -> String {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    let spaced = matches!(rest.chars().nth(1), None | Some(' '));
+    let at = match rest.chars().next() {
+        Some('.') | Some(')') if (1..10).contains(&digits) && spaced => digits,
+        _ if digits > 0 => return line.to_string(),
+        Some('#') | Some('>') => 0,
+        Some('-') | Some('+') if spaced => 0,
+        Some(c @ '-') | Some(c @ '=') if line.trim_end().chars().all(|d| d == c) => 0,
+        _ => return line.to_string(),
+    };
+    format!("{}\\{}", &line[..at], &line[at..])
+}
+
+// `text` in backticks, more of them than in any run of backticks in it
+fn code_span(text: &str) -> String {
+    let fence = "`".repeat(backticks(text) + 1);
+    let pad = if text.starts_with('`') || text.ends_with('`') { " " } else { "" };
+    format!("{}{}{}{}{}", fence, pad, text, pad, fence)
+}
+
+// the longest run of backticks in `text`
+fn backticks(text: &str) -> usize {
+    text.split(|c| c != '`').map(|run| run.len()).max().unwrap_or(0)
+}
+
+// characters that have a subscript or superscript form, and those forms
+const SUBSCRIPTS: (&str, &str) = ("0123456789+-=()", "₀₁₂₃₄₅₆₇₈₉₊₋₌₍₎");
+const SUPERSCRIPTS: (&str, &str) = ("0123456789+-=()in", "⁰¹²³⁴⁵⁶⁷⁸⁹⁺⁻⁼⁽⁾ⁱⁿ");
+
+// `text` in the forms of `scripts`, if it has them all
+fn script(text: &str, (from, to): (&str, &str)) -> Option<String> {
+    text.chars().map(|c| from.chars().position(|f| f == c).and_then(|i| to.chars().nth(i))).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let html = "\
+<!DOCTYPE html>
+<html>
+<head><title> Manual </title><style>p { color: red }</style></head>
+<body>
+  <h1>Manual</h1>
+  <p>Read the
+     <a href=\"/install.html\"> install guide </a> first.</p>
+  <ul>
+    <li><a href=\"https://example.com/download\">Download</a>
+    <li>More
+      <ol start=3><li>one<li>two</ol>
+  </ul>
+  <blockquote><p>quoted</p><p>twice</p></blockquote>
+  <hr>
+  <p><a href=\"faq.html\"><img src=\"q.png\" alt=\"\"></a></p>
+</body>
+</html>";
+        let page = Page::from_html(html);
+        assert_eq!(page.title(), Some("Manual"));
+        assert_eq!(page.link(2), Some("https://example.com/download"));
+        assert_eq!(page.link(0), None);
+        assert_eq!(
+            page.to_string(),
+            "\
+# Manual
+
+Read the [install guide][1] first.
+
+- [Download][2]
+- More
+  3. one
+  4. two
+
+> quoted
+>
+> twice
+
+---
+
+[link][3]
+
+[1]: /install.html
+[2]: https://example.com/download
+[3]: faq.html
+"
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        let html = "<p>An <em> emphasis </em>,<b>strong <i>and</i></b> <del>gone</del>\
+                    <code>a `b`</code> <kbd>`</kbd> H<sub>2</sub>O x<sup>2</sup> x<sup>y</sup>\
+                    <code><em>raw</em></code><em><i>once</i></em><em> </em>a<br>  b<br><br></p>\
+                    <blockquote>q<br>r</blockquote><h2>one<br>line</h2>";
+        assert_eq!(
+            Page::from_html(html).to_string(),
+            "\
+An *emphasis* ,**strong *and*** ~~gone~~`` a `b` `` `` ` `` H₂O x² x<sup>y</sup>`raw`*once* a\\
+b
+
+> q\\
+> r
+
+## one line
+"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        let html = "<p>a *star* _u_ [x] \\ ~t~ <em>a*b</em> <code>*raw*</code></p><p>1. not list</p><p>- x</p>\
+                    <p>&amp;lt;b&amp;gt;x&amp;lt;/b&amp;gt; &lt;i&gt; a &amp; b &amp;#60; &amp;#x3c; &amp;c</p>\
+                    <p># no</p><p>---</p><p>2024. year -5</p><h2># one</h2>\
+                    <p><em>a</em><em>b</em> <b>c</b><b>d</b><em>e</em><b>f</b> <code>`</code><kbd>x</kbd></p>\
+                    <table><tr><td><em>a</em><td><em>b</em></table>";
+        assert_eq!(
+            Page::from_html(html).to_string(),
+            "\
+a \\*star\\* \\_u\\_ \\[x\\] \\\\ \\~t\\~ *a\\*b* `*raw*`
+
+1\\. not list
+
+\\- x
+
+\\&lt;b\\&gt;x\\&lt;/b\\&gt; \\<i> a & b \\&#60; \\&#x3c; &c
+
+\\# no
+
+\\---
+
+2024\\. year -5
+
+## # one
+
+*ab* **cd***e***f** `` `x ``
+
+| *a* | *b* |
+| --- | --- |
+"
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        let html = "<pre class='highlight-source-sh'><code class='language-rust'>\nfn main() {\n\n\tlet s = \"```\";\n}\n</code></pre>\
+                    <blockquote><pre>  a\n\nb</pre></blockquote><ol><li><pre>code</pre><li>two</ol>";
+        assert_eq!(
+            Page::from_html(html).to_string(),
+            "\
+````rust
+fn main() {
+
+\tlet s = \"```\";
+}
+````
+
+> ```
+>   a
+>
+> b
+> ```
+
+1. ```
+   code
+   ```
+
+2. two
+"
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let html = "<table><tr><th>a<th><em>b</em></tr><tr><td>1<td>2<br>3</table>\
+                    <table><tr><td colspan=2>ab<tr><td>1<td>2</table>\
+                    <ol><li><table><tr><th>x<tr><td>y</table><li>two</ol>";
+        assert_eq!(
+            Page::from_html(html).to_string(),
+            "\
+| a   | *b* |
+| --- | --- |
+| 1   | 2 3 |
+
+```
+┌───────┐
+│ ab    │
+├───┬───┤
+│ 1 │ 2 │
+└───┴───┘
+```
+
+1. | x   |
+   | --- |
+   | y   |
+
+2. two
+"
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        let page = Page::from_text("plain\n\ttext\n");
+        assert_eq!(page.lines(), vec!["plain", "\ttext"]);
+        assert_eq!(page.title(), None);
+        assert!(page.links().is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+// Plain text generator
+//
+// Renders the document tree as text, like `lynx -dump`: no marks but the numbers of the links in
+// front of them, and the link targets listed at the end.
+//
+// ```
+//    Manual
+//
+//    Read the [1]install guide first.
+//
+//      * [2]Download
+//      * More
+//
+// References
+//
+//    1. /install.html
+//    2. https://example.com/download
+// ```
+
+use crate::renderer::backend::{self, Block, Flow, Inline, Layout, Renderer};
+use crate::renderer::dom::Element;
+use crate::renderer::layout;
+use crate::renderer::table;
+use crate::utils::width;
+
+// left margin of the body
+const MARGIN: &str = "   ";
+
+// a line of text before it is wrapped
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Line {
+    // margin and list markers before the first row, and before the others
+    lead: String,
+    indent: String,
+    text: String,
+    // shown as it is (code blocks, rules)
+    pre: bool,
+}
+
+// the document as lines of at most `width` cells (longer code lines aside)
+pub fn generate(document: &Element, width: usize) -> Vec<String> {
+    let mut gen = Generator {
+        width: width.max(1),
+        ..Generator::default()
+    };
+    gen.flow.prefixes.push(MARGIN.to_string());
+    backend::render(document, gen)
+}
+
+#[derive(Default)]
+struct Generator {
+    width: usize,
+    lines: Vec<Line>,
+    // the margin first in the prefixes
+    flow: Flow,
+}
+
+impl Renderer for Generator {
+    type Output = Vec<String>;
+
+    fn start(&mut self, block: &Block) {
+        self.enter(block);
+        match block {
+            Block::Rule => {
+                let lead = self.flow.indent();
+                let rule = "_".repeat(self.width.saturating_sub(width::width(&lead)).max(1));
+                self.push_line(Line {
+                    indent: lead.clone(),
+                    lead,
+                    text: rule,
+                    pre: true,
+                });
+                self.flow.gap = true;
+            }
+            Block::Code(text, _) => {
+                for (i, line) in text.lines().enumerate() {
+                    let lead = if i == 0 { self.flow.lead() } else { self.flow.indent() };
+                    self.push_line(Line {
+                        lead,
+                        indent: String::new(),
+                        text: line.replace('\t', "    "),
+                        pre: true,
+                    });
+                }
+                self.flow.gap = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, block: &Block) {
+        match block {
+            Block::Table => {
+                let rows = self.flow.rows();
+                let room = self.width.saturating_sub(width::width(&self.flow.indent()));
+                // the marker of a list item on the first line
+                for (i, text) in table::grid(&rows, room).into_iter().enumerate() {
+                    let lead = if i == 0 { self.flow.lead() } else { self.flow.indent() };
+                    self.push_line(Line {
+                        lead,
+                        indent: String::new(),
+                        text,
+                        pre: true,
+                    });
+                }
+                self.flow.gap = true;
+            }
+            _ => self.leave(block),
+        }
+    }
+
+    fn text(&mut self, s: &str) {
+        self.flow.text(s);
+    }
+
+    // `[n]text`
+    fn start_inline(&mut self, inline: &Inline) {
+        if let Inline::Link(n, _) = inline {
+            self.flow.inline.push_str(&format!("[{}]", n));
+        }
+    }
+
+    fn image(&mut self, alt: &str, _src: &str) {
+        self.flow.inline.push_str(&format!("[{}]", alt));
+    }
+
+    fn line_break(&mut self) {
+        self.flush();
+    }
+
+    fn finish(mut self, _title: Option<String>, links: Vec<String>) -> Vec<String> {
+        self.flush();
+        let mut out = self.lines.iter().flat_map(|line| rows(line, self.width)).collect::<Vec<_>>();
+        if !links.is_empty() {
+            out.push(String::new());
+            out.push("References".to_string());
+            out.push(String::new());
+            let digits = links.len().to_string().len();
+            for (i, link) in links.iter().enumerate() {
+                out.push(format!("{}{:>w$}. {}", MARGIN, i + 1, link, w = digits));
+            }
+        }
+        out
+    }
+}
+
+impl Layout for Generator {
+    fn flow(&mut self) -> &mut Flow {
+        &mut self.flow
+    }
+
+    fn flush(&mut self) {
+        if let Some(text) = self.flow.take() {
+            let line = Line {
+                lead: self.flow.lead(),
+                indent: self.flow.indent(),
+                text,
+                pre: false,
+            };
+            self.push_line(line);
+        }
+    }
+
+    fn quote(&self) -> &'static str {
+        "   "
+    }
+
+    fn marker(&self, number: Option<usize>) -> String {
+        match number {
+            Some(n) => format!("{:>3}. ", n),
+            None => "  * ".to_string(),
+        }
+    }
+}
+
+impl Generator {
+    fn push_line(&mut self, line: Line) {
+        if std::mem::take(&mut self.flow.gap) && !self.lines.is_empty() {
+            self.lines.push(Line::default());
+        }
+        self.lines.push(line);
+    }
+}
+
+// `line` wrapped to `width`
+fn rows(line: &Line, width: usize) -> Vec<String> {
+    if line.pre || line.text.is_empty() {
+        return vec![format!("{}{}", line.lead, line.text).trim_end().to_string()];
+    }
+    let chars = line.text.chars().collect::<Vec<_>>();
+    let room = width.saturating_sub(width::width(&line.lead)).max(width / 2).max(1);
+    layout::wrap(&line.text, room)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start, end))| {
+            let lead = if i == 0 { &line.lead } else { &line.indent };
+            format!("{}{}", lead, chars[start..end].iter().collect::<String>())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::dom;
+
+    #[test]
+    fn test_generate() {
+        let html = "\
+<h1>Manual</h1>
+<p>Read the <a href=\"/install.html\">install guide</a> first.</p>
+<ul><li><a href=\"https://example.com/download\">Download</a><li>More<ol><li>one</ol></ul>
+<blockquote>quoted</blockquote>
+<hr>
+<pre>
+  code
+</pre>";
+        assert_eq!(
+            generate(&dom::parse(html), 24),
+            vec![
+                "   Manual",
+                "",
+                "   Read the [1]install",
+                "   guide first.",
+                "",
+                "     * [2]Download",
+                "     * More",
+                "         1. one",
+                "",
+                "      quoted",
+                "",
+                "   _____________________",
+                "",
+                "     code",
+                "",
+                "References",
+                "",
+                "   1. /install.html",
+                "   2. https://example.com/download",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let html = "<ul><li><table><tr><th>a<th>b<tr><td>1<td>2</table></ul>";
+        assert_eq!(
+            generate(&dom::parse(html), 24),
+            vec![
+                "     * ┌───┬───┐",
+                "       │ a │ b │",
+                "       ├───┼───┤",
+                "       │ 1 │ 2 │",
+                "       └───┴───┘",
+            ]
+        );
+    }
+}
@@ -0,0 +1,351 @@
+// Tables laid out as text
+//
+// ```
+// | Name   | Size |       ┌──────┬───────┐
+// | ------ | ---- |       │ Name │ Size  │
+// | 日本語 | 1 KB |       ├──────┼───┬───┤
+//                         │ 日本 │ 1 │ 2 │
+//                         │      ├───┼───┘
+//                         │      │ a │
+//                         └──────┴───┘
+// ```
+//
+// `gfm` gives a GitHub-flavored Markdown pipe table, for tables without spanning cells; `grid`
+// draws any table with box-drawing characters, cells spanning columns and rows, and text wrapped
+// in its column when the table would be wider than the width given. Widths are in terminal cells
+// (see `utils/width.rs`).
+
+use crate::renderer::layout;
+use crate::utils::width;
+
+// narrowest column a cell is wrapped to
+const MIN_COLUMN: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub text: String,
+    pub header: bool,
+    pub colspan: usize,
+    pub rowspan: usize,
+}
+
+impl Cell {
+    pub fn new(text: &str, header: bool) -> Self {
+        Self {
+            text: text.to_string(),
+            header,
+            colspan: 1,
+            rowspan: 1,
+        }
+    }
+}
+
+// a pipe table, the first row being the header, or `None` if a cell spans several
+pub fn gfm(rows: &[Vec<Cell>]) -> Option<Vec<String>> {
+    if rows.iter().flatten().any(|c| c.colspan > 1 || c.rowspan > 1) {
+        return None;
+    }
+    let columns = rows.iter().map(|r| r.len()).max().filter(|&n| n > 0)?;
+    let text = |row: &[Cell], i: usize| row.get(i).map_or(String::new(), |c| c.text.replace('|', "\\|"));
+    let widths = (0..columns)
+        .map(|i| rows.iter().map(|r| width::width(&text(r, i))).max().unwrap_or(0).max(MIN_COLUMN))
+        .collect::<Vec<_>>();
+
+    let line = |cells: Vec<String>| {
+        let cells = cells.iter().zip(&widths).map(|(c, &w)| pad(c, w)).collect::<Vec<_>>();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = vec![line((0..columns).map(|i| text(&rows[0], i)).collect())];
+    lines.push(line(widths.iter().map(|&w| "-".repeat(w)).collect()));
+    for row in &rows[1..] {
+        lines.push(line((0..columns).map(|i| text(row, i)).collect()));
+    }
+    Some(lines)
+}
+
+// a cell placed in the grid
+struct Placed<'a> {
+    cell: &'a Cell,
+    row: usize,
+    column: usize,
+    // the row span, not beyond the table
+    rowspan: usize,
+    lines: Vec<String>,
+}
+
+// a grid drawn with box-drawing characters, at most `max_width` cells wide if it can be
+pub fn grid(rows: &[Vec<Cell>], max_width: usize) -> Vec<String> {
+    let (mut cells, columns) = place(rows);
+    if columns == 0 {
+        return vec![];
+    }
+    let widths = column_widths(&cells, columns, max_width);
+    let x = offsets(&widths, 3);
+    for placed in cells.iter_mut() {
+        let room = x[placed.column + placed.cell.colspan] - x[placed.column] - 3;
+        placed.lines = wrap(&placed.cell.text, room);
+    }
+    let heights = row_heights(&cells, rows.len());
+    let y = offsets(&heights, 1);
+
+    // lines of the boxes, then the text in them
+    let mut canvas = Canvas::new(x[columns] + 1, y[rows.len()] + 1);
+    for placed in &cells {
+        let (top, bottom) = (y[placed.row], y[placed.row + placed.rowspan]);
+        let (left, right) = (x[placed.column], x[placed.column + placed.cell.colspan]);
+        canvas.horizontal(top, left, right);
+        canvas.horizontal(bottom, left, right);
+        canvas.vertical(left, top, bottom);
+        canvas.vertical(right, top, bottom);
+    }
+    for placed in &cells {
+        for (i, line) in placed.lines.iter().enumerate() {
+            canvas.text(y[placed.row] + 1 + i, x[placed.column] + 2, line);
+        }
+    }
+    canvas.lines()
+}
+
+// cells with their rows and columns (the first free place from the left, as browsers do), and
+// the number of columns
+fn place(rows: &[Vec<Cell>]) -> (Vec<Placed<'_>>, usize) {
+    let mut taken: Vec<Vec<bool>> = vec![vec![]; rows.len()];
+    let mut cells = vec![];
+    for (r, row) in rows.iter().enumerate() {
+        let mut column = 0;
+        for cell in row {
+            while taken[r].get(column) == Some(&true) {
+                column += 1;
+            }
+            let rowspan = cell.rowspan.clamp(1, rows.len() - r);
+            for line in taken.iter_mut().skip(r).take(rowspan) {
+                if line.len() < column + cell.colspan {
+                    line.resize(column + cell.colspan, false);
+                }
+                for t in &mut line[column..column + cell.colspan] {
+                    *t = true;
+                }
+            }
+            cells.push(Placed {
+                cell,
+                row: r,
+                column,
+                rowspan,
+                lines: vec![],
+            });
+            column += cell.colspan;
+        }
+    }
+    let columns = taken.iter().map(|t| t.len()).max().unwrap_or(0);
+    (cells, columns)
+}
+
+// widths of the text in the columns: wide enough for every cell (a spanning cell widening its
+// last column), then narrowed from the widest until the grid fits in `max_width`
+fn column_widths(cells: &[Placed], columns: usize, max_width: usize) -> Vec<usize> {
+    let mut widths = vec![1; columns];
+    let mut spanning = vec![];
+    for placed in cells {
+        let w = width::width(&placed.cell.text);
+        if placed.cell.colspan == 1 {
+            widths[placed.column] = widths[placed.column].max(w);
+        } else {
+            spanning.push((placed.column, placed.cell.colspan, w));
+        }
+    }
+    for (column, span, w) in spanning {
+        let room = widths[column..column + span].iter().sum::<usize>() + 3 * (span - 1);
+        if w > room {
+            widths[column + span - 1] += w - room;
+        }
+    }
+
+    let total = |widths: &[usize]| widths.iter().sum::<usize>() + 3 * widths.len() + 1;
+    while total(&widths) > max_width {
+        let (i, &w) = widths.iter().enumerate().max_by_key(|(_, &w)| w).unwrap();
+        if w <= MIN_COLUMN {
+            break;
+        }
+        widths[i] -= 1;
+    }
+    widths
+}
+
+// heights of the rows: enough for every cell (a spanning cell heightening its last row)
+fn row_heights(cells: &[Placed], rows: usize) -> Vec<usize> {
+    let mut heights = vec![1; rows];
+    let spanning = cells.iter().filter(|p| p.rowspan > 1).collect::<Vec<_>>();
+    for placed in cells.iter().filter(|p| p.rowspan == 1) {
+        heights[placed.row] = heights[placed.row].max(placed.lines.len());
+    }
+    for placed in spanning {
+        let span = placed.rowspan;
+        let room = heights[placed.row..placed.row + span].iter().sum::<usize>() + span - 1;
+        if placed.lines.len() > room {
+            heights[placed.row + span - 1] += placed.lines.len() - room;
+        }
+    }
+    heights
+}
+
+// where each of `sizes` starts, with `gap` cells between them, and where the last one ends
+fn offsets(sizes: &[usize], gap: usize) -> Vec<usize> {
+    let mut offsets = vec![0];
+    for size in sizes {
+        offsets.push(offsets.last().unwrap() + size + gap);
+    }
+    offsets
+}
+
+// `text` wrapped to `width` cells
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    if text.is_empty() {
+        return vec![];
+    }
+    let chars = text.chars().collect::<Vec<_>>();
+    layout::wrap(text, width).into_iter().map(|(start, end)| chars[start..end].iter().collect()).collect()
+}
+
+// `s` followed by spaces up to `width` cells
+fn pad(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(width::width(s))))
+}
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+// terminal cells to draw on: lines as the directions they go to, and text
+struct Canvas {
+    lines: Vec<Vec<u8>>,
+    // `None` for the cell covered by a wide character on its left
+    text: Vec<Vec<Option<char>>>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            lines: vec![vec![0; width]; height],
+            text: vec![vec![Some(' '); width]; height],
+        }
+    }
+
+    fn horizontal(&mut self, y: usize, x0: usize, x1: usize) {
+        for x in x0..=x1 {
+            if x > x0 {
+                self.lines[y][x] |= LEFT;
+            }
+            if x < x1 {
+                self.lines[y][x] |= RIGHT;
+            }
+        }
+    }
+
+    fn vertical(&mut self, x: usize, y0: usize, y1: usize) {
+        for y in y0..=y1 {
+            if y > y0 {
+                self.lines[y][x] |= UP;
+            }
+            if y < y1 {
+                self.lines[y][x] |= DOWN;
+            }
+        }
+    }
+
+    fn text(&mut self, y: usize, x: usize, s: &str) {
+        let mut x = x;
+        for c in s.chars() {
+            let w = width::char_width(c);
+            if w == 0 || x + w > self.text[y].len() {
+                continue;
+            }
+            self.text[y][x] = Some(c);
+            if w == 2 {
+                self.text[y][x + 1] = None;
+            }
+            x += w;
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .zip(&self.text)
+            .map(|(lines, text)| {
+                let mut out = String::new();
+                for (&line, &c) in lines.iter().zip(text) {
+                    match (line, c) {
+                        (0, Some(c)) => out.push(c),
+                        (0, None) => {}
+                        (line, _) => out.push(corner(line)),
+                    }
+                }
+                out.trim_end().to_string()
+            })
+            .collect()
+    }
+}
+
+fn corner(line: u8) -> char {
+    match line {
+        l if l == UP | DOWN | LEFT | RIGHT => '┼',
+        l if l == UP | DOWN | RIGHT => '├',
+        l if l == UP | DOWN | LEFT => '┤',
+        l if l == LEFT | RIGHT | DOWN => '┬',
+        l if l == LEFT | RIGHT | UP => '┴',
+        l if l == DOWN | RIGHT => '┌',
+        l if l == DOWN | LEFT => '┐',
+        l if l == UP | RIGHT => '└',
+        l if l == UP | LEFT => '┘',
+        l if l & (UP | DOWN) != 0 => '│',
+        _ => '─',
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rows(src: &[&[&str]]) -> Vec<Vec<Cell>> {
+        src.iter()
+            .enumerate()
+            .map(|(i, row)| row.iter().map(|t| Cell::new(t, i == 0)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_gfm() {
+        let table = rows(&[&["Name", "Size"], &["日本語", "1|2"], &["x"]]);
+        assert_eq!(
+            gfm(&table).unwrap(),
+            vec!["| Name   | Size |", "| ------ | ---- |", "| 日本語 | 1\\|2 |", "| x      |      |"]
+        );
+        let mut table = table;
+        table[1][0].colspan = 2;
+        assert!(gfm(&table).is_none());
+    }
+
+    #[test]
+    fn test_grid() {
+        let mut table = rows(&[&["Name", "Size"], &["日本", "1", "2"], &["a"]]);
+        table[0][1].colspan = 2;
+        table[1][0].rowspan = 2;
+        assert_eq!(
+            grid(&table, 80),
+            vec![
+                "┌──────┬───────┐",
+                "│ Name │ Size  │",
+                "├──────┼───┬───┤",
+                "│ 日本 │ 1 │ 2 │",
+                "│      ├───┼───┘",
+                "│      │ a │",
+                "└──────┴───┘",
+            ]
+        );
+
+        // wrapped to fit
+        let table = rows(&[&["a long cell", "b"]]);
+        assert_eq!(grid(&table, 12), vec!["┌──────┬───┐", "│ a    │ b │", "│ long │   │", "│ cell │   │", "└──────┴───┘"]);
+    }
+}
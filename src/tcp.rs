@@ -0,0 +1,43 @@
+// User-space TCP over IPv4
+//
+// An alternative to the OS's TCP socket for learning and debugging:
+// `stack::Stack` speaks TCP (RFC 9293) over any `device::PacketDevice`
+// which carries raw IPv4 packets.
+//
+// ```
+// Client -> StackTransport -> Stack -> PacketDevice (e.g. LoopbackDevice) -> Stack -> server
+// ```
+
+pub mod device;
+pub mod packet;
+pub mod stack;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// xorshift64* (packet loss, initial sequence numbers and ports)
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // the state must not be zero
+        Self(seed.max(1))
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        Self::new(nanos ^ (std::process::id() as u64) << 32)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,165 @@
+// Packet devices carry raw IPv4 packets for `Stack`.
+//
+// `LoopbackDevice::pair` connects two stacks in memory;
+// `Impairment` makes the link lose and reorder packets (deterministically by a seed)
+// so retransmission and reassembly can be tested.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use crate::tcp::Rng;
+
+pub trait PacketDevice: Send + Sync {
+    fn send(&self, packet: &[u8]) -> io::Result<()>;
+
+    // wait up to `timeout` for a packet (`None` if no packet arrived)
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Impairment {
+    loss: f64,
+    reorder: f64,
+    seed: u64,
+}
+
+impl Default for Impairment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Impairment {
+    // a perfect link
+    pub fn new() -> Self {
+        Self { loss: 0.0, reorder: 0.0, seed: 1 }
+    }
+
+    // drop packets with probability `p`
+    pub fn with_loss(mut self, p: f64) -> Self {
+        self.loss = p;
+        self
+    }
+
+    // deliver packets before the previous one with probability `p`
+    pub fn with_reorder(mut self, p: f64) -> Self {
+        self.reorder = p;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+type Queue = Arc<(Mutex<VecDeque<Vec<u8>>>, Condvar)>;
+
+pub struct LoopbackDevice {
+    inbox: Queue,
+    // the inbox of the other end
+    peer: Queue,
+    impairment: Impairment,
+    rng: Mutex<Rng>,
+    sent: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+impl LoopbackDevice {
+    // two ends of a link; `impairment` applies in both directions
+    pub fn pair(impairment: Impairment) -> (Self, Self) {
+        let a: Queue = Arc::new((Mutex::new(VecDeque::new()), Condvar::new()));
+        let b: Queue = Arc::new((Mutex::new(VecDeque::new()), Condvar::new()));
+        let end = |inbox: &Queue, peer: &Queue, seed: u64| Self {
+            inbox: inbox.clone(),
+            peer: peer.clone(),
+            impairment,
+            rng: Mutex::new(Rng::new(seed)),
+            sent: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        };
+        (
+            end(&a, &b, impairment.seed),
+            end(&b, &a, impairment.seed ^ 0x9E37_79B9_7F4A_7C15),
+        )
+    }
+
+    // packets passed to `send`
+    pub fn sent(&self) -> usize {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    // packets lost on the way to the other end
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl PacketDevice for LoopbackDevice {
+    fn send(&self, packet: &[u8]) -> io::Result<()> {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        let (lost, reordered) = {
+            let mut rng = self.rng.lock().unwrap();
+            (rng.next_f64() < self.impairment.loss, rng.next_f64() < self.impairment.reorder)
+        };
+        if lost {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let (queue, cond) = &*self.peer;
+        let mut queue = queue.lock().unwrap();
+        if reordered && !queue.is_empty() {
+            let last = queue.len() - 1;
+            queue.insert(last, packet.to_vec());
+        } else {
+            queue.push_back(packet.to_vec());
+        }
+        cond.notify_all();
+        Ok(())
+    }
+
+    fn recv(&self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        let (queue, cond) = &*self.inbox;
+        let queue = queue.lock().unwrap();
+        let (mut queue, _) = cond.wait_timeout_while(queue, timeout, |q| q.is_empty()).unwrap();
+        Ok(queue.pop_front())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_loopback() {
+        let (a, b) = LoopbackDevice::pair(Impairment::new());
+        a.send(b"1").unwrap();
+        a.send(b"2").unwrap();
+        assert_eq!(b.recv(Duration::from_millis(10)).unwrap(), Some(b"1".to_vec()));
+        assert_eq!(b.recv(Duration::from_millis(10)).unwrap(), Some(b"2".to_vec()));
+        assert_eq!(b.recv(Duration::from_millis(10)).unwrap(), None);
+        assert_eq!(a.recv(Duration::from_millis(10)).unwrap(), None);
+
+        // the same seed loses the same packets
+        let impairment = Impairment::new().with_loss(0.3).with_reorder(0.3).with_seed(42);
+        let run = || {
+            let (a, b) = LoopbackDevice::pair(impairment);
+            for i in 0..100u8 {
+                a.send(&[i]).unwrap();
+            }
+            let mut received = vec![];
+            while let Some(p) = b.recv(Duration::from_millis(1)).unwrap() {
+                received.push(p[0]);
+            }
+            assert_eq!(received.len() + a.dropped(), 100);
+            received
+        };
+        let received = run();
+        assert_eq!(received, run());
+        assert!(received.len() < 90);
+        assert!(received.windows(2).any(|w| w[0] > w[1]));
+    }
+}
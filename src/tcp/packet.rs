@@ -0,0 +1,260 @@
+// IPv4 packets (RFC 791) and TCP segments (RFC 9293 section 3.1)
+//
+// ```
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |          Source Port          |       Destination Port        |
+// |                        Sequence Number                        |
+// |                    Acknowledgment Number                      |
+// |  Data |       |C|E|U|A|P|R|S|F|                               |
+// | Offset| Rsrvd |W|C|R|C|S|S|Y|I|            Window             |
+// |       |       |R|E|G|K|H|T|N|N|                               |
+// |           Checksum            |         Urgent Pointer        |
+// |                    Options                    |    Padding    |
+// |                             data                              |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// ```
+//
+// IP options and fragmentation are not supported.
+
+use std::error::Error;
+use std::fmt;
+use std::net::Ipv4Addr;
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum PacketError {
+    TooShort,
+    NotIpv4,
+    NotTcp,
+    Fragmented,
+    BadChecksum,
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort => write!(f, "Packet error: packet is too short"),
+            Self::NotIpv4 => write!(f, "Packet error: not an IPv4 packet"),
+            Self::NotTcp => write!(f, "Packet error: not a TCP segment"),
+            Self::Fragmented => write!(f, "Packet error: fragmented packets are not supported"),
+            Self::BadChecksum => write!(f, "Packet error: bad checksum"),
+        }
+    }
+}
+
+impl Error for PacketError {}
+// ------------------------------------
+
+pub const PROTOCOL_TCP: u8 = 6;
+
+pub const FIN: u8 = 0x01;
+pub const SYN: u8 = 0x02;
+pub const RST: u8 = 0x04;
+pub const PSH: u8 = 0x08;
+pub const ACK: u8 = 0x10;
+
+const IPV4_HEADER: usize = 20;
+const TCP_HEADER: usize = 20;
+const TTL: u8 = 64;
+
+// the Internet checksum (RFC 1071) of `data`
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+// an IPv4 packet carrying `payload`
+pub fn ipv4_packet(src: Ipv4Addr, dst: Ipv4Addr, protocol: u8, id: u16, payload: &[u8]) -> Vec<u8> {
+    let total = (IPV4_HEADER + payload.len()) as u16;
+    let mut packet = vec![0x45, 0x00];
+    packet.extend_from_slice(&total.to_be_bytes());
+    packet.extend_from_slice(&id.to_be_bytes());
+    // don't fragment
+    packet.extend_from_slice(&[0x40, 0x00, TTL, protocol, 0, 0]);
+    packet.extend_from_slice(&src.octets());
+    packet.extend_from_slice(&dst.octets());
+    let sum = checksum(&packet);
+    packet[10..12].copy_from_slice(&sum.to_be_bytes());
+    packet.extend_from_slice(payload);
+    packet
+}
+
+// (source, destination, protocol, payload) of an IPv4 packet
+pub fn parse_ipv4(packet: &[u8]) -> Result<(Ipv4Addr, Ipv4Addr, u8, &[u8]), PacketError> {
+    if packet.len() < IPV4_HEADER {
+        return Err(PacketError::TooShort);
+    }
+    if packet[0] >> 4 != 4 {
+        return Err(PacketError::NotIpv4);
+    }
+    let header = (packet[0] & 0x0F) as usize * 4;
+    let total = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+    if header < IPV4_HEADER || total < header || packet.len() < total {
+        return Err(PacketError::TooShort);
+    }
+    if checksum(&packet[..header]) != 0 {
+        return Err(PacketError::BadChecksum);
+    }
+    // more fragments, or a fragment offset
+    if u16::from_be_bytes([packet[6], packet[7]]) & 0x3FFF != 0 {
+        return Err(PacketError::Fragmented);
+    }
+    let src = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    let dst = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+    Ok((src, dst, packet[9], &packet[header..total]))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Segment {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub seq: u32,
+    pub ack: u32,
+    pub flags: u8,
+    pub window: u16,
+    // the MSS option (only in SYN segments)
+    pub mss: Option<u16>,
+    pub payload: Vec<u8>,
+}
+
+impl Segment {
+    pub fn has(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    // length in sequence space (SYN and FIN count as one)
+    pub fn seq_len(&self) -> u32 {
+        self.payload.len() as u32 + self.has(SYN) as u32 + self.has(FIN) as u32
+    }
+
+    pub fn encode(&self, src: Ipv4Addr, dst: Ipv4Addr) -> Vec<u8> {
+        let options: Vec<u8> = match self.mss {
+            Some(mss) => vec![2, 4, (mss >> 8) as u8, mss as u8],
+            None => vec![],
+        };
+        let offset = (TCP_HEADER + options.len()) / 4;
+        let mut buf = vec![];
+        buf.extend_from_slice(&self.src_port.to_be_bytes());
+        buf.extend_from_slice(&self.dst_port.to_be_bytes());
+        buf.extend_from_slice(&self.seq.to_be_bytes());
+        buf.extend_from_slice(&self.ack.to_be_bytes());
+        buf.push((offset as u8) << 4);
+        buf.push(self.flags);
+        buf.extend_from_slice(&self.window.to_be_bytes());
+        // checksum and urgent pointer
+        buf.extend_from_slice(&[0, 0, 0, 0]);
+        buf.extend_from_slice(&options);
+        buf.extend_from_slice(&self.payload);
+
+        let sum = checksum(&[pseudo_header(src, dst, buf.len()), buf.clone()].concat());
+        buf[16..18].copy_from_slice(&sum.to_be_bytes());
+        buf
+    }
+
+    // `src` and `dst` are the addresses of the IPv4 packet (for the checksum)
+    pub fn decode(buf: &[u8], src: Ipv4Addr, dst: Ipv4Addr) -> Result<Self, PacketError> {
+        if buf.len() < TCP_HEADER {
+            return Err(PacketError::TooShort);
+        }
+        let offset = (buf[12] >> 4) as usize * 4;
+        if offset < TCP_HEADER || buf.len() < offset {
+            return Err(PacketError::TooShort);
+        }
+        if checksum(&[pseudo_header(src, dst, buf.len()), buf.to_vec()].concat()) != 0 {
+            return Err(PacketError::BadChecksum);
+        }
+
+        let mut mss = None;
+        let mut options = &buf[TCP_HEADER..offset];
+        while let Some(&kind) = options.first() {
+            match kind {
+                // end of option list
+                0 => break,
+                // no-operation
+                1 => options = &options[1..],
+                _ => {
+                    let len = *options.get(1).ok_or(PacketError::TooShort)? as usize;
+                    if len < 2 || options.len() < len {
+                        return Err(PacketError::TooShort);
+                    }
+                    if kind == 2 && len == 4 {
+                        mss = Some(u16::from_be_bytes([options[2], options[3]]));
+                    }
+                    options = &options[len..];
+                }
+            }
+        }
+
+        Ok(Self {
+            src_port: u16::from_be_bytes([buf[0], buf[1]]),
+            dst_port: u16::from_be_bytes([buf[2], buf[3]]),
+            seq: u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            ack: u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]),
+            flags: buf[13],
+            window: u16::from_be_bytes([buf[14], buf[15]]),
+            mss,
+            payload: buf[offset..].to_vec(),
+        })
+    }
+}
+
+fn pseudo_header(src: Ipv4Addr, dst: Ipv4Addr, len: usize) -> Vec<u8> {
+    let mut header = vec![];
+    header.extend_from_slice(&src.octets());
+    header.extend_from_slice(&dst.octets());
+    header.extend_from_slice(&[0, PROTOCOL_TCP]);
+    header.extend_from_slice(&(len as u16).to_be_bytes());
+    header
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        // RFC 1071 section 3
+        assert_eq!(checksum(&[0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7]), !0xddf2);
+
+        let (a, b) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2));
+        let packet = ipv4_packet(a, b, PROTOCOL_TCP, 7, b"xyz");
+        assert_eq!(parse_ipv4(&packet).unwrap(), (a, b, PROTOCOL_TCP, &b"xyz"[..]));
+        let mut broken = packet.clone();
+        broken[8] = 1;
+        assert!(matches!(parse_ipv4(&broken), Err(PacketError::BadChecksum)));
+    }
+
+    #[test]
+    fn test_segment() {
+        let (a, b) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2));
+        let syn = Segment {
+            src_port: 49152,
+            dst_port: 80,
+            seq: 0xFFFF_FFFF,
+            flags: SYN,
+            window: 65535,
+            mss: Some(1460),
+            ..Segment::default()
+        };
+        let bytes = syn.encode(a, b);
+        assert_eq!(bytes.len(), 24);
+        assert_eq!(Segment::decode(&bytes, a, b).unwrap(), syn);
+        assert_eq!(syn.seq_len(), 1);
+
+        let data = Segment { flags: ACK | PSH | FIN, payload: b"hello".to_vec(), ..syn };
+        let data = Segment { mss: None, ..data };
+        let bytes = data.encode(a, b);
+        assert_eq!(Segment::decode(&bytes, a, b).unwrap(), data);
+        assert_eq!(data.seq_len(), 6);
+        // the pseudo header covers the addresses
+        assert!(matches!(Segment::decode(&bytes, b, b), Err(PacketError::BadChecksum)));
+    }
+}
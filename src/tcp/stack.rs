@@ -0,0 +1,900 @@
+// TCP connections over a `PacketDevice`
+//
+// A background thread reads packets from the device and runs the timers;
+// `Connection` and `Listener` are handles used from other threads.
+//
+// - three-way handshake (active and passive open) with the MSS option
+// - sequence/acknowledgment tracking and reassembly of out-of-order segments
+// - retransmission (go-back-N) with the RTO of RFC 6298 and Karn's algorithm
+// - flow control by the receive window, zero window probes
+// - FIN teardown (including simultaneous close and TIME-WAIT) and RST
+//
+// Not implemented: congestion control, fast retransmit, window scaling, SACK,
+// simultaneous open and urgent data.
+//
+// ref: https://www.rfc-editor.org/rfc/rfc9293
+// ref: https://www.rfc-editor.org/rfc/rfc6298
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::tcp::Rng;
+use crate::tcp::device::PacketDevice;
+use crate::tcp::packet::{self, Segment, ACK, FIN, PSH, RST, SYN};
+
+const MSS: usize = 1460;
+// the default MSS without the option (RFC 9293 section 3.7.1)
+const DEFAULT_MSS: usize = 536;
+// the largest window without window scaling
+const RECV_BUFFER: usize = 65535;
+// `write` blocks while this much data is unacknowledged
+const SEND_BUFFER: usize = 256 * 1024;
+
+const INITIAL_RTO: Duration = Duration::from_millis(200);
+// far below RFC 6298's 1 second: packet devices are usually in memory
+const MIN_RTO: Duration = Duration::from_millis(20);
+const MAX_RTO: Duration = Duration::from_secs(10);
+const MAX_RETRIES: u32 = 12;
+// 2 MSL, shortened for the same reason as `MIN_RTO`
+const TIME_WAIT: Duration = Duration::from_millis(200);
+// how often the timers are checked
+const TICK: Duration = Duration::from_millis(2);
+
+const EPHEMERAL_PORTS: (u16, u16) = (49152, 65535);
+
+// (local port, remote address)
+type Key = (u16, SocketAddrV4);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+    Closed,
+}
+
+// sequence numbers wrap around (RFC 9293 section 3.4)
+fn seq_lt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+fn seq_le(a: u32, b: u32) -> bool {
+    a == b || seq_lt(a, b)
+}
+
+// Transmission Control Block
+struct Tcb {
+    state: State,
+    local: SocketAddrV4,
+    remote: SocketAddrV4,
+
+    // send sequence space: `snd_una` <= `snd_nxt` <= `snd_max`
+    // (`snd_nxt` goes back to `snd_una` on retransmission)
+    iss: u32,
+    snd_una: u32,
+    snd_nxt: u32,
+    snd_max: u32,
+    snd_wnd: u32,
+    mss: usize,
+    // data from `snd_una` (unacknowledged or not yet sent)
+    send_buf: VecDeque<u8>,
+    close_requested: bool,
+    fin_sent: bool,
+    fin_acked: bool,
+
+    // receive sequence space
+    irs: u32,
+    rcv_nxt: u32,
+    recv_buf: VecDeque<u8>,
+    // offset from `irs` -> data
+    out_of_order: BTreeMap<u32, Vec<u8>>,
+    fin_seq: Option<u32>,
+    peer_closed: bool,
+    ack_pending: bool,
+    // the window in the last segment sent
+    advertised: usize,
+
+    // retransmission
+    rto: Duration,
+    srtt: Option<Duration>,
+    rttvar: Duration,
+    // (sequence number to be acknowledged, time sent)
+    rtt_probe: Option<(u32, Instant)>,
+    deadline: Option<Instant>,
+    retries: u32,
+    time_wait_until: Option<Instant>,
+
+    error: Option<ErrorKind>,
+    // no handle refers to the connection (removed once closed)
+    detached: bool,
+}
+
+impl Tcb {
+    fn new(local: SocketAddrV4, remote: SocketAddrV4, iss: u32, state: State) -> Self {
+        Self {
+            state,
+            local,
+            remote,
+            iss,
+            snd_una: iss,
+            snd_nxt: iss,
+            snd_max: iss,
+            snd_wnd: 0,
+            mss: DEFAULT_MSS,
+            send_buf: VecDeque::new(),
+            close_requested: false,
+            fin_sent: false,
+            fin_acked: false,
+            irs: 0,
+            rcv_nxt: 0,
+            recv_buf: VecDeque::new(),
+            out_of_order: BTreeMap::new(),
+            fin_seq: None,
+            peer_closed: false,
+            ack_pending: false,
+            advertised: 0,
+            rto: INITIAL_RTO,
+            srtt: None,
+            rttvar: Duration::from_millis(0),
+            rtt_probe: None,
+            deadline: None,
+            retries: 0,
+            time_wait_until: None,
+            error: None,
+            detached: false,
+        }
+    }
+
+    fn window(&self) -> usize {
+        RECV_BUFFER - self.recv_buf.len()
+    }
+
+    fn segment(&self, flags: u8, seq: u32, payload: Vec<u8>) -> Segment {
+        Segment {
+            src_port: self.local.port(),
+            dst_port: self.remote.port(),
+            seq,
+            ack: if flags & ACK != 0 { self.rcv_nxt } else { 0 },
+            flags,
+            window: self.window() as u16,
+            mss: None,
+            payload,
+        }
+    }
+
+    // the peer's SYN (or SYN-ACK)
+    fn synchronize(&mut self, seg: &Segment) {
+        self.irs = seg.seq;
+        self.rcv_nxt = seg.seq.wrapping_add(1);
+        self.snd_wnd = seg.window as u32;
+        self.mss = seg.mss.map_or(DEFAULT_MSS, |mss| mss as usize).min(MSS);
+    }
+
+    fn fail(&mut self, kind: ErrorKind) {
+        self.state = State::Closed;
+        self.error = Some(kind);
+        self.deadline = None;
+        self.send_buf.clear();
+    }
+
+    fn enter_time_wait(&mut self, now: Instant) {
+        self.state = State::TimeWait;
+        self.deadline = None;
+        self.time_wait_until = Some(now + TIME_WAIT);
+    }
+
+    // segments to send now
+    fn output(&mut self, now: Instant) -> Vec<Segment> {
+        let mut out = vec![];
+        match self.state {
+            // not sent yet, or to be retransmitted
+            State::SynSent | State::SynReceived if self.snd_nxt == self.iss => {
+                let flags = if self.state == State::SynReceived { SYN | ACK } else { SYN };
+                let mut syn = self.segment(flags, self.iss, vec![]);
+                syn.mss = Some(MSS as u16);
+                out.push(syn);
+                self.snd_nxt = self.iss.wrapping_add(1);
+            }
+            State::Established | State::CloseWait | State::FinWait1 | State::Closing | State::LastAck => {
+                let data_len = self.send_buf.len() as u32;
+                let mut offset = self.snd_nxt.wrapping_sub(self.snd_una);
+                // a zero window is probed with one octet
+                let window = self.snd_wnd.max((offset == 0) as u32);
+                while offset < data_len && offset < window {
+                    let n = (data_len - offset).min(window - offset).min(self.mss as u32);
+                    let payload = self.send_buf.iter().skip(offset as usize).take(n as usize).copied().collect();
+                    out.push(self.segment(ACK | PSH, self.snd_una.wrapping_add(offset), payload));
+                    offset += n;
+                }
+                if self.close_requested && !self.fin_sent && !self.fin_acked && offset == data_len {
+                    out.push(self.segment(FIN | ACK, self.snd_una.wrapping_add(data_len), vec![]));
+                    offset += 1;
+                    self.fin_sent = true;
+                    self.state = match self.state {
+                        State::Established => State::FinWait1,
+                        State::CloseWait => State::LastAck,
+                        state => state,
+                    };
+                }
+                self.snd_nxt = self.snd_una.wrapping_add(offset);
+            }
+            _ => {}
+        }
+
+        if !out.is_empty() {
+            // time only segments sent for the first time (Karn's algorithm)
+            if seq_lt(self.snd_max, self.snd_nxt) {
+                self.snd_max = self.snd_nxt;
+                if self.rtt_probe.is_none() {
+                    self.rtt_probe = Some((self.snd_nxt, now));
+                }
+            }
+            if self.deadline.is_none() {
+                self.deadline = Some(now + self.rto);
+            }
+        } else if self.ack_pending && self.state != State::SynSent && self.state != State::Closed {
+            out.push(self.segment(ACK, self.snd_nxt, vec![]));
+        }
+        if !out.is_empty() {
+            self.ack_pending = false;
+            self.advertised = self.window();
+        }
+        out
+    }
+
+    fn on_timeout(&mut self) {
+        self.deadline = None;
+        self.retries += 1;
+        if self.retries > MAX_RETRIES {
+            self.fail(ErrorKind::TimedOut);
+            return;
+        }
+        self.rto = (self.rto * 2).min(MAX_RTO);
+        self.rtt_probe = None;
+        // go back to the first unacknowledged octet
+        self.snd_nxt = self.snd_una;
+        if !self.fin_acked {
+            self.fin_sent = false;
+        }
+    }
+
+    fn update_rto(&mut self, rtt: Duration) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(rtt);
+                self.rttvar = rtt / 2;
+            }
+            Some(srtt) => {
+                self.rttvar = self.rttvar * 3 / 4 + srtt.abs_diff(rtt) / 4;
+                self.srtt = Some(srtt * 7 / 8 + rtt / 8);
+            }
+        }
+        let rto = self.srtt.unwrap_or(rtt) + (self.rttvar * 4).max(TICK);
+        self.rto = rto.max(MIN_RTO).min(MAX_RTO);
+    }
+
+    fn input(&mut self, seg: &Segment, now: Instant) {
+        if seg.has(RST) {
+            match self.state {
+                State::SynSent if seg.has(ACK) && seg.ack == self.snd_nxt => self.fail(ErrorKind::ConnectionRefused),
+                State::SynSent | State::Closed => {}
+                _ => {
+                    let offset = seg.seq.wrapping_sub(self.rcv_nxt) as usize;
+                    if offset < self.window().max(1) {
+                        self.fail(ErrorKind::ConnectionReset);
+                    }
+                }
+            }
+            return;
+        }
+
+        match self.state {
+            State::SynSent => {
+                if seg.has(SYN) && seg.has(ACK) && seg.ack == self.iss.wrapping_add(1) {
+                    self.synchronize(seg);
+                    self.acknowledge(seg, now);
+                    self.state = State::Established;
+                    self.ack_pending = true;
+                }
+                return;
+            }
+            State::SynReceived => {
+                if seg.has(SYN) {
+                    // our SYN-ACK was lost
+                    self.snd_nxt = self.iss;
+                    return;
+                }
+                if !seg.has(ACK) || seg.ack != self.iss.wrapping_add(1) {
+                    return;
+                }
+                self.acknowledge(seg, now);
+                self.state = State::Established;
+            }
+            State::Closed => return,
+            _ => {}
+        }
+
+        if seg.has(SYN) {
+            // a retransmitted SYN-ACK: our ACK was lost
+            self.ack_pending = true;
+            return;
+        }
+        if seg.has(ACK) {
+            self.acknowledge(seg, now);
+        }
+        self.receive(seg, now);
+    }
+
+    fn acknowledge(&mut self, seg: &Segment, now: Instant) {
+        let ack = seg.ack;
+        if seq_lt(self.snd_una, ack) && seq_le(ack, self.snd_max) {
+            let mut acked = ack.wrapping_sub(self.snd_una) as usize;
+            if self.state == State::SynSent || self.state == State::SynReceived {
+                acked -= 1;
+            }
+            let data = acked.min(self.send_buf.len());
+            self.send_buf.drain(..data);
+            if acked > data {
+                self.fin_acked = true;
+                self.fin_sent = true;
+            }
+            self.snd_una = ack;
+            if seq_lt(self.snd_nxt, ack) {
+                self.snd_nxt = ack;
+            }
+            if let Some((seq, sent)) = self.rtt_probe {
+                if seq_le(seq, ack) {
+                    self.update_rto(now - sent);
+                    self.rtt_probe = None;
+                }
+            }
+            self.retries = 0;
+            self.deadline = if self.snd_una == self.snd_max { None } else { Some(now + self.rto) };
+
+            if self.fin_acked {
+                match self.state {
+                    State::FinWait1 => self.state = State::FinWait2,
+                    State::Closing => self.enter_time_wait(now),
+                    State::LastAck => self.state = State::Closed,
+                    _ => {}
+                }
+            }
+        }
+        if ack == self.snd_una {
+            self.snd_wnd = seg.window as u32;
+        }
+    }
+
+    fn receive(&mut self, seg: &Segment, now: Instant) {
+        if seg.seq_len() == 0 {
+            return;
+        }
+        self.ack_pending = true;
+        if !matches!(self.state, State::Established | State::FinWait1 | State::FinWait2) {
+            // the peer has already sent FIN: only acknowledge retransmissions
+            return;
+        }
+
+        // trim what has already been received
+        let mut seq = seg.seq;
+        let mut data = &seg.payload[..];
+        if seq_lt(seq, self.rcv_nxt) {
+            let skip = (self.rcv_nxt.wrapping_sub(seq) as usize).min(data.len());
+            data = &data[skip..];
+            seq = seq.wrapping_add(skip as u32);
+        }
+        if seg.has(FIN) {
+            self.fin_seq = Some(seg.seq.wrapping_add(seg.payload.len() as u32));
+        }
+
+        if !data.is_empty() {
+            if seq == self.rcv_nxt {
+                self.append(data);
+                self.reassemble();
+            } else if (seq.wrapping_sub(self.rcv_nxt) as usize) < self.window() {
+                self.out_of_order.insert(seq.wrapping_sub(self.irs), data.to_vec());
+            }
+        }
+
+        if self.fin_seq == Some(self.rcv_nxt) && !self.peer_closed {
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(1);
+            self.peer_closed = true;
+            match self.state {
+                State::Established => self.state = State::CloseWait,
+                State::FinWait1 if self.fin_acked => self.enter_time_wait(now),
+                State::FinWait1 => self.state = State::Closing,
+                State::FinWait2 => self.enter_time_wait(now),
+                _ => {}
+            }
+        }
+    }
+
+    // in-order data (as much as the buffer holds)
+    fn append(&mut self, data: &[u8]) {
+        let n = data.len().min(self.window());
+        self.recv_buf.extend(&data[..n]);
+        self.rcv_nxt = self.rcv_nxt.wrapping_add(n as u32);
+    }
+
+    fn reassemble(&mut self) {
+        while let Some(&offset) = self.out_of_order.keys().next() {
+            let next = self.rcv_nxt.wrapping_sub(self.irs);
+            if offset > next {
+                break;
+            }
+            let data = self.out_of_order.remove(&offset).unwrap_or_default();
+            let skip = (next - offset) as usize;
+            if skip < data.len() {
+                self.append(&data[skip..]);
+            }
+        }
+    }
+}
+
+struct Inner {
+    addr: Ipv4Addr,
+    conns: HashMap<Key, Tcb>,
+    // port -> established connections waiting for `accept`
+    listeners: HashMap<u16, VecDeque<Key>>,
+    rng: Rng,
+    next_port: u16,
+    ip_id: u16,
+}
+
+struct Shared {
+    device: Arc<dyn PacketDevice>,
+    inner: Mutex<Inner>,
+    // notified whenever a connection changes
+    cond: Condvar,
+}
+
+impl Shared {
+    fn send_segments(&self, inner: &mut Inner, remote: Ipv4Addr, segs: Vec<Segment>) {
+        for seg in segs {
+            inner.ip_id = inner.ip_id.wrapping_add(1);
+            let tcp = seg.encode(inner.addr, remote);
+            let ip = packet::ipv4_packet(inner.addr, remote, packet::PROTOCOL_TCP, inner.ip_id, &tcp);
+            // a packet lost here is recovered like one lost on the link
+            let _ = self.device.send(&ip);
+        }
+    }
+
+    fn flush(&self, inner: &mut Inner, key: Key, now: Instant) {
+        let segs = match inner.conns.get_mut(&key) {
+            Some(tcb) => tcb.output(now),
+            None => return,
+        };
+        self.send_segments(inner, *key.1.ip(), segs);
+    }
+
+    fn handle_packet(&self, bytes: &[u8]) {
+        let (src, dst, protocol, payload) = match packet::parse_ipv4(bytes) {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        let mut inner = self.inner.lock().unwrap();
+        if dst != inner.addr || protocol != packet::PROTOCOL_TCP {
+            return;
+        }
+        let seg = match Segment::decode(payload, src, dst) {
+            Ok(seg) => seg,
+            Err(_) => return,
+        };
+        let now = Instant::now();
+        let key = (seg.dst_port, SocketAddrV4::new(src, seg.src_port));
+
+        if let Some(tcb) = inner.conns.get_mut(&key) {
+            let was = tcb.state;
+            tcb.input(&seg, now);
+            let accepted = was == State::SynReceived && tcb.state != State::SynReceived && tcb.state != State::Closed;
+            if accepted {
+                if let Some(queue) = inner.listeners.get_mut(&key.0) {
+                    queue.push_back(key);
+                }
+            }
+        } else if seg.has(SYN) && !seg.has(ACK) && inner.listeners.contains_key(&seg.dst_port) {
+            let iss = inner.rng.next_u64() as u32;
+            let mut tcb = Tcb::new(SocketAddrV4::new(dst, seg.dst_port), key.1, iss, State::SynReceived);
+            tcb.synchronize(&seg);
+            // until accepted
+            tcb.detached = true;
+            inner.conns.insert(key, tcb);
+        } else if !seg.has(RST) {
+            // reset segments to nowhere (RFC 9293 section 3.10.7.1)
+            let mut rst = Segment {
+                src_port: seg.dst_port,
+                dst_port: seg.src_port,
+                ..Segment::default()
+            };
+            if seg.has(ACK) {
+                rst.seq = seg.ack;
+                rst.flags = RST;
+            } else {
+                rst.ack = seg.seq.wrapping_add(seg.seq_len());
+                rst.flags = RST | ACK;
+            }
+            self.send_segments(&mut inner, src, vec![rst]);
+            return;
+        }
+        self.flush(&mut inner, key, now);
+        self.cond.notify_all();
+    }
+
+    fn tick(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        let mut changed = false;
+        let keys: Vec<Key> = inner.conns.keys().copied().collect();
+        for key in keys {
+            let tcb = match inner.conns.get_mut(&key) {
+                Some(tcb) => tcb,
+                None => continue,
+            };
+            if tcb.deadline.is_some_and(|d| d <= now) {
+                tcb.on_timeout();
+                changed = true;
+            }
+            if tcb.time_wait_until.is_some_and(|t| t <= now) {
+                tcb.time_wait_until = None;
+                tcb.state = State::Closed;
+                changed = true;
+            }
+            if tcb.state == State::Closed && tcb.detached {
+                inner.conns.remove(&key);
+                continue;
+            }
+            self.flush(&mut inner, key, now);
+        }
+        if changed {
+            self.cond.notify_all();
+        }
+    }
+}
+
+fn run(shared: Weak<Shared>) {
+    while let Some(shared) = shared.upgrade() {
+        if let Ok(Some(packet)) = shared.device.recv(TICK) {
+            shared.handle_packet(&packet);
+        }
+        shared.tick();
+    }
+}
+
+// A TCP/IP stack with one IPv4 address on `device`.
+// Clones share the stack; it stops when the last clone and connection are dropped.
+#[derive(Clone)]
+pub struct Stack {
+    shared: Arc<Shared>,
+}
+
+impl Stack {
+    pub fn new(device: Arc<dyn PacketDevice>, addr: Ipv4Addr) -> Self {
+        let mut rng = Rng::from_time();
+        let range = (EPHEMERAL_PORTS.1 - EPHEMERAL_PORTS.0) as u64;
+        let next_port = EPHEMERAL_PORTS.0 + (rng.next_u64() % range) as u16;
+        let shared = Arc::new(Shared {
+            device,
+            inner: Mutex::new(Inner {
+                addr,
+                conns: HashMap::new(),
+                listeners: HashMap::new(),
+                rng,
+                next_port,
+                ip_id: 0,
+            }),
+            cond: Condvar::new(),
+        });
+        let weak = Arc::downgrade(&shared);
+        thread::spawn(move || run(weak));
+        Self { shared }
+    }
+
+    pub fn addr(&self) -> Ipv4Addr {
+        self.shared.inner.lock().unwrap().addr
+    }
+
+    pub fn listen(&self, port: u16) -> io::Result<Listener> {
+        let mut inner = self.shared.inner.lock().unwrap();
+        if inner.listeners.contains_key(&port) {
+            return Err(io::Error::from(ErrorKind::AddrInUse));
+        }
+        inner.listeners.insert(port, VecDeque::new());
+        Ok(Listener {
+            shared: self.shared.clone(),
+            local: SocketAddrV4::new(inner.addr, port),
+        })
+    }
+
+    // active open: block until the connection is established
+    pub fn connect(&self, remote: SocketAddrV4) -> io::Result<Connection> {
+        let mut inner = self.shared.inner.lock().unwrap();
+        let port = allocate_port(&mut inner).ok_or_else(|| io::Error::from(ErrorKind::AddrInUse))?;
+        let key = (port, remote);
+        let local = SocketAddrV4::new(inner.addr, port);
+        let iss = inner.rng.next_u64() as u32;
+        inner.conns.insert(key, Tcb::new(local, remote, iss, State::SynSent));
+        self.shared.flush(&mut inner, key, Instant::now());
+
+        loop {
+            let tcb = match inner.conns.get(&key) {
+                Some(tcb) => tcb,
+                None => return Err(io::Error::from(ErrorKind::ConnectionAborted)),
+            };
+            match tcb.state {
+                State::SynSent => inner = self.shared.cond.wait(inner).unwrap(),
+                State::Closed => {
+                    let kind = tcb.error.unwrap_or(ErrorKind::ConnectionRefused);
+                    inner.conns.remove(&key);
+                    return Err(io::Error::from(kind));
+                }
+                _ => return Ok(Connection::new(self.shared.clone(), key, local)),
+            }
+        }
+    }
+}
+
+fn allocate_port(inner: &mut Inner) -> Option<u16> {
+    for _ in EPHEMERAL_PORTS.0..EPHEMERAL_PORTS.1 {
+        let port = inner.next_port;
+        inner.next_port = if port + 1 >= EPHEMERAL_PORTS.1 { EPHEMERAL_PORTS.0 } else { port + 1 };
+        let used = inner.listeners.contains_key(&port) || inner.conns.keys().any(|(p, _)| *p == port);
+        if !used {
+            return Some(port);
+        }
+    }
+    None
+}
+
+pub struct Listener {
+    shared: Arc<Shared>,
+    local: SocketAddrV4,
+}
+
+impl Listener {
+    pub fn local_addr(&self) -> SocketAddrV4 {
+        self.local
+    }
+
+    // block until a connection is established
+    pub fn accept(&self) -> io::Result<Connection> {
+        let mut inner = self.shared.inner.lock().unwrap();
+        loop {
+            let key = inner.listeners.get_mut(&self.local.port()).and_then(|q| q.pop_front());
+            match key {
+                Some(key) => {
+                    // it may have been reset while waiting
+                    if let Some(tcb) = inner.conns.get_mut(&key) {
+                        tcb.detached = false;
+                        return Ok(Connection::new(self.shared.clone(), key, self.local));
+                    }
+                }
+                None => inner = self.shared.cond.wait(inner).unwrap(),
+            }
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.shared.inner.lock().unwrap().listeners.remove(&self.local.port());
+    }
+}
+
+// Dropping the connection closes it (like `close(2)`).
+pub struct Connection {
+    shared: Arc<Shared>,
+    key: Key,
+    local: SocketAddrV4,
+    read_timeout: Mutex<Option<Duration>>,
+}
+
+impl Connection {
+    fn new(shared: Arc<Shared>, key: Key, local: SocketAddrV4) -> Self {
+        Self {
+            shared,
+            key,
+            local,
+            read_timeout: Mutex::new(None),
+        }
+    }
+
+    pub fn local_addr(&self) -> SocketAddrV4 {
+        self.local
+    }
+
+    pub fn peer_addr(&self) -> SocketAddrV4 {
+        self.key.1
+    }
+
+    pub fn state(&self) -> State {
+        let inner = self.shared.inner.lock().unwrap();
+        inner.conns.get(&self.key).map_or(State::Closed, |tcb| tcb.state)
+    }
+
+    // `None` blocks forever (default)
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        *self.read_timeout.lock().unwrap() = timeout;
+        Ok(())
+    }
+
+    // send FIN after the buffered data; the connection can still receive
+    pub fn close(&self) -> io::Result<()> {
+        let mut inner = self.shared.inner.lock().unwrap();
+        if let Some(tcb) = inner.conns.get_mut(&self.key) {
+            tcb.close_requested = true;
+        }
+        self.shared.flush(&mut inner, self.key, Instant::now());
+        Ok(())
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let deadline = self.read_timeout.lock().unwrap().map(|t| Instant::now() + t);
+        let mut inner = self.shared.inner.lock().unwrap();
+        loop {
+            let tcb = match inner.conns.get_mut(&self.key) {
+                Some(tcb) => tcb,
+                None => return Ok(0),
+            };
+            if !tcb.recv_buf.is_empty() {
+                let n = buf.len().min(tcb.recv_buf.len());
+                for (dst, src) in buf.iter_mut().zip(tcb.recv_buf.drain(..n)) {
+                    *dst = src;
+                }
+                // window update
+                if tcb.window() >= tcb.advertised + tcb.mss {
+                    tcb.ack_pending = true;
+                }
+                self.shared.flush(&mut inner, self.key, Instant::now());
+                return Ok(n);
+            }
+            if let Some(kind) = tcb.error {
+                return Err(io::Error::from(kind));
+            }
+            if tcb.peer_closed || tcb.state == State::Closed {
+                return Ok(0);
+            }
+            inner = match deadline {
+                None => self.shared.cond.wait(inner).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(io::Error::from(ErrorKind::WouldBlock));
+                    }
+                    self.shared.cond.wait_timeout(inner, deadline - now).unwrap().0
+                }
+            };
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut inner = self.shared.inner.lock().unwrap();
+        loop {
+            let tcb = match inner.conns.get_mut(&self.key) {
+                Some(tcb) => tcb,
+                None => return Err(io::Error::from(ErrorKind::NotConnected)),
+            };
+            if let Some(kind) = tcb.error {
+                return Err(io::Error::from(kind));
+            }
+            if tcb.close_requested || !matches!(tcb.state, State::Established | State::CloseWait) {
+                return Err(io::Error::from(ErrorKind::BrokenPipe));
+            }
+            let room = SEND_BUFFER - tcb.send_buf.len();
+            if room > 0 {
+                let n = room.min(buf.len());
+                tcb.send_buf.extend(&buf[..n]);
+                self.shared.flush(&mut inner, self.key, Instant::now());
+                return Ok(n);
+            }
+            inner = self.shared.cond.wait(inner).unwrap();
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let mut inner = match self.shared.inner.lock() {
+            Ok(inner) => inner,
+            Err(_) => return,
+        };
+        if let Some(tcb) = inner.conns.get_mut(&self.key) {
+            tcb.close_requested = true;
+            tcb.detached = true;
+        }
+        self.shared.flush(&mut inner, self.key, Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tcp::device::{Impairment, LoopbackDevice};
+
+    fn stacks(impairment: Impairment) -> (Stack, Stack, Arc<LoopbackDevice>) {
+        let (a, b) = LoopbackDevice::pair(impairment);
+        let a = Arc::new(a);
+        (
+            Stack::new(a.clone(), Ipv4Addr::new(10, 0, 0, 1)),
+            Stack::new(Arc::new(b), Ipv4Addr::new(10, 0, 0, 2)),
+            a,
+        )
+    }
+
+    #[test]
+    fn test_echo() {
+        let (client, server, _) = stacks(Impairment::new());
+        let listener = server.listen(7).unwrap();
+        let handle = thread::spawn(move || {
+            let mut conn = listener.accept().unwrap();
+            let mut buf = vec![];
+            conn.read_to_end(&mut buf).unwrap();
+            assert_eq!(conn.state(), State::CloseWait);
+            conn.write_all(&buf).unwrap();
+        });
+
+        let mut conn = client.connect(SocketAddrV4::new(server.addr(), 7)).unwrap();
+        assert_eq!(conn.state(), State::Established);
+        assert_eq!(conn.peer_addr().port(), 7);
+        conn.write_all(b"hello, world").unwrap();
+        conn.close().unwrap();
+        let mut echo = String::new();
+        conn.read_to_string(&mut echo).unwrap();
+        assert_eq!(echo, "hello, world");
+        handle.join().unwrap();
+
+        // FIN-WAIT-2 -> TIME-WAIT -> CLOSED
+        thread::sleep(TIME_WAIT * 2);
+        assert_eq!(conn.state(), State::Closed);
+    }
+
+    #[test]
+    fn test_lossy_link() {
+        let impairment = Impairment::new().with_loss(0.05).with_reorder(0.1).with_seed(7);
+        let (client, server, device) = stacks(impairment);
+        let listener = server.listen(9).unwrap();
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let expected = data.clone();
+        let handle = thread::spawn(move || {
+            let mut conn = listener.accept().unwrap();
+            let mut buf = vec![];
+            conn.read_to_end(&mut buf).unwrap();
+            buf
+        });
+
+        let mut conn = client.connect(SocketAddrV4::new(server.addr(), 9)).unwrap();
+        conn.write_all(&data).unwrap();
+        drop(conn);
+        assert!(handle.join().unwrap() == expected);
+        assert!(device.dropped() > 0);
+    }
+
+    #[test]
+    fn test_refused() {
+        let (client, server, _) = stacks(Impairment::new());
+        let err = client.connect(SocketAddrV4::new(server.addr(), 80)).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+    }
+}
@@ -0,0 +1,623 @@
+// Interactive browser
+//
+// Shows pages full-screen in the pager (see `ui/pager.rs` for the keys). Links are numbered by the
+// Markdown generator; typing a number and Enter follows the link, `g` opens any URL.
+// `b` and `f` move through the session history without fetching pages again, and `H` lists it
+// (typing a number there jumps to that entry). Every page visited is also added to the global
+// history on disk; `a` bookmarks the page and `B` lists the bookmarks to open one by its number
+// (`12 d` removes the bookmark [12] and `/` filters the list by words, `#tag` matching tags only).
+//
+// Each tab has its own history. Pages are loaded by threads so the screen keeps answering keys,
+// and a link opened in a new tab loads in the background; the tab bar shows once there are two.
+// Every load gets its own client, and all of them share one cookie jar.
+//
+// ```
+// # Example Domain
+//
+// This domain is for use in illustrative examples in documents.
+//
+// [More information...][1]
+//
+// [1]: https://www.iana.org/domains/example
+//  Example Domain                                                                      All
+// ```
+
+mod history;
+mod pager;
+mod search;
+mod tabs;
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use aktis::http::Method;
+use aktis::http::client::{Body, Client};
+use aktis::http::cookie::CookieJar;
+use aktis::http::download;
+use aktis::http::request::{Head, Request};
+use aktis::http::response::ResponseHead;
+use aktis::http::url::URL;
+use aktis::renderer::dom::{self, Element};
+use aktis::renderer::markdown_generator::{self, Page};
+use aktis::utils::charset;
+use aktis::utils::term::{self, Key, KeyReader};
+use history::Entry;
+use pager::{Action, Pager};
+use tabs::Tabs;
+use crate::profile::{self, history as global_history};
+use crate::profile::bookmarks::{Bookmark, Bookmarks};
+
+// redirects followed for one page
+const MAX_REDIRECTS: usize = 10;
+
+// ERROR HANDLING ----------------------
+#[derive(Debug)]
+pub enum UiError {
+    NoLink(usize),
+    NotText(String),
+    TooManyRedirects(String),
+    NoProfile,
+}
+
+impl fmt::Display for UiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoLink(n) => write!(f, "UI error: there is no link [{}]", n),
+            Self::NotText(s) => write!(f, "UI error: `{}` is not text; save it with `aktis download`", s),
+            Self::TooManyRedirects(s) => write!(f, "UI error: too many redirects from {}", s),
+            Self::NoProfile => write!(f, "UI error: no directory for bookmarks; set $AKTIS_HOME"),
+        }
+    }
+}
+
+impl Error for UiError {}
+// ------------------------------------
+
+// what a URL gave
+pub enum Fetched {
+    Page(URL, Document),
+    // not meant to be displayed; the body is left unread
+    File(URL, ResponseHead, Body),
+}
+
+// a page as it came, before it is rendered
+pub enum Document {
+    Html(Element),
+    Text(String),
+}
+
+impl Document {
+    // the page as Markdown, as the pager shows it (never styled: see `ansi_generator` for that)
+    pub fn page(&self) -> Page {
+        match self {
+            Self::Html(document) => markdown_generator::generate(document),
+            Self::Text(text) => Page::from_text(text),
+        }
+    }
+}
+
+// browse from `page` (fetched from `url`) until the user quits, loading other pages with clients
+// from `new_client` (which must not prompt for passwords: that would break the full-screen view)
+pub fn run(cookies: CookieJar, new_client: fn() -> Client, url: URL, page: Page) -> Result<(), Box<dyn Error>> {
+    let _screen = Screen::enter()?;
+    let (sender, events) = mpsc::channel();
+    let keys = sender.clone();
+    thread::spawn(move || {
+        let mut reader = KeyReader::new(io::stdin());
+        loop {
+            let key = reader.read_key();
+            let failed = key.is_err();
+            if keys.send(Event::Key(key)).is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    let mut browser = Browser {
+        cookies: Arc::new(Mutex::new(cookies)),
+        new_client,
+        tabs: Tabs::new(),
+        pager: Pager::new(term::size()),
+        view: View::Page,
+        bookmarks: Bookmarks::new(),
+        filter: String::new(),
+        sender,
+        serial: 0,
+    };
+    browser.visit(browser.tabs.current().id(), url, page);
+
+    loop {
+        browser.draw()?;
+        match events.recv()? {
+            Event::Key(key) => {
+                let action = browser.pager.handle(key?);
+                if browser.handle(action) {
+                    return Ok(());
+                }
+            }
+            Event::Loaded { tab, serial, load, result } => browser.loaded(tab, serial, load, *result),
+        }
+    }
+}
+
+// raw mode on the alternate screen, restored on drop (also when panicking)
+struct Screen {
+    _raw: term::RawMode,
+}
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        let raw = term::raw_mode(term::STDIN)?;
+        let mut out = io::stdout();
+        write!(out, "\x1b[?1049h\x1b[H\x1b[2J")?;
+        out.flush()?;
+        Ok(Self { _raw: raw })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        let _ = write!(out, "\x1b[?25h\x1b[?1049l");
+        let _ = out.flush();
+    }
+}
+
+// what the browser waits for
+enum Event {
+    Key(io::Result<Key>),
+    // the load `serial` of the tab `tab` is over
+    Loaded {
+        tab: usize,
+        serial: u64,
+        load: Load,
+        // boxed as a file holds the whole response head
+        result: Box<Result<Fetched, String>>,
+    },
+}
+
+// why a page is loaded
+enum Load {
+    // to go to it
+    Visit,
+    // to show the history entry of this URL again (its page was not held any more)
+    Reload(URL),
+}
+
+// what the pager shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Page,
+    History,
+    Bookmarks,
+}
+
+struct Browser {
+    cookies: Arc<Mutex<CookieJar>>,
+    new_client: fn() -> Client,
+    tabs: Tabs,
+    pager: Pager,
+    view: View,
+    // as listed by `B`, and the words the list is filtered by
+    bookmarks: Bookmarks,
+    filter: String,
+    // for the threads loading pages
+    sender: mpsc::Sender<Event>,
+    // of the last load started
+    serial: u64,
+}
+
+impl Browser {
+    // whether to quit
+    fn handle(&mut self, action: Action) -> bool {
+        match action {
+            Action::None => {}
+            // `q` closes a list, as does the key that opened it
+            Action::Quit if self.view != View::Page => self.show(),
+            Action::History if self.view == View::History => self.show(),
+            Action::Bookmarks if self.view == View::Bookmarks => self.show(),
+            Action::Quit => return true,
+            Action::History => self.list_history(),
+            Action::Bookmarks => self.list_bookmarks(),
+            Action::Bookmark => self.bookmark(),
+            Action::Follow(n) => match self.view {
+                View::Page => self.follow(n, false),
+                View::History => self.go_history(n),
+                View::Bookmarks => self.go_bookmark(n, false),
+            },
+            Action::FollowInTab(n) => match self.view {
+                View::Page => self.follow(n, true),
+                View::History => {}
+                View::Bookmarks => self.go_bookmark(n, true),
+            },
+            Action::Remove(n) if self.view == View::Bookmarks => self.remove_bookmark(n),
+            Action::Filter(query) if self.view == View::Bookmarks => {
+                self.filter = query;
+                self.show_bookmarks();
+            }
+            Action::Remove(_) | Action::Filter(_) => {}
+            Action::Open(input) => {
+                if let Some(url) = self.parse(&input) {
+                    self.open(url);
+                }
+            }
+            Action::OpenInTab(input) => {
+                if let Some(url) = self.parse(&input) {
+                    self.open_tab(url, true);
+                }
+            }
+            Action::Back => self.back(),
+            Action::Forward => self.forward(),
+            Action::NextTab => self.switch(Tabs::next),
+            Action::PrevTab => self.switch(Tabs::prev),
+            Action::CloseTab => self.close_tab(),
+        }
+        false
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let size = term::size();
+        let bar = self.tabs.len() > 1;
+        self.pager.set_margin(bar as usize, size);
+        let title = match self.view {
+            View::History => "History".to_string(),
+            View::Bookmarks => "Bookmarks".to_string(),
+            View::Page => self.tabs.current().title(),
+        };
+        let mut out = io::stdout();
+        if bar {
+            write!(out, "\x1b[H{}\x1b[K", self.tabs.bar(size.0))?;
+        }
+        out.write_all(self.pager.draw(&title).as_bytes())?;
+        out.flush()
+    }
+
+    // a URL typed by the user (`None` after showing why it is wrong)
+    fn parse(&mut self, input: &str) -> Option<URL> {
+        match URL::parse(input) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                self.pager.set_message(&e.to_string());
+                None
+            }
+        }
+    }
+
+    fn follow(&mut self, n: usize, new_tab: bool) {
+        let entry = match self.tabs.current().history().current() {
+            Some(entry) => entry,
+            None => return,
+        };
+        let url = match entry.page().and_then(|page| page.link(n)) {
+            Some(href) => entry.url().join(href),
+            None => return self.pager.set_message(&UiError::NoLink(n).to_string()),
+        };
+        match url {
+            Ok(url) if new_tab => self.open_tab(url, false),
+            Ok(url) => self.open(url),
+            Err(e) => self.pager.set_message(&e.to_string()),
+        }
+    }
+
+    // load `url` in the current tab, which shows its page until the new one is there
+    fn open(&mut self, url: URL) {
+        self.load(self.tabs.current().id(), url, Load::Visit);
+    }
+
+    // load `url` in a new tab, and go to it if `select`
+    fn open_tab(&mut self, url: URL, select: bool) {
+        let id = self.tabs.open();
+        self.load(id, url, Load::Visit);
+        if select {
+            self.leave();
+            self.tabs.select(id);
+            self.show();
+        } else if let Some(i) = self.tabs.index(id) {
+            self.pager.set_message(&format!("opened in tab {}", i + 1));
+        }
+    }
+
+    // fetch `url` in a thread for the tab `id` (replacing its load going on)
+    fn load(&mut self, id: usize, url: URL, load: Load) {
+        self.serial += 1;
+        let serial = self.serial;
+        if let Some(tab) = self.tabs.get_mut(id) {
+            tab.set_loading(serial, url.clone());
+        }
+        let cookies = Arc::clone(&self.cookies);
+        let mut client = (self.new_client)();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result = request(&mut client, url, Some(&cookies)).and_then(|(url, head, body)| render(url, head, body));
+            let result = Box::new(result.map_err(|e| e.to_string()));
+            let _ = sender.send(Event::Loaded { tab: id, serial, load, result });
+        });
+    }
+
+    fn loaded(&mut self, id: usize, serial: u64, load: Load, result: Result<Fetched, String>) {
+        // dropped if the tab is closed or went on to load another page
+        if !self.tabs.get_mut(id).is_some_and(|tab| tab.finish_loading(serial)) {
+            return;
+        }
+        match (result, load) {
+            (Ok(Fetched::Page(url, document)), Load::Visit) => self.visit(id, url, document.page()),
+            (Ok(Fetched::Page(_, document)), Load::Reload(url)) => {
+                let page = document.page();
+                let entry = self.tabs.get_mut(id).and_then(|tab| tab.history_mut().current_mut());
+                match entry {
+                    Some(entry) if entry.url() == &url => entry.set_page(page),
+                    // the tab moved on to another entry
+                    _ => return,
+                }
+                if self.tabs.current().id() == id && self.view == View::Page {
+                    self.show();
+                }
+            }
+            (Ok(Fetched::File(_, head, _)), _) => {
+                let media_type = head.headers().content_type();
+                let media_type = media_type.map_or("application/octet-stream".to_string(), |m| m.essence());
+                self.report(id, &UiError::NotText(media_type).to_string());
+            }
+            (Err(e), _) => self.report(id, &e),
+        }
+    }
+
+    // show `message` about the tab `id` in the status line
+    fn report(&mut self, id: usize, message: &str) {
+        match self.tabs.index(id) {
+            Some(i) if i != self.tabs.position() => self.pager.set_message(&format!("tab {}: {}", i + 1, message)),
+            _ => self.pager.set_message(message),
+        }
+    }
+
+    fn visit(&mut self, id: usize, url: URL, page: Page) {
+        // browsing goes on without the global history
+        if let Some(path) = profile::history_path() {
+            let visit = global_history::Visit::new(&url.to_string(), page.title().unwrap_or_default());
+            let _ = global_history::record(path, &visit);
+        }
+        let current = self.tabs.current().id() == id;
+        if current {
+            self.leave();
+        }
+        if let Some(tab) = self.tabs.get_mut(id) {
+            tab.history_mut().visit(Entry::new(url, page));
+        }
+        if current {
+            self.show();
+        }
+    }
+
+    fn back(&mut self) {
+        self.leave();
+        if self.tabs.current_mut().history_mut().back().is_none() {
+            self.pager.set_message("no previous page");
+            return;
+        }
+        self.show();
+    }
+
+    fn forward(&mut self) {
+        self.leave();
+        if self.tabs.current_mut().history_mut().forward().is_none() {
+            self.pager.set_message("no next page");
+            return;
+        }
+        self.show();
+    }
+
+    // jump to the `n`-th entry of the history list
+    fn go_history(&mut self, n: usize) {
+        let history = self.tabs.current_mut().history_mut();
+        match n.checked_sub(1).and_then(|i| history.go(i)) {
+            Some(_) => self.show(),
+            None => self.pager.set_message(&UiError::NoLink(n).to_string()),
+        }
+    }
+
+    fn list_history(&mut self) {
+        self.leave();
+        self.view = View::History;
+        let mut lines = vec!["# History".to_string(), String::new()];
+        lines.extend(self.tabs.current().history().lines());
+        self.pager.set_lines(lines);
+    }
+
+    // add the current page to the bookmarks file (once; folders and tags are set with `aktis bookmarks`)
+    fn bookmark(&mut self) {
+        let entry = match self.tabs.current().history().current() {
+            Some(entry) => entry,
+            None => return,
+        };
+        let path = match profile::bookmarks_path() {
+            Some(path) => path,
+            None => return self.pager.set_message(&UiError::NoProfile.to_string()),
+        };
+        let url = entry.url().to_string();
+        let mut bookmarks = match Bookmarks::load(&path) {
+            Ok(bookmarks) => bookmarks,
+            Err(e) => return self.pager.set_message(&e.to_string()),
+        };
+        if let Some(bookmark) = bookmarks.get(&url) {
+            return self.pager.set_message(&format!("already bookmarked: {}", bookmark.summary()));
+        }
+        bookmarks.add(Bookmark::new(&url, entry.title().unwrap_or(&url)));
+        match bookmarks.save(&path) {
+            Ok(()) => self.pager.set_message(&format!("bookmarked {}", url)),
+            Err(e) => self.pager.set_message(&e.to_string()),
+        }
+    }
+
+    // the bookmarks numbered from 1, as `aktis bookmarks` lists them
+    fn list_bookmarks(&mut self) {
+        let path = match profile::bookmarks_path() {
+            Some(path) => path,
+            None => return self.pager.set_message(&UiError::NoProfile.to_string()),
+        };
+        self.bookmarks = match Bookmarks::load(path) {
+            Ok(bookmarks) => bookmarks,
+            Err(e) => return self.pager.set_message(&e.to_string()),
+        };
+        self.leave();
+        self.view = View::Bookmarks;
+        self.filter.clear();
+        self.show_bookmarks();
+    }
+
+    // the bookmarks matching the filter, keeping their numbers in the whole list
+    fn show_bookmarks(&mut self) {
+        let mut lines = match self.filter.as_str() {
+            "" => vec!["# Bookmarks".to_string(), String::new()],
+            filter => vec![format!("# Bookmarks: {}", filter), String::new()],
+        };
+        let found = self.bookmarks.entries().iter().enumerate().filter(|(_, b)| b.matches(&self.filter));
+        lines.extend(found.map(|(i, b)| format!("{:>3}. {}", i + 1, b.summary())));
+        let empty = lines.len() == 2;
+        self.pager.set_lines(lines);
+        self.pager.set_filter();
+        if empty && !self.filter.is_empty() {
+            self.pager.set_message("no bookmark matches; `/` Enter lists them all");
+        }
+    }
+
+    // remove the `n`-th bookmark of the list from the file
+    fn remove_bookmark(&mut self, n: usize) {
+        let url = match n.checked_sub(1).and_then(|i| self.bookmarks.entries().get(i)) {
+            Some(bookmark) => bookmark.url().to_string(),
+            None => return self.pager.set_message(&UiError::NoLink(n).to_string()),
+        };
+        let path = match profile::bookmarks_path() {
+            Some(path) => path,
+            None => return self.pager.set_message(&UiError::NoProfile.to_string()),
+        };
+        let mut bookmarks = match Bookmarks::load(&path) {
+            Ok(bookmarks) => bookmarks,
+            Err(e) => return self.pager.set_message(&e.to_string()),
+        };
+        // the file may have changed since it was listed
+        let removed = match bookmarks.remove(&url) {
+            Some(removed) => removed,
+            None => return self.pager.set_message(&format!("not bookmarked any more: {}", url)),
+        };
+        if let Err(e) = bookmarks.save(&path) {
+            return self.pager.set_message(&e.to_string());
+        }
+        self.bookmarks = bookmarks;
+        let top = self.pager.top();
+        self.show_bookmarks();
+        self.pager.set_top(top);
+        self.pager.set_message(&format!("removed {}", removed.summary()));
+    }
+
+    fn go_bookmark(&mut self, n: usize, new_tab: bool) {
+        let url = n.checked_sub(1).and_then(|i| self.bookmarks.entries().get(i)).map(|b| b.url().to_string());
+        let url = match url {
+            Some(url) => self.parse(&url),
+            None => return self.pager.set_message(&UiError::NoLink(n).to_string()),
+        };
+        match url {
+            Some(url) if new_tab => self.open_tab(url, false),
+            Some(url) => self.open(url),
+            None => {}
+        }
+    }
+
+    // go to another tab with `select`
+    fn switch(&mut self, select: fn(&mut Tabs)) {
+        self.leave();
+        select(&mut self.tabs);
+        self.show();
+    }
+
+    fn close_tab(&mut self) {
+        self.leave();
+        match self.tabs.close() {
+            Some(_) => self.show(),
+            None => self.pager.set_message("this is the last tab; `q` quits"),
+        }
+    }
+
+    // remember where the current page is scrolled to
+    fn leave(&mut self) {
+        if self.view != View::Page {
+            return;
+        }
+        if let Some(entry) = self.tabs.current_mut().history_mut().current_mut() {
+            entry.set_top(self.pager.top());
+        }
+    }
+
+    // show the current entry of the current tab, loading it again if its page is not held any more
+    fn show(&mut self) {
+        self.view = View::Page;
+        let tab = self.tabs.current();
+        let (lines, top) = match tab.history().current() {
+            Some(entry) => match entry.page() {
+                Some(page) => (page.lines(), entry.top()),
+                None if tab.loading().is_some() => (vec![], 0),
+                None => {
+                    let (id, url) = (tab.id(), entry.url().clone());
+                    self.load(id, url.clone(), Load::Reload(url));
+                    (vec![], 0)
+                }
+            },
+            None => (vec![], 0),
+        };
+        self.pager.set_lines(lines);
+        self.pager.set_top(top);
+    }
+}
+
+// GET `url` following redirects, and render it unless it is a file to save
+pub fn fetch(client: &mut Client, url: URL) -> Result<Fetched, Box<dyn Error>> {
+    let (url, head, body) = request(client, url, None)?;
+    render(url, head, body)
+}
+
+// GET `url` following redirects; the body of the last response is left unread
+// (with `cookies` shared by threads, the client sends and stores those instead of its own)
+fn request(
+    client: &mut Client,
+    mut url: URL,
+    cookies: Option<&Mutex<CookieJar>>,
+) -> Result<(URL, ResponseHead, Body), Box<dyn Error>> {
+    for _ in 0..=MAX_REDIRECTS {
+        if let Some(cookies) = cookies {
+            client.set_cookie_jar(cookies.lock().unwrap().clone());
+        }
+        let req = Request::new(&url, Method::GET, Head::new(&url.authority()), None);
+        let (head, body) = client.open(req)?;
+        if let Some(cookies) = cookies {
+            let mut cookies = cookies.lock().unwrap();
+            for set_cookie in head.headers().set_cookies() {
+                cookies.set_cookie(&url, set_cookie);
+            }
+        }
+        if head.status().is_redirection() {
+            if let Some(location) = head.headers().location() {
+                url = url.join(location)?;
+                continue;
+            }
+        }
+        return Ok((url, head, body));
+    }
+    Err(Box::new(UiError::TooManyRedirects(url.to_string())))
+}
+
+fn render(url: URL, head: ResponseHead, mut body: Body) -> Result<Fetched, Box<dyn Error>> {
+    let headers = head.headers();
+    if download::wants_download(headers) {
+        return Ok(Fetched::File(url, head, body));
+    }
+    let content_type = headers.content_type();
+    let mut bytes = vec![];
+    body.read_to_end(&mut bytes)?;
+    let text = charset::decode(&bytes, content_type.as_ref().and_then(|m| m.charset()));
+    let document = match content_type {
+        Some(m) if !m.is_html() => Document::Text(text),
+        _ => Document::Html(dom::parse(&text)),
+    };
+    Ok(Fetched::Page(url, document))
+}
@@ -0,0 +1,217 @@
+// Session history of the browser
+//
+// ```
+// back          current   forward
+// [A, B]        C         [E, D]      (the top of each stack is at the end)
+// ```
+//
+// Visiting a page pushes the current one onto `back` and forgets `forward`. Each entry remembers
+// where it was scrolled to, and keeps its rendered page while it is within `MAX_CACHED` steps of
+// the current one; the pages of older entries are fetched again.
+
+use aktis::http::url::URL;
+use aktis::renderer::markdown_generator::Page;
+
+const MAX_CACHED: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    url: URL,
+    title: Option<String>,
+    // first line shown
+    top: usize,
+    page: Option<Page>,
+}
+
+impl Entry {
+    pub fn new(url: URL, page: Page) -> Self {
+        Self {
+            url,
+            title: page.title().map(|t| t.to_string()),
+            top: 0,
+            page: Some(page),
+        }
+    }
+
+    pub fn url(&self) -> &URL {
+        &self.url
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    pub fn set_top(&mut self, top: usize) {
+        self.top = top;
+    }
+
+    // `None` if the page has to be fetched again
+    pub fn page(&self) -> Option<&Page> {
+        self.page.as_ref()
+    }
+
+    pub fn set_page(&mut self, page: Page) {
+        self.title = page.title().map(|t| t.to_string());
+        self.page = Some(page);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    back: Vec<Entry>,
+    current: Option<Entry>,
+    forward: Vec<Entry>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn current(&self) -> Option<&Entry> {
+        self.current.as_ref()
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut Entry> {
+        self.current.as_mut()
+    }
+
+    pub fn visit(&mut self, entry: Entry) {
+        if let Some(current) = self.current.replace(entry) {
+            self.back.push(current);
+        }
+        self.forward.clear();
+        self.evict();
+    }
+
+    // move one step back (`None` at the oldest entry)
+    pub fn back(&mut self) -> Option<&mut Entry> {
+        let entry = self.back.pop()?;
+        if let Some(current) = self.current.replace(entry) {
+            self.forward.push(current);
+        }
+        self.evict();
+        self.current.as_mut()
+    }
+
+    // move one step forward (`None` at the newest entry)
+    pub fn forward(&mut self) -> Option<&mut Entry> {
+        let entry = self.forward.pop()?;
+        if let Some(current) = self.current.replace(entry) {
+            self.back.push(current);
+        }
+        self.evict();
+        self.current.as_mut()
+    }
+
+    // jump to the `index`-th entry of `entries()`
+    pub fn go(&mut self, index: usize) -> Option<&mut Entry> {
+        if index >= self.len() {
+            return None;
+        }
+        while self.back.len() > index {
+            self.back();
+        }
+        while self.back.len() < index {
+            self.forward();
+        }
+        self.current.as_mut()
+    }
+
+    // all entries from the oldest
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.back.iter().chain(self.current.iter()).chain(self.forward.iter().rev())
+    }
+
+    // index of the current entry in `entries()`
+    pub fn position(&self) -> usize {
+        self.back.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.back.len() + self.current.iter().len() + self.forward.len()
+    }
+
+    // the history list numbered from 1, the current entry marked with `*`
+    //
+    // ```
+    //    1. Example Domain  http://example.com/
+    // *  2. IANA  https://www.iana.org/
+    // ```
+    pub fn lines(&self) -> Vec<String> {
+        self.entries()
+            .enumerate()
+            .map(|(i, entry)| {
+                let mark = if i == self.position() { '*' } else { ' ' };
+                let title = entry.title().unwrap_or("(untitled)");
+                format!("{} {:>3}. {}  {}", mark, i + 1, title, entry.url())
+            })
+            .collect()
+    }
+
+    // forget the pages of entries too far from the current one
+    fn evict(&mut self) {
+        for entry in self.back.iter_mut().rev().skip(MAX_CACHED) {
+            entry.page = None;
+        }
+        for entry in self.forward.iter_mut().rev().skip(MAX_CACHED) {
+            entry.page = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(path: &str) -> Entry {
+        let url = URL::parse(&format!("http://example.com{}", path)).unwrap();
+        Entry::new(url, Page::from_html(&format!("<title>{}</title>", path)))
+    }
+
+    fn paths(history: &History) -> Vec<String> {
+        history.entries().map(|e| e.url().path()).collect()
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = History::new();
+        assert!(history.back().is_none());
+        for path in ["/a", "/b", "/c"] {
+            history.visit(entry(path));
+        }
+        history.current_mut().unwrap().set_top(5);
+
+        assert_eq!(history.back().unwrap().url().path(), "/b");
+        assert_eq!(history.back().unwrap().url().path(), "/a");
+        assert!(history.back().is_none());
+        let c = history.go(2).unwrap();
+        assert_eq!((c.url().path(), c.top()), ("/c".to_string(), 5));
+        assert!(history.forward().is_none());
+
+        // visiting drops the forward entries
+        history.go(0);
+        history.visit(entry("/d"));
+        assert_eq!(paths(&history), vec!["/a", "/d"]);
+        assert!(history.forward().is_none());
+        assert_eq!(history.lines(), vec!["    1. /a  http://example.com/a", "*   2. /d  http://example.com/d"]);
+    }
+
+    #[test]
+    fn test_evict() {
+        let mut history = History::new();
+        for i in 0..MAX_CACHED + 3 {
+            history.visit(entry(&format!("/{}", i)));
+        }
+        let cached = history.entries().filter(|e| e.page().is_some()).count();
+        assert_eq!(cached, MAX_CACHED + 1);
+        assert!(history.go(0).unwrap().page().is_none());
+        // going back evicted the newest pages, the ones near the oldest are still there
+        assert_eq!(history.entries().last().unwrap().page(), None);
+        assert!(history.go(5).unwrap().page().is_some());
+    }
+}
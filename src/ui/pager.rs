@@ -0,0 +1,666 @@
+// Scrolling view of a page with a status line below it
+//
+// ```
+// j, Down, Enter     one line down       Space, PgDn, Ctrl-F    one screen down
+// k, Up              one line up         PgUp, Ctrl-B           one screen up
+// Home, <            top                 End, >, G              bottom
+// 12 Enter           follow link [12]    g                      open a URL
+// b                  back                f                      forward
+// H                  history list        q, Ctrl-C              quit
+// a                  bookmark the page   B                      bookmark list
+// 12 t               link [12] in a new tab (loaded in the background)
+// 12 d               remove bookmark [12] (in the bookmark list)
+// T                  open a URL in a new tab
+// Tab, ]             next tab            [                      previous tab
+// x                  close the tab
+// /, ?               search forward, backward (see `ui/search.rs`);
+//                    `/` filters the bookmark list instead
+// n, N               next, previous match
+// ```
+//
+// The pager only keeps the view state; what to do with links and URLs is up to the caller.
+
+use aktis::renderer::layout;
+use aktis::utils::term::Key;
+use aktis::utils::width::{self, char_width};
+use super::search::{self, Hit, Search};
+
+const TAB_WIDTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Quit,
+    // link number, as typed
+    Follow(usize),
+    FollowInTab(usize),
+    Open(String),
+    OpenInTab(String),
+    Back,
+    Forward,
+    History,
+    Bookmark,
+    Bookmarks,
+    // bookmark number, as typed
+    Remove(usize),
+    // words typed after `/` in a filtered list
+    Filter(String),
+    NextTab,
+    PrevTab,
+    CloseTab,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    // digits of a link number
+    Link(String),
+    // line being typed after `g`, `T` or `/` (in a filtered list)
+    Prompt { input: String, kind: Input },
+    // pattern being typed after `/` or `?`, and the first line shown before
+    Search { search: Search, origin: usize },
+}
+
+// what the line typed at the prompt is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Input {
+    Url,
+    UrlInTab,
+    Filter,
+}
+
+// a row of the screen: the part of the line `line` from the character `start`, after `indent`
+struct Row {
+    text: String,
+    indent: String,
+    line: usize,
+    start: usize,
+}
+
+pub struct Pager {
+    // lines of the page as given
+    source: Vec<String>,
+    // the same lines wrapped to the width
+    lines: Vec<Row>,
+    top: usize,
+    width: usize,
+    // rows for the page (the last row of the terminal is the status line)
+    height: usize,
+    // rows above the page left to the caller
+    margin: usize,
+    mode: Mode,
+    message: Option<String>,
+    // whether `/` filters the lines (by the caller) instead of searching them
+    filter: bool,
+    // the last search, its matches in `source` and the one shown
+    search: Search,
+    hits: Vec<Hit>,
+    current: Option<usize>,
+}
+
+impl Pager {
+    // `size`: (columns, rows) of the terminal
+    pub fn new(size: (usize, usize)) -> Self {
+        let mut pager = Self {
+            source: vec![],
+            lines: vec![],
+            top: 0,
+            width: 0,
+            height: 0,
+            margin: 0,
+            mode: Mode::Normal,
+            message: None,
+            filter: false,
+            search: Search::default(),
+            hits: vec![],
+            current: None,
+        };
+        pager.resize(size);
+        pager
+    }
+
+    // show another page from the top (the last search goes on in it, and `/` searches again)
+    pub fn set_lines(&mut self, lines: Vec<String>) {
+        self.message = None;
+        self.filter = false;
+        self.source = lines.iter().map(|l| sanitize(l)).collect();
+        self.top = 0;
+        self.layout();
+        self.find();
+    }
+
+    pub fn resize(&mut self, (width, rows): (usize, usize)) {
+        let (width, height) = (width.max(1), rows.saturating_sub(1 + self.margin).max(1));
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.layout();
+        }
+    }
+
+    // leave the first `rows` rows of the terminal (for a tab bar)
+    pub fn set_margin(&mut self, rows: usize, size: (usize, usize)) {
+        if rows != self.margin {
+            self.margin = rows;
+            // forces the layout
+            self.height = 0;
+        }
+        self.resize(size);
+    }
+
+    // first line shown
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    // scroll to `top` (as far as the page goes)
+    pub fn set_top(&mut self, top: usize) {
+        self.top = top.min(self.max_top());
+    }
+
+    // let `/` ask for words to filter the lines with (`Action::Filter`) until the next `set_lines`
+    pub fn set_filter(&mut self) {
+        self.filter = true;
+    }
+
+    // shown in the status line until the next key
+    pub fn set_message(&mut self, message: &str) {
+        self.message = Some(message.to_string());
+    }
+
+    pub fn handle(&mut self, key: Key) -> Action {
+        self.message = None;
+        let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+        match mode {
+            Mode::Normal => self.normal(key),
+            Mode::Link(digits) => self.link(digits, key),
+            Mode::Prompt { input, kind } => self.prompt(input, kind, key),
+            Mode::Search { search, origin } => self.search(search, origin, key),
+        }
+    }
+
+    fn normal(&mut self, key: Key) -> Action {
+        let page = self.height as isize;
+        match key {
+            Key::Char('q') | Key::Ctrl('c') => return Action::Quit,
+            Key::Char('b') => return Action::Back,
+            Key::Char('f') => return Action::Forward,
+            Key::Char('H') => return Action::History,
+            Key::Char('a') => return Action::Bookmark,
+            Key::Char('B') => return Action::Bookmarks,
+            Key::Tab | Key::Char(']') => return Action::NextTab,
+            Key::Char('[') => return Action::PrevTab,
+            Key::Char('x') => return Action::CloseTab,
+            Key::Char('j') | Key::Down | Key::Enter => self.scroll(1),
+            Key::Char('k') | Key::Up => self.scroll(-1),
+            Key::Char(' ') | Key::PageDown | Key::Ctrl('f') => self.scroll(page),
+            Key::PageUp | Key::Ctrl('b') => self.scroll(-page),
+            Key::Char('<') | Key::Home => self.top = 0,
+            Key::Char('>') | Key::Char('G') | Key::End => self.top = self.max_top(),
+            Key::Char('g') => self.mode = Mode::Prompt { input: String::new(), kind: Input::Url },
+            Key::Char('T') => self.mode = Mode::Prompt { input: String::new(), kind: Input::UrlInTab },
+            Key::Char('/') if self.filter => self.mode = Mode::Prompt { input: String::new(), kind: Input::Filter },
+            Key::Char('/') => self.mode = Mode::Search { search: Search::new(false), origin: self.top },
+            Key::Char('?') => self.mode = Mode::Search { search: Search::new(true), origin: self.top },
+            Key::Char('n') => self.next(false),
+            Key::Char('N') => self.next(true),
+            Key::Char(c) if c.is_ascii_digit() => self.mode = Mode::Link(c.to_string()),
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn link(&mut self, mut digits: String, key: Key) -> Action {
+        match key {
+            Key::Char(c) if c.is_ascii_digit() => digits.push(c),
+            Key::Backspace => {
+                digits.pop();
+                if digits.is_empty() {
+                    return Action::None;
+                }
+            }
+            Key::Enter => return digits.parse().map_or(Action::None, Action::Follow),
+            Key::Char('t') => return digits.parse().map_or(Action::None, Action::FollowInTab),
+            Key::Char('d') => return digits.parse().map_or(Action::None, Action::Remove),
+            // anything else cancels
+            _ => return Action::None,
+        }
+        self.mode = Mode::Link(digits);
+        Action::None
+    }
+
+    fn prompt(&mut self, mut input: String, kind: Input, key: Key) -> Action {
+        match key {
+            Key::Char(c) => input.push(c),
+            Key::Backspace => {
+                input.pop();
+            }
+            Key::Ctrl('u') => input.clear(),
+            Key::Enter => {
+                let input = input.trim().to_string();
+                return match kind {
+                    // no words show the whole list again
+                    Input::Filter => Action::Filter(input),
+                    _ if input.is_empty() => Action::None,
+                    Input::Url => Action::Open(input),
+                    Input::UrlInTab => Action::OpenInTab(input),
+                };
+            }
+            Key::Escape | Key::Ctrl('c') | Key::Ctrl('g') => return Action::None,
+            _ => {}
+        }
+        self.mode = Mode::Prompt { input, kind };
+        Action::None
+    }
+
+    fn search(&mut self, mut search: Search, origin: usize, key: Key) -> Action {
+        match key {
+            Key::Char(c) => search.text_mut().push(c),
+            Key::Backspace => {
+                search.text_mut().pop();
+            }
+            Key::Ctrl('u') => search.text_mut().clear(),
+            Key::Ctrl('r') => search.toggle_regex(),
+            Key::Ctrl('s') => search.toggle_case(),
+            Key::Enter => {
+                self.confirm(search, origin);
+                return Action::None;
+            }
+            Key::Escape | Key::Ctrl('c') | Key::Ctrl('g') => {
+                // back to the last search
+                self.top = origin;
+                self.find();
+                return Action::None;
+            }
+            _ => {}
+        }
+
+        // follow the first match as it is typed
+        self.top = origin;
+        self.hits = match search.pattern() {
+            Ok(Some(pattern)) => search::find(&pattern, &self.source),
+            _ => vec![],
+        };
+        self.current = self.first_from(origin, search.backward()).map(|(i, _)| i);
+        if let Some(i) = self.current {
+            self.reveal(i);
+        }
+        self.mode = Mode::Search { search, origin };
+        Action::None
+    }
+
+    // keep `search` (an empty one repeats the last search in its direction)
+    fn confirm(&mut self, search: Search, origin: usize) {
+        if search.text().is_empty() {
+            let backward = search.backward();
+            self.search.set_backward(backward);
+        } else {
+            self.search = search;
+        }
+        self.top = origin;
+        self.find();
+        if let Err(e) = self.search.pattern() {
+            return self.set_message(&e.to_string());
+        }
+        match self.first_from(origin, self.search.backward()) {
+            Some((i, wrapped)) => self.show_hit(i, wrapped),
+            None => self.not_found(),
+        }
+    }
+
+    // `n` (or `N` with `reverse`): the match after the one shown, or the first one from the screen
+    fn next(&mut self, reverse: bool) {
+        let backward = self.search.backward() != reverse;
+        let n = self.hits.len();
+        let shown = self.current.filter(|&i| (self.top..self.top + self.height).contains(&self.hit_row(i)));
+        let next = match shown {
+            Some(0) if backward => Some((n - 1, true)),
+            Some(i) if backward => Some((i - 1, false)),
+            Some(i) if i + 1 == n => Some((0, true)),
+            Some(i) => Some((i + 1, false)),
+            // the first row is searched forward, the rows above it backward
+            None if backward => self.first_from(self.top, true),
+            None => self.first_from(self.top, false),
+        };
+        match next {
+            Some((i, wrapped)) => self.show_hit(i, wrapped),
+            None => self.not_found(),
+        }
+    }
+
+    // the first match from the row `row` on (or before it if `backward`), and whether the search
+    // went around the end of the page to find it
+    fn first_from(&self, row: usize, backward: bool) -> Option<(usize, bool)> {
+        let last = self.hits.len().checked_sub(1)?;
+        let i = (0..self.hits.len()).position(|i| self.hit_row(i) >= row);
+        Some(match i {
+            Some(0) if backward => (last, true),
+            Some(i) if backward => (i - 1, false),
+            None if backward => (last, false),
+            Some(i) => (i, false),
+            None => (0, true),
+        })
+    }
+
+    fn show_hit(&mut self, i: usize, wrapped: bool) {
+        self.current = Some(i);
+        self.reveal(i);
+        let wrapped = if wrapped { " (search wrapped)" } else { "" };
+        self.set_message(&format!("{} {}/{}{}", self.search.prompt(), i + 1, self.hits.len(), wrapped));
+    }
+
+    fn not_found(&mut self) {
+        self.current = None;
+        match self.search.text() {
+            "" => self.set_message("no previous search"),
+            _ => self.set_message(&format!("pattern not found: {}", self.search.prompt())),
+        }
+    }
+
+    // scroll to the match `i` unless it is on the screen
+    fn reveal(&mut self, i: usize) {
+        let row = self.hit_row(i);
+        if row < self.top || row >= self.top + self.height {
+            self.top = row.min(self.max_top());
+        }
+    }
+
+    // the row where the match `i` starts
+    fn hit_row(&self, i: usize) -> usize {
+        let hit = self.hits[i];
+        let after = self.lines.partition_point(|r| (r.line, r.start) <= (hit.line, hit.start));
+        after.saturating_sub(1)
+    }
+
+    // the matches of the last search in the page
+    fn find(&mut self) {
+        self.hits = match self.search.pattern() {
+            Ok(Some(pattern)) => search::find(&pattern, &self.source),
+            _ => vec![],
+        };
+        self.current = None;
+    }
+
+    fn scroll(&mut self, n: isize) {
+        let top = self.top as isize + n;
+        self.top = top.clamp(0, self.max_top() as isize) as usize;
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+
+    fn layout(&mut self) {
+        let width = self.width;
+        let source = &self.source;
+        self.lines = layout::rows(source, width)
+            .into_iter()
+            .flat_map(|row| {
+                let chars = source[row.line].chars().skip(row.start).take(row.end - row.start).collect::<Vec<_>>();
+                // what is not wrapped (code, tables) is cut at the edge of the screen
+                let text = chars.iter().collect::<String>();
+                let room = width.saturating_sub(width::width(&row.indent)).max(1);
+                layout::cut(&text, room).into_iter().map(move |(start, end)| Row {
+                    text: chars[start..end].iter().collect(),
+                    indent: row.indent.clone(),
+                    line: row.line,
+                    start: row.start + start,
+                })
+            })
+            .collect();
+        self.top = self.top.min(self.max_top());
+    }
+
+    // `row` with the matches in it in reverse video (the one shown also bold and underlined)
+    fn highlight(&self, row: &Row) -> String {
+        let chars = row.text.chars().collect::<Vec<_>>();
+        let mut out = row.indent.clone();
+        let mut pos = 0;
+        let first = self.hits.partition_point(|h| h.line < row.line);
+        for (i, hit) in self.hits.iter().enumerate().skip(first).take_while(|(_, h)| h.line == row.line) {
+            let start = hit.start.saturating_sub(row.start);
+            let end = hit.end.saturating_sub(row.start).min(chars.len());
+            if start >= end {
+                continue;
+            }
+            out.extend(&chars[pos..start]);
+            out.push_str(if self.current == Some(i) { "\x1b[1;4;7m" } else { "\x1b[7m" });
+            out.extend(&chars[start..end]);
+            out.push_str("\x1b[m");
+            pos = end;
+        }
+        out.extend(&chars[pos..]);
+        out
+    }
+
+    // escape sequences redrawing the whole screen; `title` goes to the status line
+    pub fn draw(&self, title: &str) -> String {
+        let mut out = format!("\x1b[{};1H", self.margin + 1);
+        for row in 0..self.height {
+            if let Some(row) = self.lines.get(self.top + row) {
+                out.push_str(&self.highlight(row));
+            }
+            out.push_str("\x1b[K\r\n");
+        }
+
+        match &self.mode {
+            // the cursor stays at the end of the input
+            Mode::Link(digits) => out.push_str(&format!("link: {}\x1b[K\x1b[?25h", digits)),
+            Mode::Prompt { input, kind } => {
+                let label = match kind {
+                    Input::Url => "URL",
+                    Input::UrlInTab => "URL (new tab)",
+                    Input::Filter => "filter",
+                };
+                let prompt = format!("{}: {}", label, input);
+                out.push_str(&self.input_line(&prompt));
+            }
+            Mode::Search { search, .. } => out.push_str(&self.input_line(&search.prompt())),
+            Mode::Normal => {
+                // the title comes from the page, so it may hide escape sequences too
+                let left = sanitize(self.message.as_deref().unwrap_or(title));
+                let right = format!(" {} ", self.position());
+                let room = self.width.saturating_sub(width::width(&right));
+                let left = format!(" {}", left);
+                let left = width::head(&left, room);
+                let fill = " ".repeat(room - width::width(left));
+                let status = format!("{}{}{}", left, fill, right);
+                let status = width::head(&status, self.width);
+                // reverse video
+                out.push_str(&format!("\x1b[7m{}\x1b[m\x1b[?25l", status));
+            }
+        }
+        out
+    }
+
+    // the end of `prompt` (keeping the end of a long input in sight), with the cursor after it
+    fn input_line(&self, prompt: &str) -> String {
+        format!("{}\x1b[K\x1b[?25h", width::tail(prompt, self.width - 1))
+    }
+
+    // `Top`, `Bot`, `All` or a percentage, like less(1)
+    fn position(&self) -> String {
+        let bottom = self.top + self.height;
+        if bottom >= self.lines.len() {
+            if self.top == 0 { "All".to_string() } else { "Bot".to_string() }
+        } else if self.top == 0 {
+            "Top".to_string()
+        } else {
+            format!("{}%", bottom * 100 / self.lines.len())
+        }
+    }
+}
+
+// expand tabs and drop other control characters (a page must not send escape sequences)
+pub(crate) fn sanitize(line: &str) -> String {
+    let mut out = String::new();
+    let mut col = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let n = TAB_WIDTH - col % TAB_WIDTH;
+            out.push_str(&" ".repeat(n));
+            col += n;
+        } else if !c.is_control() {
+            out.push(c);
+            col += char_width(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pager(lines: usize) -> Pager {
+        let mut pager = Pager::new((20, 6));
+        pager.set_lines((1..=lines).map(|n| format!("line {}", n)).collect());
+        pager
+    }
+
+    fn wrap(line: &str, width: usize) -> Vec<String> {
+        let chars = line.chars().collect::<Vec<_>>();
+        layout::wrap(line, width).into_iter().map(|(start, end)| chars[start..end].iter().collect()).collect()
+    }
+
+    fn typed(pager: &mut Pager, keys: &str) -> Action {
+        let mut action = Action::None;
+        for c in keys.chars() {
+            action = pager.handle(if c == '\n' { Key::Enter } else { Key::Char(c) });
+        }
+        action
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut pager = pager(12);
+        assert_eq!(pager.position(), "Top");
+        typed(&mut pager, "jjk");
+        assert_eq!(pager.top, 1);
+        pager.handle(Key::PageDown);
+        assert_eq!(pager.top, 6);
+        typed(&mut pager, " ");
+        // the last screen is full
+        assert_eq!(pager.top, 7);
+        assert_eq!(pager.position(), "Bot");
+        pager.handle(Key::Home);
+        pager.handle(Key::PageUp);
+        assert_eq!(pager.top, 0);
+        assert_eq!(pager.handle(Key::Char('q')), Action::Quit);
+
+        let screen = pager.draw("Title");
+        assert!(screen.starts_with("\x1b[1;1Hline 1\x1b[K\r\nline 2"));
+        assert!(screen.contains("\x1b[7m Title          Top \x1b[m"));
+        pager.set_top(100);
+        assert_eq!(pager.top(), 7);
+    }
+
+    #[test]
+    fn test_input() {
+        let mut pager = pager(3);
+        assert_eq!(typed(&mut pager, "12\n"), Action::Follow(12));
+        typed(&mut pager, "3");
+        assert!(pager.draw("").contains("link: 3"));
+        pager.handle(Key::Backspace);
+        assert_eq!(pager.mode, Mode::Normal);
+        assert_eq!(typed(&mut pager, "gexample.com/\n"), Action::Open("example.com/".to_string()));
+        typed(&mut pager, "gabc");
+        assert_eq!(pager.handle(Key::Escape), Action::None);
+        // `q` quits again once the prompt is gone
+        assert_eq!(typed(&mut pager, "q"), Action::Quit);
+
+        assert_eq!(typed(&mut pager, "7t"), Action::FollowInTab(7));
+        assert_eq!(typed(&mut pager, "Tb.example/\n"), Action::OpenInTab("b.example/".to_string()));
+        assert_eq!(typed(&mut pager, "12d"), Action::Remove(12));
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut pager = pager(3);
+        pager.set_filter();
+        typed(&mut pager, "/#rust");
+        assert!(pager.draw("").contains("filter: #rust"));
+        assert_eq!(typed(&mut pager, "\n"), Action::Filter("#rust".to_string()));
+        assert_eq!(typed(&mut pager, "/\n"), Action::Filter(String::new()));
+        // other lines are searched again
+        pager.set_lines(vec!["line 1".to_string()]);
+        assert_eq!(typed(&mut pager, "/line\n"), Action::None);
+        assert_eq!(pager.hits.len(), 1);
+    }
+
+    #[test]
+    fn test_margin() {
+        let mut pager = pager(12);
+        pager.set_margin(1, (20, 6));
+        assert_eq!(pager.height, 4);
+        assert!(pager.draw("").starts_with("\x1b[2;1Hline 1"));
+        typed(&mut pager, ">");
+        assert_eq!(pager.top, 8);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut pager = Pager::new((10, 4));
+        let mut lines = (1..=8).map(|n| format!("line {}", n)).collect::<Vec<_>>();
+        lines.push("日本語の文章を折り返す".to_string());
+        pager.set_lines(lines);
+
+        // incremental: `l` is on the screen, `line 7` is not
+        typed(&mut pager, "/l");
+        assert_eq!((pager.top, pager.current), (0, Some(0)));
+        typed(&mut pager, "ine 7");
+        assert_eq!((pager.top, pager.current), (6, Some(0)));
+        pager.handle(Key::Escape);
+        assert_eq!((pager.top, pager.hits.len()), (0, 0));
+
+        // a match over the break of a wrapped line is shown on both rows
+        typed(&mut pager, "/折り返す\n");
+        assert_eq!(pager.top, 8);
+        assert_eq!(pager.message.as_deref(), Some("/折り返す 1/1"));
+        let screen = pager.draw("");
+        assert!(screen.contains("章を\x1b[1;4;7m折り返\x1b[m\x1b[K\r\n\x1b[1;4;7mす\x1b[m"));
+
+        typed(&mut pager, "/");
+        pager.handle(Key::Ctrl('r'));
+        typed(&mut pager, "line [2-3]\n");
+        assert_eq!((pager.current, pager.top), (Some(0), 1));
+        assert_eq!(pager.message.as_deref(), Some("/(regex)line [2-3] 1/2 (search wrapped)"));
+        typed(&mut pager, "n");
+        assert_eq!(pager.message.as_deref(), Some("/(regex)line [2-3] 2/2"));
+        typed(&mut pager, "nN");
+        assert_eq!(pager.current, Some(1));
+        // an empty pattern repeats the last one, here backward from the top of the screen
+        typed(&mut pager, "?\n");
+        assert_eq!(pager.message.as_deref(), Some("?(regex)line [2-3] 2/2 (search wrapped)"));
+
+        typed(&mut pager, "/LINE 1\n");
+        assert_eq!(pager.hits.len(), 1);
+        typed(&mut pager, "/");
+        pager.handle(Key::Ctrl('s'));
+        typed(&mut pager, "LINE 1\n");
+        assert_eq!(pager.message.as_deref(), Some("pattern not found: /(case)LINE 1"));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("a quick brown fox", 7), vec!["a quick", "brown", "fox"]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("", 4), vec![""]);
+        assert_eq!(sanitize("a\tb\x1b[2Jc"), "a       b[2Jc");
+    }
+
+    #[test]
+    fn test_title() {
+        use aktis::renderer::{dom, markdown_generator};
+
+        let page = markdown_generator::generate(&dom::parse("<title>x&#27;]0;pwn&#7;</title>"));
+        let title = page.title().unwrap();
+        assert!(title.contains('\x1b'));
+        let mut pager = pager(3);
+        let screen = pager.draw(title);
+        assert!(screen.contains("\x1b[7m x]0;pwn "));
+        assert!(!screen.contains('\x07'));
+        pager.set_message("\x1b[2Jgone");
+        assert!(pager.draw(title).contains("\x1b[7m [2Jgone "));
+    }
+}
@@ -0,0 +1,124 @@
+// In-page search
+//
+// ```
+// /rust        forward from the top of the screen     ?rust        backward
+// Ctrl-R       regular expression on/off               Ctrl-S       case on/off (ignored by default)
+// n            next match                               N            previous match
+// ```
+//
+// The view follows the first match as the pattern is typed; Enter keeps the search and Escape
+// goes back to where it started. Matches are found in the lines before they are wrapped to the
+// screen, so a match may go on over the next row.
+
+use aktis::utils::pattern::{Pattern, PatternError};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Search {
+    text: String,
+    backward: bool,
+    regex: bool,
+    case: bool,
+}
+
+impl Search {
+    pub fn new(backward: bool) -> Self {
+        Self {
+            backward,
+            ..Self::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn text_mut(&mut self) -> &mut String {
+        &mut self.text
+    }
+
+    pub fn backward(&self) -> bool {
+        self.backward
+    }
+
+    pub fn set_backward(&mut self, backward: bool) {
+        self.backward = backward;
+    }
+
+    pub fn toggle_regex(&mut self) {
+        self.regex = !self.regex;
+    }
+
+    pub fn toggle_case(&mut self) {
+        self.case = !self.case;
+    }
+
+    // `None` for an empty pattern
+    pub fn pattern(&self) -> Result<Option<Pattern>, PatternError> {
+        if self.text.is_empty() {
+            return Ok(None);
+        }
+        if self.regex {
+            Ok(Some(Pattern::regex(&self.text, !self.case)?))
+        } else {
+            Ok(Some(Pattern::literal(&self.text, !self.case)))
+        }
+    }
+
+    // `/rust`, `?(regex,case)ru.t`
+    pub fn prompt(&self) -> String {
+        let flags = [(self.regex, "regex"), (self.case, "case")]
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+        let flags = if flags.is_empty() { String::new() } else { format!("({})", flags.join(",")) };
+        format!("{}{}{}", if self.backward { '?' } else { '/' }, flags, self.text)
+    }
+}
+
+// a match: index of the line and character range in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hit {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+// all the matches in `lines`, in order
+pub fn find(pattern: &Pattern, lines: &[String]) -> Vec<Hit> {
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(line, text)| {
+            pattern
+                .find_all(text)
+                .into_iter()
+                .map(move |(start, end)| Hit { line, start, end })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let lines = vec!["Rust and rust".to_string(), "錆 (さび) は rust".to_string()];
+        let mut search = Search::new(false);
+        assert_eq!(search.pattern(), Ok(None));
+        search.text_mut().push_str("rust");
+        let hits = find(&search.pattern().unwrap().unwrap(), &lines);
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[2], Hit { line: 1, start: 9, end: 13 });
+
+        search.toggle_case();
+        assert_eq!(find(&search.pattern().unwrap().unwrap(), &lines).len(), 2);
+        search.toggle_regex();
+        *search.text_mut() = "\\(.+\\)".to_string();
+        assert_eq!(search.prompt(), "/(regex,case)\\(.+\\)");
+        assert_eq!(find(&search.pattern().unwrap().unwrap(), &lines), vec![Hit { line: 1, start: 2, end: 6 }]);
+        *search.text_mut() = "(".to_string();
+        assert!(search.pattern().is_err());
+    }
+}
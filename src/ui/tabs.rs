@@ -0,0 +1,236 @@
+// Tabs of the browser, each with its own session history
+//
+// ```
+//  1 Example Domain │ 2 IANA │ 3 loading http://exa…
+// ```
+//
+// Tabs are known by an id that stays the same when other tabs are closed, so a page loaded in
+// the background finds its tab (or is dropped if the tab is gone).
+
+use aktis::http::url::URL;
+use aktis::utils::width;
+use super::history::History;
+use super::pager::sanitize;
+
+pub struct Tab {
+    id: usize,
+    history: History,
+    // serial number and URL of the page being loaded
+    loading: Option<(u64, URL)>,
+}
+
+impl Tab {
+    fn new(id: usize) -> Self {
+        Self {
+            id,
+            history: History::new(),
+            loading: None,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    pub fn loading(&self) -> Option<&URL> {
+        self.loading.as_ref().map(|(_, url)| url)
+    }
+
+    // a newer load replaces the one going on
+    pub fn set_loading(&mut self, serial: u64, url: URL) {
+        self.loading = Some((serial, url));
+    }
+
+    // whether the load `serial` was the last one started (it is then over)
+    pub fn finish_loading(&mut self, serial: u64) -> bool {
+        match self.loading {
+            Some((n, _)) if n == serial => {
+                self.loading = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        if let Some(url) = self.loading() {
+            return format!("loading {}", url);
+        }
+        match self.history.current() {
+            Some(entry) => entry.title().map_or_else(|| entry.url().to_string(), |t| t.to_string()),
+            None => "(empty)".to_string(),
+        }
+    }
+}
+
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    current: usize,
+    next_id: usize,
+}
+
+impl Tabs {
+    // one empty tab
+    pub fn new() -> Self {
+        Self {
+            tabs: vec![Tab::new(0)],
+            current: 0,
+            next_id: 1,
+        }
+    }
+
+    pub fn current(&self) -> &Tab {
+        &self.tabs[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.current]
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Tab> {
+        self.tabs.iter_mut().find(|tab| tab.id == id)
+    }
+
+    // index of the current tab
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
+    // index of the tab `id`
+    pub fn index(&self, id: usize) -> Option<usize> {
+        self.tabs.iter().position(|tab| tab.id == id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    // a new empty tab right after the current one; returns its id
+    pub fn open(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.tabs.insert(self.current + 1, Tab::new(id));
+        id
+    }
+
+    pub fn select(&mut self, id: usize) {
+        if let Some(i) = self.index(id) {
+            self.current = i;
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.tabs.len();
+    }
+
+    pub fn prev(&mut self) {
+        self.current = (self.current + self.tabs.len() - 1) % self.tabs.len();
+    }
+
+    // close the current tab and select the next one (the last tab is never closed)
+    pub fn close(&mut self) -> Option<Tab> {
+        if self.tabs.len() == 1 {
+            return None;
+        }
+        let tab = self.tabs.remove(self.current);
+        self.current = self.current.min(self.tabs.len() - 1);
+        Some(tab)
+    }
+
+    // a line of `width` columns naming the tabs, the current one in reverse video
+    pub fn bar(&self, width: usize) -> String {
+        let room = (width / self.tabs.len()).max(6);
+        let mut out = String::new();
+        let mut used = 0;
+        for (i, tab) in self.tabs.iter().enumerate() {
+            // titles come from pages: no escape sequences in the bar
+            let label = format!(" {} {}", i + 1, sanitize(&tab.title()));
+            let label = truncate(&label, room - 2);
+            let cell = format!("{} ", label);
+            let n = width::width(&cell) + 1;
+            if used + n > width {
+                break;
+            }
+            used += n;
+            if i == self.current {
+                out.push_str(&format!("\x1b[7m{}\x1b[m", cell));
+            } else {
+                out.push_str(&cell);
+            }
+            out.push('│');
+        }
+        out
+    }
+}
+
+// `s` cut to `cells` cells, ending with `…` if cut
+fn truncate(s: &str, cells: usize) -> String {
+    if width::width(s) <= cells {
+        return s.to_string();
+    }
+    format!("{}…", width::head(s, cells.saturating_sub(1)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tabs() {
+        let mut tabs = Tabs::new();
+        assert!(tabs.close().is_none());
+        let b = tabs.open();
+        let c = tabs.open();
+        // opened right after the current tab, which stays selected
+        assert_eq!(tabs.index(c), Some(1));
+        assert_eq!(tabs.index(b), Some(2));
+        assert_eq!(tabs.position(), 0);
+
+        tabs.prev();
+        assert_eq!(tabs.current().id(), b);
+        tabs.next();
+        tabs.select(c);
+        assert_eq!(tabs.close().unwrap().id(), c);
+        assert_eq!(tabs.current().id(), b);
+        assert_eq!(tabs.close().unwrap().id(), b);
+        assert_eq!((tabs.len(), tabs.position()), (1, 0));
+        assert!(tabs.get_mut(b).is_none());
+    }
+
+    #[test]
+    fn test_loading() {
+        let mut tabs = Tabs::new();
+        let tab = tabs.current_mut();
+        tab.set_loading(1, URL::parse("http://example.com/a").unwrap());
+        tab.set_loading(2, URL::parse("http://example.com/b").unwrap());
+        // the first load was replaced
+        assert!(!tab.finish_loading(1));
+        assert_eq!(tab.title(), "loading http://example.com/b");
+        assert!(tab.finish_loading(2));
+        assert_eq!(tab.title(), "(empty)");
+
+        tabs.open();
+        assert_eq!(tabs.bar(30), "\x1b[7m 1 (empty) \x1b[m│ 2 (empty) │");
+        assert_eq!(tabs.bar(16), "\x1b[7m 1 (e… \x1b[m│ 2 (e… │");
+    }
+
+    #[test]
+    fn test_bar() {
+        use aktis::renderer::{dom, markdown_generator};
+        use super::super::history::Entry;
+
+        let page = markdown_generator::generate(&dom::parse("<title>x&#27;]0;pwn&#7;</title>"));
+        let mut tabs = Tabs::new();
+        let url = URL::parse("http://example.com/").unwrap();
+        tabs.current_mut().history_mut().visit(Entry::new(url, page));
+        assert_eq!(tabs.bar(30), "\x1b[7m 1 x]0;pwn \x1b[m│");
+    }
+}
@@ -1,2 +1,9 @@
-
+pub mod base64;
+pub mod charset;
 pub mod consumer;
+pub mod md5;
+pub mod pattern;
+pub mod progress;
+pub mod sha256;
+pub mod term;
+pub mod width;
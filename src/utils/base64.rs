@@ -0,0 +1,68 @@
+// Base64 encoding with the standard alphabet and padding (RFC 4648 section 4)
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(src: &[u8]) -> String {
+    let mut result = String::new();
+    for chunk in src.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+// return `None` if `src` is not valid Base64 (whitespace is ignored)
+pub fn decode(src: &str) -> Option<Vec<u8>> {
+    let mut result = vec![];
+    let mut buf: u32 = 0;
+    let mut bits = 0;
+    let mut padding = 0;
+    for c in src.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        if c == b'=' {
+            padding += 1;
+            continue;
+        }
+        if padding > 0 {
+            return None;
+        }
+        let v = ALPHABET.iter().position(|&a| a == c)? as u32;
+        buf = buf << 6 | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buf >> bits) as u8);
+            buf &= (1 << bits) - 1;
+        }
+    }
+    if padding > 2 {
+        return None;
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        // RFC 4648 section 10
+        let vectors = [
+            ("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in vectors.iter() {
+            assert_eq!(encode(plain.as_bytes()), *encoded);
+            assert_eq!(decode(encoded).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(encode(b"Aladdin:open sesame"), "QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+        assert!(decode("Zm9v!").is_none());
+    }
+}
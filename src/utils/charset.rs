@@ -0,0 +1,82 @@
+// Decode bytes into `String` by charset label.
+//
+// Only encodings which do not need big mapping tables are supported:
+// UTF-8, UTF-16LE/BE, US-ASCII and ISO-8859-1 (decoded as windows-1252 like browsers do).
+// Unknown labels fall back to (lossy) UTF-8.
+//
+// ref: https://encoding.spec.whatwg.org/#names-and-labels
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+impl Charset {
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().trim_matches('"').to_ascii_lowercase();
+        match label.as_str() {
+            "utf-8" | "utf8" | "unicode-1-1-utf-8" => Some(Self::Utf8),
+            "utf-16le" | "utf-16" => Some(Self::Utf16Le),
+            "utf-16be" => Some(Self::Utf16Be),
+            "us-ascii" | "ascii" | "iso-8859-1" | "iso8859-1" | "latin1" | "l1"
+            | "windows-1252" | "cp1252" | "x-cp1252" => Some(Self::Windows1252),
+            _ => None,
+        }
+    }
+}
+
+// windows-1252 code points for 0x80..=0x9F
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+// decode `bytes` as `label` (if any).
+// A byte order mark takes precedence over the label.
+pub fn decode(bytes: &[u8], label: Option<&str>) -> String {
+    let (charset, bytes) = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => (Some(Charset::Utf8), rest),
+        [0xFF, 0xFE, rest @ ..] => (Some(Charset::Utf16Le), rest),
+        [0xFE, 0xFF, rest @ ..] => (Some(Charset::Utf16Be), rest),
+        _ => (label.and_then(Charset::from_label), bytes),
+    };
+
+    match charset.unwrap_or(Charset::Utf8) {
+        Charset::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+        Charset::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+        Charset::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+        Charset::Windows1252 => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9F => WINDOWS_1252[(b - 0x80) as usize],
+                _ => b as char,
+            })
+            .collect(),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], f: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| f([pair[0], pair[1]]));
+    std::char::decode_utf16(units)
+        .map(|c| c.unwrap_or(std::char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("こんにちは".as_bytes(), Some("UTF-8")), "こんにちは");
+        assert_eq!(decode(b"caf\xe9 \x80", Some("ISO-8859-1")), "café €");
+        assert_eq!(decode(b"\xff\xfea\x00b\x00", Some("iso-8859-1")), "ab");
+        assert_eq!(decode(b"\x00a\x00b", Some("utf-16be")), "ab");
+        assert_eq!(decode(b"abc\xff", Some("x-unknown")), "abc\u{FFFD}");
+    }
+}
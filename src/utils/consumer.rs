@@ -159,6 +159,13 @@ impl Consumer {
         return Some(result);
     }
     
+    // return the rest of the string
+    pub fn rest(&mut self) -> String {
+        let res = self.queue[self.pos..].iter().collect::<String>();
+        self.pos = self.queue.len();
+        res
+    }
+
     // skip white spaces
     pub fn skip_space(&mut self) {
         loop {
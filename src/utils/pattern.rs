@@ -0,0 +1,482 @@
+use std::error::Error;
+use std::fmt;
+
+// ERROR HANDLING ----------------------
+#[derive(Debug, PartialEq, Eq)]
+pub enum PatternError {
+    Unclosed(char),
+    Unmatched(char),
+    NothingToRepeat(char),
+    TrailingBackslash,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unclosed(c) => write!(f, "Pattern error: `{}` is not closed", c),
+            Self::Unmatched(c) => write!(f, "Pattern error: unmatched `{}`", c),
+            Self::NothingToRepeat(c) => write!(f, "Pattern error: nothing to repeat before `{}`", c),
+            Self::TrailingBackslash => write!(f, "Pattern error: `\\` at the end"),
+        }
+    }
+}
+
+impl Error for PatternError {}
+// ------------------------------------
+
+// Text to search for: literal text, or a small regular expression
+//
+// ```
+// .        any character           [abc] [a-z] [^0-9]    character classes
+// * + ?    repeat the one before   \d \w \s              digit, word character, space (\D \W \S: others)
+// ^ $      start, end of the text  a|b (ab)              alternatives, group
+// \.       `.` itself
+// ```
+//
+// Matching works on characters, so positions are character indices (not bytes) and a match never
+// splits a multi-byte character. Without case, characters are compared in lower case.
+//
+// The pattern is compiled to a program and run as a Pike VM: every way the match can go on is kept
+// as a thread, and all threads advance one character at a time, so a search takes time linear in
+// the text whatever the pattern (`(a*)*b` included). Threads are kept in the order a backtracking
+// matcher would try them, which gives the same matches: leftmost, then greedy.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    program: Vec<Inst>,
+    ignore_case: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Char(char),
+    Any,
+    // items, negated
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    // node, least and most times
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            Self::Range(a, b) => a <= c && c <= b,
+            Self::Digit(yes) => c.is_ascii_digit() == yes,
+            Self::Word(yes) => (c.is_alphanumeric() || c == '_') == yes,
+            Self::Space(yes) => c.is_whitespace() == yes,
+        }
+    }
+}
+
+// an instruction of the compiled pattern
+//
+// ```
+// a|b*     0: Split(1, 3)  1: Char(a)  2: Jump(6)  3: Split(4, 6)  4: Char(b)  5: Jump(3)  6: Match
+// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inst {
+    // one character matching the node (`Char`, `Any` or `Class`)
+    Char(Node),
+    Start,
+    End,
+    // go on at both, the first before the second
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+impl Pattern {
+    // `text` as is
+    pub fn literal(text: &str, ignore_case: bool) -> Self {
+        Self::compile(&[text.chars().map(Node::Char).collect()], ignore_case)
+    }
+
+    pub fn regex(src: &str, ignore_case: bool) -> Result<Self, PatternError> {
+        let mut parser = Parser { chars: src.chars().collect(), pos: 0 };
+        let alt = parser.alt()?;
+        match parser.peek() {
+            Some(c) => Err(PatternError::Unmatched(c)),
+            None => Ok(Self::compile(&alt, ignore_case)),
+        }
+    }
+
+    fn compile(alt: &[Vec<Node>], ignore_case: bool) -> Self {
+        let mut program = vec![];
+        emit_alt(&mut program, alt);
+        program.push(Inst::Match);
+        Self { program, ignore_case }
+    }
+
+    // character ranges of the matches in `text`, from the left and not overlapping
+    // (empty matches are skipped)
+    pub fn find_all(&self, text: &str) -> Vec<(usize, usize)> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut found = vec![];
+        let mut from = 0;
+        while let Some((start, end)) = self.find_at(&chars, from) {
+            found.push((start, end));
+            from = end;
+        }
+        found
+    }
+
+    // the first match starting at `from` or later that is not empty
+    fn find_at(&self, text: &[char], from: usize) -> Option<(usize, usize)> {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut found = None;
+        for pos in from..=text.len() {
+            // a match starting here comes after those started before
+            if found.is_none() && pos < text.len() {
+                self.add(&mut current, 0, pos, pos, text);
+            }
+            if current.list.is_empty() {
+                break;
+            }
+            for &(pc, start) in &current.list {
+                match &self.program[pc] {
+                    Inst::Char(node) if pos < text.len() && self.match_char(node, text[pos]) => {
+                        self.add(&mut next, pc + 1, pos + 1, start, text);
+                    }
+                    // the threads after this one would only give matches less preferred
+                    Inst::Match if start < pos => {
+                        found = Some((start, pos));
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        found
+    }
+
+    // add the thread at `pc`, following jumps and assertions to the instructions reading a
+    // character (or matching)
+    fn add(&self, threads: &mut Threads, pc: usize, pos: usize, start: usize, text: &[char]) {
+        if threads.seen[pc] {
+            return;
+        }
+        threads.seen[pc] = true;
+        match self.program[pc] {
+            Inst::Split(first, second) => {
+                self.add(threads, first, pos, start, text);
+                self.add(threads, second, pos, start, text);
+            }
+            Inst::Jump(to) => self.add(threads, to, pos, start, text),
+            Inst::Start if pos == 0 => self.add(threads, pc + 1, pos, start, text),
+            Inst::End if pos == text.len() => self.add(threads, pc + 1, pos, start, text),
+            Inst::Start | Inst::End => {}
+            Inst::Char(_) | Inst::Match => threads.list.push((pc, start)),
+        }
+    }
+
+    fn match_char(&self, node: &Node, c: char) -> bool {
+        match node {
+            Node::Any => true,
+            Node::Char(p) if self.ignore_case => lower(*p) == lower(c),
+            Node::Char(p) => *p == c,
+            Node::Class(items, negated) => {
+                let found = if self.ignore_case {
+                    items.iter().any(|i| i.matches(c) || i.matches(lower(c)) || i.matches(upper(c)))
+                } else {
+                    items.iter().any(|i| i.matches(c))
+                };
+                found != *negated
+            }
+            _ => false,
+        }
+    }
+}
+
+// threads at one position: where they are in the program and where their match started, in the
+// order they are preferred
+struct Threads {
+    list: Vec<(usize, usize)>,
+    // instructions already reached, by a thread preferred to any later one
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            list: vec![],
+            seen: vec![false; len],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.seen.iter_mut().for_each(|s| *s = false);
+    }
+}
+
+// the alternatives in order, each jumping to the end
+fn emit_alt(program: &mut Vec<Inst>, alt: &[Vec<Node>]) {
+    let mut jumps = vec![];
+    for (i, seq) in alt.iter().enumerate() {
+        if i + 1 < alt.len() {
+            let split = program.len();
+            program.push(Inst::Split(split + 1, 0));
+            emit_seq(program, seq);
+            jumps.push(program.len());
+            program.push(Inst::Jump(0));
+            program[split] = Inst::Split(split + 1, program.len());
+        } else {
+            emit_seq(program, seq);
+        }
+    }
+    for jump in jumps {
+        program[jump] = Inst::Jump(program.len());
+    }
+}
+
+fn emit_seq(program: &mut Vec<Inst>, seq: &[Node]) {
+    for node in seq {
+        emit_node(program, node);
+    }
+}
+
+fn emit_node(program: &mut Vec<Inst>, node: &Node) {
+    match node {
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Group(alt) => emit_alt(program, alt),
+        Node::Repeat(node, min, max) => {
+            for _ in 0..*min {
+                emit_node(program, node);
+            }
+            match max {
+                // as many times as possible first: `Split(node, out) node Jump(split)`
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    emit_node(program, node);
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                // each more time optional: `Split(node, out) node Split(node, out) node ...`
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(program.len() + 1, 0));
+                        emit_node(program, node);
+                    }
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, program.len());
+                    }
+                }
+            }
+        }
+        node => program.push(Inst::Char(node.clone())),
+    }
+}
+
+// one character for one (`ß` stays `ß` in upper case)
+fn lower(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+fn upper(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    // seq ('|' seq)*
+    fn alt(&mut self) -> Result<Vec<Vec<Node>>, PatternError> {
+        let mut alt = vec![self.seq()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alt.push(self.seq()?);
+        }
+        Ok(alt)
+    }
+
+    // (atom ('*' | '+' | '?')*)*
+    fn seq(&mut self) -> Result<Vec<Node>, PatternError> {
+        let mut seq: Vec<Node> = vec![];
+        while let Some(c) = self.peek() {
+            let node = match c {
+                '|' | ')' => break,
+                '*' | '+' | '?' => {
+                    self.pos += 1;
+                    let node = match seq.pop() {
+                        Some(Node::Start) | Some(Node::End) | None => return Err(PatternError::NothingToRepeat(c)),
+                        Some(node) => node,
+                    };
+                    let (min, max) = match c {
+                        '*' => (0, None),
+                        '+' => (1, None),
+                        _ => (0, Some(1)),
+                    };
+                    Node::Repeat(Box::new(node), min, max)
+                }
+                _ => {
+                    self.pos += 1;
+                    self.atom(c)?
+                }
+            };
+            seq.push(node);
+        }
+        Ok(seq)
+    }
+
+    // after `c`
+    fn atom(&mut self, c: char) -> Result<Node, PatternError> {
+        match c {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '(' => {
+                let alt = self.alt()?;
+                match self.next() {
+                    Some(')') => Ok(Node::Group(alt)),
+                    _ => Err(PatternError::Unclosed('(')),
+                }
+            }
+            '[' => self.class(),
+            '\\' => match self.escape()? {
+                ClassItem::Range(a, _) => Ok(Node::Char(a)),
+                item => Ok(Node::Class(vec![item], false)),
+            },
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    // after `\`
+    fn escape(&mut self) -> Result<ClassItem, PatternError> {
+        let c = self.next().ok_or(PatternError::TrailingBackslash)?;
+        Ok(match c {
+            'd' | 'D' => ClassItem::Digit(c == 'd'),
+            'w' | 'W' => ClassItem::Word(c == 'w'),
+            's' | 'S' => ClassItem::Space(c == 's'),
+            't' => ClassItem::Range('\t', '\t'),
+            c => ClassItem::Range(c, c),
+        })
+    }
+
+    // after `[`; `]` first and `-` at the ends are themselves
+    fn class(&mut self) -> Result<Node, PatternError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = vec![];
+        loop {
+            let c = self.next().ok_or(PatternError::Unclosed('['))?;
+            let item = match c {
+                ']' if !items.is_empty() => return Ok(Node::Class(items, negated)),
+                '\\' => self.escape()?,
+                c => ClassItem::Range(c, c),
+            };
+            let item = match item {
+                ClassItem::Range(a, _) if self.peek() == Some('-') && !matches!(self.chars.get(self.pos + 1), Some(']') | None) => {
+                    self.pos += 1;
+                    let b = match self.next() {
+                        Some('\\') => match self.escape()? {
+                            ClassItem::Range(b, _) => b,
+                            _ => return Err(PatternError::Unmatched('-')),
+                        },
+                        Some(b) => b,
+                        None => return Err(PatternError::Unclosed('[')),
+                    };
+                    ClassItem::Range(a, b)
+                }
+                item => item,
+            };
+            items.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Vec<(usize, usize)> {
+        Pattern::regex(pattern, false).unwrap().find_all(text)
+    }
+
+    #[test]
+    fn test_literal() {
+        let pattern = Pattern::literal("a.b", true);
+        assert_eq!(pattern.find_all("A.B axb a.ba.b"), vec![(0, 3), (8, 11), (11, 14)]);
+        assert_eq!(Pattern::literal("a.b", false).find_all("A.B"), vec![]);
+        // character indices
+        assert_eq!(Pattern::literal("日本", false).find_all("これは日本語の日本"), vec![(3, 5), (7, 9)]);
+        assert_eq!(Pattern::literal("ÉTÉ", true).find_all("un été"), vec![(3, 6)]);
+        assert_eq!(Pattern::literal("", true).find_all("abc"), vec![]);
+    }
+
+    #[test]
+    fn test_regex() {
+        assert_eq!(find("colou?r", "color colour colouur"), vec![(0, 5), (6, 12)]);
+        assert_eq!(find("a+", "baaac a"), vec![(1, 4), (6, 7)]);
+        assert_eq!(find("^ab|c$", "abcabc"), vec![(0, 2), (5, 6)]);
+        assert_eq!(find("(ab)+c", "abababc"), vec![(0, 7)]);
+        assert_eq!(find("[a-c]+[^a-c]", "xabcd"), vec![(1, 5)]);
+        assert_eq!(find("[]a-]+", "x]-a"), vec![(1, 4)]);
+        assert_eq!(find("\\d+\\.\\d", "v1.25"), vec![(1, 4)]);
+        assert_eq!(find("\\w+", "日本 語"), vec![(0, 2), (3, 4)]);
+        assert_eq!(find("x*", "abc"), vec![]);
+        assert_eq!(find("(a*)*b", "aab"), vec![(0, 3)]);
+        assert_eq!(Pattern::regex("[A-Z]\\w", true).unwrap().find_all("xy"), vec![(0, 2)]);
+    }
+
+    // no backtracking: these took exponential (or cubic) time
+    #[test]
+    fn test_linear() {
+        let started = std::time::Instant::now();
+        let a = "a".repeat(5000);
+        assert_eq!(find("(a*)*b", &a), vec![]);
+        assert_eq!(find("(a*)*b", &format!("{}b", a)), vec![(0, 5001)]);
+        assert_eq!(find("(\\w+\\s?)*$", &format!("{}!", a)), vec![]);
+        assert_eq!(find(".*.*.*x", &"y".repeat(2000)), vec![]);
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Pattern::regex("(ab", false), Err(PatternError::Unclosed('(')));
+        assert_eq!(Pattern::regex("ab)", false), Err(PatternError::Unmatched(')')));
+        assert_eq!(Pattern::regex("[ab", false), Err(PatternError::Unclosed('[')));
+        assert_eq!(Pattern::regex("*a", false), Err(PatternError::NothingToRepeat('*')));
+        assert_eq!(Pattern::regex("a\\", false), Err(PatternError::TrailingBackslash));
+    }
+}
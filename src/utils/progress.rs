@@ -0,0 +1,146 @@
+// A one-line progress bar redrawn in place with `\r`
+//
+// ```
+// [=========>          ]  45%  1.2 MiB / 2.6 MiB  310.4 KiB/s  ETA 0:05
+// ```
+//
+// Without the total size only the amount and the rate are shown.
+// Bytes received before `start` (resumed downloads) do not count towards the rate.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 20;
+// redraw at most this often
+const INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct ProgressBar<W: Write> {
+    out: W,
+    start: Instant,
+    offset: u64,
+    last_draw: Option<Instant>,
+}
+
+impl<W: Write> ProgressBar<W> {
+    // `offset`: bytes already there when the transfer starts
+    pub fn new(out: W, offset: u64) -> Self {
+        Self {
+            out,
+            start: Instant::now(),
+            offset,
+            last_draw: None,
+        }
+    }
+
+    pub fn update(&mut self, received: u64, total: Option<u64>) -> io::Result<()> {
+        let now = Instant::now();
+        let done = total == Some(received);
+        if !done && self.last_draw.is_some_and(|last| now - last < INTERVAL) {
+            return Ok(());
+        }
+        self.last_draw = Some(now);
+        let line = render(received.saturating_sub(self.offset), received, total, now - self.start);
+        // `\x1b[K` clears the rest of a longer previous line
+        write!(self.out, "\r{}\x1b[K", line)?;
+        self.out.flush()
+    }
+
+    // move to the next line, leaving the last state visible
+    pub fn finish(&mut self) -> io::Result<()> {
+        writeln!(self.out)
+    }
+}
+
+// `transferred` bytes in `elapsed` (for the rate), `received` of `total` in all
+pub fn render(transferred: u64, received: u64, total: Option<u64>, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 { transferred as f64 / secs } else { 0.0 };
+    let rate = format!("{}/s", format_bytes(rate as u64));
+
+    let total = match total {
+        Some(total) if total > 0 => total,
+        _ => return format!("{}  {}", format_bytes(received), rate),
+    };
+    let ratio = (received as f64 / total as f64).min(1.0);
+    let filled = (ratio * BAR_WIDTH as f64) as usize;
+    let bar = if filled >= BAR_WIDTH {
+        "=".repeat(BAR_WIDTH)
+    } else {
+        format!("{}>{}", "=".repeat(filled), " ".repeat(BAR_WIDTH - filled - 1))
+    };
+    let eta = if received >= total {
+        "done".to_string()
+    } else if transferred == 0 || secs == 0.0 {
+        "ETA --:--".to_string()
+    } else {
+        let left = (total - received) as f64 / (transferred as f64 / secs);
+        format!("ETA {}", format_duration(Duration::from_secs_f64(left)))
+    };
+    format!(
+        "[{}] {:>3}%  {} / {}  {}  {}",
+        bar,
+        (ratio * 100.0) as u64,
+        format_bytes(received),
+        format_bytes(total),
+        rate,
+        eta
+    )
+}
+
+// `1536` -> `1.5 KiB`
+pub fn format_bytes(n: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if n < 1024 {
+        return format!("{} B", n);
+    }
+    let mut value = n as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+// `0:05`, `12:34` or `1:02:03`
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(format_bytes(1000), "1000 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 << 30), "5.0 GiB");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+
+        let second = Duration::from_secs(1);
+        assert_eq!(
+            render(1024, 1024, Some(4096), second),
+            "[=====>              ]  25%  1.0 KiB / 4.0 KiB  1.0 KiB/s  ETA 0:03"
+        );
+        assert_eq!(render(4096, 4096, Some(4096), second), "[====================] 100%  4.0 KiB / 4.0 KiB  4.0 KiB/s  done");
+        assert_eq!(render(10, 10, None, second), "10 B  10 B/s");
+
+        let mut out = vec![];
+        let mut bar = ProgressBar::new(&mut out, 0);
+        bar.update(5, Some(10)).unwrap();
+        // too soon to redraw, but the end is always drawn
+        bar.update(6, Some(10)).unwrap();
+        bar.update(10, Some(10)).unwrap();
+        bar.finish().unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches('\r').count(), 2);
+        assert!(out.ends_with("done\x1b[K\n"));
+    }
+}
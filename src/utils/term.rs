@@ -0,0 +1,343 @@
+// Terminal helpers over termios(3)
+//
+// The termios layout differs between platforms; Linux and the BSDs (including macOS) are supported.
+// On other platforms the terminal is left as it is.
+//
+// Full-screen programs put the terminal into raw mode and decode key presses from the bytes read:
+// ```
+// let _raw = raw_mode(STDIN)?;
+// let mut keys = KeyReader::new(io::stdin());
+// while keys.read_key()? != Key::Char('q') {}
+// ```
+
+use std::io::{self, BufRead, Read, Write};
+
+pub const STDIN: i32 = 0;
+pub const STDOUT: i32 = 1;
+pub const STDERR: i32 = 2;
+
+#[cfg(unix)]
+mod sys {
+    #[cfg(target_os = "linux")]
+    pub type Flag = u32;
+    #[cfg(not(target_os = "linux"))]
+    pub type Flag = std::os::raw::c_ulong;
+
+    #[cfg(target_os = "linux")]
+    const NCCS: usize = 32;
+    #[cfg(not(target_os = "linux"))]
+    const NCCS: usize = 20;
+
+    pub const ECHO: Flag = 0o10;
+    pub const TCSANOW: i32 = 0;
+
+    #[cfg(target_os = "linux")]
+    mod flags {
+        use super::Flag;
+        pub const ISIG: Flag = 0o1;
+        pub const ICANON: Flag = 0o2;
+        pub const IEXTEN: Flag = 0o100000;
+        pub const ICRNL: Flag = 0o400;
+        pub const IXON: Flag = 0o2000;
+        pub const VTIME: usize = 5;
+        pub const VMIN: usize = 6;
+        pub const TIOCGWINSZ: std::os::raw::c_ulong = 0x5413;
+    }
+    #[cfg(not(target_os = "linux"))]
+    mod flags {
+        use super::Flag;
+        pub const ISIG: Flag = 0x80;
+        pub const ICANON: Flag = 0x100;
+        pub const IEXTEN: Flag = 0x400;
+        pub const ICRNL: Flag = 0x100;
+        pub const IXON: Flag = 0x200;
+        pub const VMIN: usize = 16;
+        pub const VTIME: usize = 17;
+        pub const TIOCGWINSZ: std::os::raw::c_ulong = 0x40087468;
+    }
+    pub use flags::*;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct Termios {
+        pub c_iflag: Flag,
+        pub c_oflag: Flag,
+        pub c_cflag: Flag,
+        pub c_lflag: Flag,
+        #[cfg(target_os = "linux")]
+        pub c_line: u8,
+        pub c_cc: [u8; NCCS],
+        pub c_ispeed: Flag,
+        pub c_ospeed: Flag,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct Winsize {
+        pub ws_row: u16,
+        pub ws_col: u16,
+        pub ws_xpixel: u16,
+        pub ws_ypixel: u16,
+    }
+
+    extern "C" {
+        pub fn isatty(fd: i32) -> i32;
+        pub fn ioctl(fd: i32, request: std::os::raw::c_ulong, ...) -> i32;
+        pub fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        pub fn tcsetattr(fd: i32, optional_actions: i32, termios: *const Termios) -> i32;
+    }
+}
+
+// whether `fd` refers to a terminal
+pub fn is_tty(fd: i32) -> bool {
+    #[cfg(unix)]
+    unsafe {
+        sys::isatty(fd) == 1
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = fd;
+        false
+    }
+}
+
+// restores the saved terminal attributes on drop
+#[cfg(unix)]
+struct Restore(i32, sys::Termios);
+
+#[cfg(unix)]
+impl Drop for Restore {
+    fn drop(&mut self) {
+        unsafe {
+            sys::tcsetattr(self.0, sys::TCSANOW, &self.1);
+        }
+    }
+}
+
+// change the attributes of `fd` with `f` until the returned guard is dropped
+#[cfg(unix)]
+fn change(fd: i32, f: impl FnOnce(&mut sys::Termios)) -> io::Result<Restore> {
+    let mut termios = std::mem::MaybeUninit::<sys::Termios>::uninit();
+    let saved = unsafe {
+        if sys::tcgetattr(fd, termios.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        termios.assume_init()
+    };
+    let mut changed = saved;
+    f(&mut changed);
+    if unsafe { sys::tcsetattr(fd, sys::TCSANOW, &changed) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Restore(fd, saved))
+}
+
+// turn off echo of `fd` until the returned guard is dropped
+#[cfg(unix)]
+fn no_echo(fd: i32) -> io::Result<Restore> {
+    change(fd, |t| t.c_lflag &= !sys::ECHO)
+}
+
+// the terminal is in raw mode while this is alive
+pub struct RawMode {
+    #[cfg(unix)]
+    _restore: Restore,
+}
+
+// read key presses one by one, without echo, line editing or signals (Ctrl-C is a key)
+pub fn raw_mode(fd: i32) -> io::Result<RawMode> {
+    #[cfg(unix)]
+    {
+        let restore = change(fd, |t| {
+            t.c_lflag &= !(sys::ECHO | sys::ICANON | sys::ISIG | sys::IEXTEN);
+            t.c_iflag &= !(sys::ICRNL | sys::IXON);
+            // block until a byte is there
+            t.c_cc[sys::VMIN] = 1;
+            t.c_cc[sys::VTIME] = 0;
+        })?;
+        Ok(RawMode { _restore: restore })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = fd;
+        Err(io::Error::new(io::ErrorKind::Other, "raw mode is not supported"))
+    }
+}
+
+// (columns, rows) of the terminal: from stdout, `$COLUMNS` and `$LINES`, or 80x24
+pub fn size() -> (usize, usize) {
+    #[cfg(unix)]
+    {
+        let mut ws = sys::Winsize::default();
+        if unsafe { sys::ioctl(STDOUT, sys::TIOCGWINSZ, &mut ws as *mut sys::Winsize) } == 0 && ws.ws_col > 0 && ws.ws_row > 0 {
+            return (ws.ws_col as usize, ws.ws_row as usize);
+        }
+    }
+    let env = |name: &str, default: usize| {
+        std::env::var(name).ok().and_then(|v| v.parse().ok()).filter(|&n| n > 0).unwrap_or(default)
+    };
+    (env("COLUMNS", 80), env("LINES", 24))
+}
+
+// print `prompt` to stderr and read a line from stdin (without the line break)
+pub fn read_line(prompt: &str) -> io::Result<String> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+// like `read_line`, but the input is not echoed if stdin is a terminal
+pub fn read_password(prompt: &str) -> io::Result<String> {
+    let tty = is_tty(STDIN);
+    #[cfg(unix)]
+    let _restore = if tty { Some(no_echo(STDIN)?) } else { None };
+    let password = read_line(prompt);
+    if tty {
+        // the line break typed by the user was not echoed either
+        eprintln!();
+    }
+    password
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    // Ctrl + a letter (lowercase)
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Delete,
+    // an escape sequence not listed above
+    Unknown,
+}
+
+// decode the first key in `bytes`: the key and the number of bytes it takes
+// (`None` if a character is cut off and more bytes are needed)
+//
+// ```
+// \x1b[A  \x1bOA  up           \x1b[5~  page up
+// \x1b[H  \x1b[1~ home         \x1b[6~  page down
+// ```
+pub fn parse_key(bytes: &[u8]) -> Option<(Key, usize)> {
+    let first = *bytes.first()?;
+    let key = match first {
+        b'\x1b' => return Some(parse_escape(bytes)),
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x01..=0x1a => Key::Ctrl((b'a' + first - 1) as char),
+        0x00..=0x1f => Key::Unknown,
+        _ => {
+            let len = match first {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            if bytes.len() < len {
+                return None;
+            }
+            return match std::str::from_utf8(&bytes[..len]).ok().and_then(|s| s.chars().next()) {
+                Some(c) => Some((Key::Char(c), len)),
+                None => Some((Key::Unknown, 1)),
+            };
+        }
+    };
+    Some((key, 1))
+}
+
+// `\x1b` alone is the escape key; `\x1b[` (CSI) and `\x1bO` (SS3) start a sequence
+fn parse_escape(bytes: &[u8]) -> (Key, usize) {
+    match bytes.get(1) {
+        Some(b'[') | Some(b'O') => {}
+        _ => return (Key::Escape, 1),
+    }
+    // parameters, then the final byte
+    let end = match bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+        Some(i) => i + 2,
+        None => return (Key::Escape, 1),
+    };
+    let key = match (&bytes[2..end], bytes[end]) {
+        (_, b'A') => Key::Up,
+        (_, b'B') => Key::Down,
+        (_, b'C') => Key::Right,
+        (_, b'D') => Key::Left,
+        (_, b'H') | (b"1", b'~') | (b"7", b'~') => Key::Home,
+        (_, b'F') | (b"4", b'~') | (b"8", b'~') => Key::End,
+        (b"3", b'~') => Key::Delete,
+        (b"5", b'~') => Key::PageUp,
+        (b"6", b'~') => Key::PageDown,
+        _ => Key::Unknown,
+    };
+    (key, end + 1)
+}
+
+// decodes key presses from a terminal in raw mode
+pub struct KeyReader<R: Read> {
+    input: R,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> KeyReader<R> {
+    pub fn new(input: R) -> Self {
+        Self { input, buf: vec![] }
+    }
+
+    // wait for the next key
+    pub fn read_key(&mut self) -> io::Result<Key> {
+        loop {
+            if let Some((key, n)) = parse_key(&self.buf) {
+                self.buf.drain(..n);
+                return Ok(key);
+            }
+            let mut chunk = [0; 64];
+            let n = self.input.read(&mut chunk)?;
+            if n == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(parse_key(b"j"), Some((Key::Char('j'), 1)));
+        assert_eq!(parse_key(b"\r"), Some((Key::Enter, 1)));
+        assert_eq!(parse_key(b"\x03"), Some((Key::Ctrl('c'), 1)));
+        assert_eq!(parse_key(b"\x7f"), Some((Key::Backspace, 1)));
+        assert_eq!(parse_key(b"\x1b"), Some((Key::Escape, 1)));
+        assert_eq!(parse_key(b"\x1b[A"), Some((Key::Up, 3)));
+        assert_eq!(parse_key(b"\x1bOB"), Some((Key::Down, 3)));
+        assert_eq!(parse_key(b"\x1b[6~j"), Some((Key::PageDown, 4)));
+        assert_eq!(parse_key(b"\x1b[1;5C"), Some((Key::Right, 6)));
+        assert_eq!(parse_key(b"\x1b[200~"), Some((Key::Unknown, 6)));
+        assert_eq!(parse_key("あ".as_bytes()), Some((Key::Char('あ'), 3)));
+        // the rest of the character has not arrived yet
+        assert_eq!(parse_key(&"あ".as_bytes()[..2]), None);
+        assert_eq!(parse_key(b""), None);
+
+        let mut keys = KeyReader::new(&b"12\r\x1b[5~q"[..]);
+        let mut read = vec![];
+        while let Ok(key) = keys.read_key() {
+            read.push(key);
+        }
+        assert_eq!(read, vec![Key::Char('1'), Key::Char('2'), Key::Enter, Key::PageUp, Key::Char('q')]);
+    }
+}
@@ -0,0 +1,216 @@
+// Width of text on a terminal, in cells
+//
+// Wide (W) and fullwidth (F) characters of Unicode East Asian Width take two cells, combining
+// marks and other zero width characters none, and the rest one (ambiguous ones included, as most
+// terminals show them).
+// ref: https://www.unicode.org/reports/tr11/
+
+// wide and fullwidth ranges (EastAsianWidth.txt, grouped where the gaps are unassigned)
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F3),
+    (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA),
+    (0x26FD, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274C),
+    (0x274E, 0x274E),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B50),
+    (0x2B55, 0x2B55),
+    // CJK radicals, punctuation, kana, Hangul compatibility jamo, CJK compatibility ...
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    // CJK unified ideographs (extension A and the main block), Yi
+    (0x3400, 0x4DBF),
+    (0x4E00, 0xA4CF),
+    (0xA960, 0xA97F),
+    // Hangul syllables
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE10, 0xFE19),
+    (0xFE30, 0xFE6F),
+    // fullwidth forms
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x16FE0, 0x16FE4),
+    (0x16FF0, 0x16FF1),
+    // Tangut, Khitan, kana supplement and extensions
+    (0x17000, 0x18CFF),
+    (0x18D00, 0x18D08),
+    (0x1AFF0, 0x1B2FF),
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A),
+    (0x1F200, 0x1F265),
+    // emoji
+    (0x1F300, 0x1F320),
+    (0x1F32D, 0x1F335),
+    (0x1F337, 0x1F37C),
+    (0x1F37E, 0x1F393),
+    (0x1F3A0, 0x1F3CA),
+    (0x1F3CF, 0x1F3D3),
+    (0x1F3E0, 0x1F3F0),
+    (0x1F3F4, 0x1F3F4),
+    (0x1F3F8, 0x1F43E),
+    (0x1F440, 0x1F440),
+    (0x1F442, 0x1F4FC),
+    (0x1F4FF, 0x1F53D),
+    (0x1F54B, 0x1F54E),
+    (0x1F550, 0x1F567),
+    (0x1F57A, 0x1F57A),
+    (0x1F595, 0x1F596),
+    (0x1F5A4, 0x1F5A4),
+    (0x1F5FB, 0x1F64F),
+    (0x1F680, 0x1F6C5),
+    (0x1F6CC, 0x1F6CC),
+    (0x1F6D0, 0x1F6D2),
+    (0x1F6D5, 0x1F6D7),
+    (0x1F6DC, 0x1F6DF),
+    (0x1F6EB, 0x1F6EC),
+    (0x1F6F4, 0x1F6FC),
+    (0x1F7E0, 0x1F7EB),
+    (0x1F7F0, 0x1F7F0),
+    (0x1F90C, 0x1F93A),
+    (0x1F93C, 0x1F945),
+    (0x1F947, 0x1F9FF),
+    (0x1FA70, 0x1FAFF),
+    // CJK unified ideographs extensions B and later
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+];
+
+// combining marks, joiners and variation selectors
+const ZERO: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200B, 0x200F),
+    (0x2060, 0x2064),
+    (0x20D0, 0x20FF),
+    (0x302A, 0x302D),
+    (0x3099, 0x309A),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+    (0xFEFF, 0xFEFF),
+    (0x1F3FB, 0x1F3FF),
+    (0xE0000, 0xE0FFF),
+];
+
+fn within(table: &[(u32, u32)], c: char) -> bool {
+    let c = c as u32;
+    table
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+// cells taken by `c`
+pub fn char_width(c: char) -> usize {
+    if c.is_ascii() {
+        return if c.is_ascii_control() { 0 } else { 1 };
+    }
+    if c.is_control() || within(ZERO, c) {
+        0
+    } else if within(WIDE, c) {
+        2
+    } else {
+        1
+    }
+}
+
+// whether `c` takes two cells
+pub fn is_wide(c: char) -> bool {
+    char_width(c) == 2
+}
+
+// cells taken by `s`
+pub fn width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+// the longest start of `s` within `cells` cells
+pub fn head(s: &str, cells: usize) -> &str {
+    let mut used = 0;
+    for (i, c) in s.char_indices() {
+        used += char_width(c);
+        if used > cells {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+// the longest end of `s` within `cells` cells
+pub fn tail(s: &str, cells: usize) -> &str {
+    let mut used = 0;
+    for (i, c) in s.char_indices().rev() {
+        used += char_width(c);
+        if used > cells {
+            return &s[i + c.len_utf8()..];
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_width() {
+        assert_eq!(width("abc"), 3);
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(width("ｱｲｳ"), 3);
+        assert_eq!(width("ＡＢ。、"), 8);
+        assert_eq!(width("한국어"), 6);
+        assert_eq!(width("e\u{301}"), 1);
+        assert_eq!(width("👍🏽"), 2);
+        assert_eq!(width("α→"), 2);
+        assert_eq!(width("𠮷"), 2);
+
+        assert_eq!(head("日本語", 5), "日本");
+        assert_eq!(head("ab", 5), "ab");
+        assert_eq!(tail("日本語", 5), "本語");
+        assert_eq!(tail("日本語", 1), "");
+    }
+}